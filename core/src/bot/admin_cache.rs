@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::warn;
+use teloxide::prelude::*;
+use teloxide::types::{ChatMemberKind, ChatMemberUpdated, UserId};
+
+use super::{AppState, HandlerResult};
+
+const DEFAULT_TTL_SECS: u64 = 300;
+
+/// 채팅마다 봇이 메시지를 지울 수 있는지 잠시 기억합니다.
+/// `my_chat_member` 업데이트가 오면 바로 갱신합니다.
+#[derive(Clone)]
+pub(crate) struct AdminCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<ChatId, (Instant, bool)>>>,
+}
+
+impl AdminCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// `PLANABOT_ADMIN_CACHE_SECS` (기본 300초)
+    pub(crate) fn from_env() -> Self {
+        let secs = std::env::var("PLANABOT_ADMIN_CACHE_SECS")
+            .ok()
+            .and_then(|raw| raw.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+        Self::new(Duration::from_secs(secs))
+    }
+
+    /// 봇이 이 채팅에서 메시지를 지울 수 있는지. 확인하지 못하면 `false`이고 기억하지 않습니다.
+    pub(crate) async fn can_delete<B>(&self, bot: &B, chat_id: ChatId, bot_id: UserId) -> bool
+    where
+        B: Requester + ?Sized,
+        B::Err: std::fmt::Debug,
+    {
+        if let Some(can_delete) = self.cached(chat_id) {
+            return can_delete;
+        }
+        match bot.get_chat_member(chat_id, bot_id).await {
+            Ok(member) => {
+                let can_delete = can_delete_messages(&member.kind);
+                self.set(chat_id, can_delete);
+                can_delete
+            }
+            Err(err) => {
+                warn!(
+                    "관리자 권한 확인 중 오류 발생 (chat {}): {:?}",
+                    chat_id, err
+                );
+                false
+            }
+        }
+    }
+
+    pub(crate) fn set(&self, chat_id: ChatId, can_delete: bool) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(chat_id, (Instant::now(), can_delete));
+        }
+    }
+
+    fn cached(&self, chat_id: ChatId) -> Option<bool> {
+        let entries = self.entries.lock().ok()?;
+        let (at, can_delete) = entries.get(&chat_id)?;
+        (at.elapsed() < self.ttl).then_some(*can_delete)
+    }
+}
+
+/// 봇의 권한이 바뀌면(`my_chat_member`) 캐시를 바로 갱신합니다.
+pub(crate) async fn handle_my_chat_member(
+    update: ChatMemberUpdated,
+    state: AppState,
+) -> HandlerResult {
+    state.admin_cache.set(
+        update.chat.id,
+        can_delete_messages(&update.new_chat_member.kind),
+    );
+    Ok(())
+}
+
+/// 관리자라도 메시지 삭제 권한이 없으면 원본을 지우고 다시 올릴 수 없습니다.
+pub(crate) fn can_delete_messages(kind: &ChatMemberKind) -> bool {
+    kind.is_privileged() && kind.can_delete_messages()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_status_expires() {
+        let cache = AdminCache::new(Duration::from_secs(60));
+        cache.set(ChatId(1), true);
+        assert_eq!(cache.cached(ChatId(1)), Some(true));
+        assert_eq!(cache.cached(ChatId(2)), None);
+
+        let expired = AdminCache::new(Duration::ZERO);
+        expired.set(ChatId(1), true);
+        assert_eq!(expired.cached(ChatId(1)), None);
+    }
+
+    #[test]
+    fn test_admin_without_delete_right_cannot_repost() {
+        let admin: ChatMemberKind = serde_json::from_value(serde_json::json!({
+            "status": "administrator",
+            "can_be_edited": false,
+            "is_anonymous": false,
+            "can_manage_chat": true,
+            "can_delete_messages": false,
+            "can_manage_video_chats": false,
+            "can_restrict_members": false,
+            "can_promote_members": false,
+            "can_change_info": false,
+            "can_invite_users": true,
+            "can_post_stories": false,
+            "can_edit_stories": false,
+            "can_delete_stories": false
+        }))
+        .unwrap();
+        assert!(admin.is_privileged());
+        assert!(!can_delete_messages(&admin));
+        assert!(can_delete_messages(&ChatMemberKind::Owner(
+            teloxide::types::Owner {
+                custom_title: None,
+                is_anonymous: false,
+            }
+        )));
+    }
+}
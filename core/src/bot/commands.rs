@@ -9,4 +9,30 @@ pub(crate) enum Command {
     Ping,
     #[command(description = "내 대화 메모리 초기화")]
     MemoryReset,
+    #[command(description = "내 대화 메모리 관리 (show | export | reset)")]
+    Memory(String),
+    #[command(description = "채팅 지식 베이스 (add | ask <질문> | list | remove <번호>)")]
+    Kb(String),
+    #[command(description = "내 즐겨찾기 목록 (필터: tag:<태그> language:<언어>, export json|csv)")]
+    Favs(String),
+    #[command(description = "채팅 콘텐츠 필터 (adult on|off, add|remove <태그>, mode hide|refuse)")]
+    Filter(String),
+    #[command(description = "내 차단 태그 (add|remove <태그>, clear)")]
+    MyFilter(String),
+    #[command(description = "이 채팅의 최근 갤러리 조회 (개수, on|off|clear)")]
+    Recent(String),
+    #[command(description = "이 채팅의 조회 통계 (기간: 일 수, 기본 30)")]
+    Stats(String),
+    #[command(description = "작가·태그 새 작품 알림 구독 (예: /follow artist:foo_bar)")]
+    Follow(String),
+    #[command(description = "구독 해제 (예: /unfollow artist:foo_bar)")]
+    Unfollow(String),
+    #[command(description = "갤러리 링크 미러 (use <이름...>, reset)")]
+    Mirrors(String),
+    #[command(
+        description = "태그·작가·언어로 갤러리 검색 (예: /search female:glasses language:korean)"
+    )]
+    Search(String),
+    #[command(hide)]
+    Cache(String),
 }
@@ -0,0 +1,478 @@
+use log::error;
+use teloxide::prelude::*;
+use teloxide::types::{
+    CallbackQuery, InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup, InputFile,
+    LinkPreviewOptions, Message, ParseMode, UserId,
+};
+use teloxide::utils::html;
+
+use crate::favorites::{self, Favorite, FavoriteFilter};
+use crate::planabrain::truncate_message;
+
+use super::gallery::{
+    FAVORITE_PREFIX, FavoriteButton, adult_disabled_message, build_gallery_keyboard,
+    gallery_error_message,
+};
+use super::search::GALLERY_DETAIL_PREFIX;
+use super::telegram::{SendOptions, send_reply_with_fallback};
+use super::{AppState, HandlerResult};
+
+pub(crate) const FAVORITES_PAGE_PREFIX: &str = "favp_";
+
+const PAGE_SIZE: usize = 10;
+
+pub(crate) fn is_favorite_callback(data: &str) -> bool {
+    data.starts_with(FAVORITE_PREFIX) || data.starts_with(FAVORITES_PAGE_PREFIX)
+}
+
+pub(crate) async fn handle_favs_command<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    args: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let Some(user) = msg.from.as_ref() else {
+        send_reply_with_fallback(
+            bot,
+            msg,
+            "선생님, 사용자 정보를 확인할 수 없습니다.",
+            SendOptions::default(),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let args = args.trim();
+    if let Some(format) = args.strip_prefix("export") {
+        // 내보내기는 개인 메시지로만 보내므로 채팅 설정과 관계없이 허용합니다.
+        return export_favorites(bot, msg, state, user.id, format.trim()).await;
+    }
+
+    if !state.adult_allowed(msg) {
+        send_reply_with_fallback(bot, msg, adult_disabled_message(), SendOptions::default())
+            .await?;
+        return Ok(());
+    }
+
+    let filter = FavoriteFilter::parse(args);
+    let favorites = match load_filtered(state, user.id, &filter).await {
+        Ok(favorites) => favorites,
+        Err(err) => {
+            error!("즐겨찾기 조회 실패 (user {}): {}", user.id, err);
+            send_reply_with_fallback(
+                bot,
+                msg,
+                "선생님, 즐겨찾기를 불러오지 못했습니다. 잠시 후 다시 시도해 주십시오.",
+                SendOptions::default(),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if favorites.is_empty() {
+        let text = if filter.is_empty() {
+            "선생님, 저장한 즐겨찾기가 없습니다. 갤러리 정보의 ⭐ 버튼으로 추가하실 수 있습니다."
+        } else {
+            "선생님, 조건에 맞는 즐겨찾기가 없습니다."
+        };
+        send_reply_with_fallback(bot, msg, text, SendOptions::default()).await?;
+        return Ok(());
+    }
+
+    let (text, keyboard) = render_page(user.id, &favorites, &filter, 0);
+    send_reply_with_fallback(
+        bot,
+        msg,
+        text,
+        SendOptions {
+            reply_markup: Some(keyboard),
+            disable_preview: Some(true),
+            parse_mode: Some(ParseMode::Html),
+            ..SendOptions::default()
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn handle_favorite_callback<B>(
+    bot: B,
+    query: CallbackQuery,
+    state: AppState,
+    data: &str,
+) -> HandlerResult
+where
+    B: Requester + Send + Sync + 'static,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    if let Some(gallery_id) = data.strip_prefix(FAVORITE_PREFIX) {
+        return toggle_favorite(&bot, query, &state, gallery_id).await;
+    }
+
+    // favp_<사용자 ID>_<페이지>_<필터>
+    let mut parts = data
+        .strip_prefix(FAVORITES_PAGE_PREFIX)
+        .unwrap_or_default()
+        .splitn(3, '_');
+    let owner = parts.next().and_then(|raw| raw.parse::<u64>().ok());
+    let page = parts.next().and_then(|raw| raw.parse::<usize>().ok());
+    let filter = FavoriteFilter::parse(parts.next().unwrap_or_default());
+
+    let (Some(owner), Some(page), Some(message)) = (owner, page, query.regular_message().cloned())
+    else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+
+    if query.from.id.0 != owner {
+        let _ = bot
+            .answer_callback_query(query.id)
+            .text("선생님, 다른 분의 즐겨찾기 목록은 넘길 수 없습니다. /favs 로 직접 확인해 주십시오.")
+            .show_alert(true)
+            .await;
+        return Ok(());
+    }
+
+    let favorites = match load_filtered(&state, query.from.id, &filter).await {
+        Ok(favorites) => favorites,
+        Err(err) => {
+            error!("즐겨찾기 조회 실패 (user {}): {}", owner, err);
+            let _ = bot
+                .answer_callback_query(query.id)
+                .text("선생님, 즐겨찾기를 불러오지 못했습니다.")
+                .show_alert(true)
+                .await;
+            return Ok(());
+        }
+    };
+
+    let _ = bot.answer_callback_query(query.id).await;
+    let (text, keyboard) = render_page(query.from.id, &favorites, &filter, page);
+    if let Err(err) = bot
+        .edit_message_text(message.chat.id, message.id, text)
+        .parse_mode(ParseMode::Html)
+        .link_preview_options(LinkPreviewOptions {
+            is_disabled: true,
+            url: None,
+            prefer_small_media: false,
+            prefer_large_media: false,
+            show_above_text: false,
+        })
+        .reply_markup(keyboard)
+        .await
+    {
+        error!(
+            "즐겨찾기 목록 수정 실패 (chat {}): {}",
+            message.chat.id, err
+        );
+    }
+    Ok(())
+}
+
+async fn toggle_favorite<B>(
+    bot: &B,
+    query: CallbackQuery,
+    state: &AppState,
+    gallery_id: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let user = query.from.id;
+    let info = state.galleries.get(gallery_id).await;
+
+    let added = match state
+        .favorites
+        .toggle(user.0, gallery_id, info.as_ref().ok())
+        .await
+    {
+        Ok(added) => added,
+        Err(err) => {
+            let text = match &info {
+                Err(lookup_err) => gallery_error_message(gallery_id, lookup_err),
+                Ok(_) => format!("선생님, 즐겨찾기를 저장하지 못했습니다. ({})", err),
+            };
+            error!(
+                "즐겨찾기 변경 실패 (user {}, id {}): {}",
+                user, gallery_id, err
+            );
+            let _ = bot
+                .answer_callback_query(query.id)
+                .text(text)
+                .show_alert(true)
+                .await;
+            return Ok(());
+        }
+    };
+
+    let text = if added {
+        "⭐ 즐겨찾기에 추가했습니다. /favs 로 확인하실 수 있습니다."
+    } else {
+        "즐겨찾기에서 해제했습니다."
+    };
+    let _ = bot.answer_callback_query(query.id.clone()).text(text).await;
+
+    // 개인 채팅의 단일 갤러리 메시지라면 버튼 문구를 현재 상태로 바꿉니다.
+    if let (Ok(info), Some(message)) = (&info, query.regular_message())
+        && message.chat.is_private()
+        && message
+            .reply_markup()
+            .is_some_and(has_single_favorite_button)
+    {
+        let favorite = if added {
+            FavoriteButton::Remove
+        } else {
+            FavoriteButton::Add
+        };
+        if let Err(err) = bot
+            .edit_message_reply_markup(message.chat.id, message.id)
+            .reply_markup(build_gallery_keyboard(
+                info,
+                &state.mirror_links(Some(message.chat.id), &info.id),
+                favorite,
+            ))
+            .await
+        {
+            error!(
+                "즐겨찾기 버튼 갱신 실패 (chat {}): {}",
+                message.chat.id, err
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn export_favorites<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    user: UserId,
+    format: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let favorites = match state.favorites.list(user.0).await {
+        Ok(favorites) => favorites,
+        Err(err) => {
+            error!("즐겨찾기 내보내기 실패 (user {}): {}", user, err);
+            send_reply_with_fallback(
+                bot,
+                msg,
+                "선생님, 즐겨찾기를 불러오지 못했습니다. 잠시 후 다시 시도해 주십시오.",
+                SendOptions::default(),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if favorites.is_empty() {
+        send_reply_with_fallback(
+            bot,
+            msg,
+            "선생님, 내보낼 즐겨찾기가 없습니다.",
+            SendOptions::default(),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let (payload, extension) = match format.to_lowercase().as_str() {
+        "" | "json" => (favorites::export_json(user.0, &favorites)?, "json"),
+        "csv" => (favorites::export_csv(&favorites), "csv"),
+        _ => {
+            send_reply_with_fallback(
+                bot,
+                msg,
+                "선생님, 사용법: /favs export json | /favs export csv",
+                SendOptions::default(),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let file =
+        InputFile::memory(payload).file_name(format!("planabot-favorites-{}.{}", user, extension));
+
+    // 목록이 그룹에 노출되지 않도록 항상 개인 메시지로 보냅니다.
+    if let Err(err) = bot.send_document(user, file).await {
+        error!("즐겨찾기 파일 전송 실패 (user {}): {}", user, err);
+        send_reply_with_fallback(
+            bot,
+            msg,
+            "선생님, 먼저 저와 개인 대화를 시작하거나 차단을 해제해 주세요.",
+            SendOptions::default(),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if !msg.chat.is_private() {
+        send_reply_with_fallback(
+            bot,
+            msg,
+            "선생님, 즐겨찾기 파일을 개인 메시지로 보냈습니다.",
+            SendOptions::default(),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn load_filtered(
+    state: &AppState,
+    user: UserId,
+    filter: &FavoriteFilter,
+) -> anyhow::Result<Vec<Favorite>> {
+    let favorites = state.favorites.list(user.0).await?;
+    Ok(favorites
+        .into_iter()
+        .filter(|f| filter.matches(f))
+        .collect())
+}
+
+fn render_page(
+    owner: UserId,
+    favorites: &[Favorite],
+    filter: &FavoriteFilter,
+    page: usize,
+) -> (String, InlineKeyboardMarkup) {
+    let pages = favorites.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(pages - 1);
+    let start = page * PAGE_SIZE;
+    let entries = &favorites[start..favorites.len().min(start + PAGE_SIZE)];
+
+    let filter_label = if filter.is_empty() {
+        String::new()
+    } else {
+        format!(", 필터: {}", html::escape(&filter.to_string()))
+    };
+    let mut lines = vec![format!(
+        "<b>선생님의 즐겨찾기입니다. (총 {}건{}, {}/{} 페이지)</b>",
+        favorites.len(),
+        filter_label,
+        page + 1,
+        pages
+    )];
+    for (index, favorite) in entries.iter().enumerate() {
+        let language = favorite
+            .language
+            .as_deref()
+            .map(|language| format!(" · {}", html::escape(language)))
+            .unwrap_or_default();
+        lines.push(format!(
+            "{}. <a href=\"{}\">{}</a> ({}){}",
+            start + index + 1,
+            html::escape(&favorite.page_url()),
+            html::escape(&favorite.title),
+            favorite.id,
+            language
+        ));
+    }
+
+    let detail_buttons: Vec<InlineKeyboardButton> = entries
+        .iter()
+        .enumerate()
+        .map(|(index, favorite)| {
+            InlineKeyboardButton::callback(
+                (start + index + 1).to_string(),
+                format!("{}{}", GALLERY_DETAIL_PREFIX, favorite.id),
+            )
+        })
+        .collect();
+    let mut rows: Vec<Vec<InlineKeyboardButton>> =
+        detail_buttons.chunks(5).map(|row| row.to_vec()).collect();
+
+    let nav_data = |page: usize| {
+        let data = format!("{}{}_{}_{}", FAVORITES_PAGE_PREFIX, owner, page, filter);
+        // 콜백 데이터는 64바이트까지만 허용됩니다.
+        (data.len() <= 64).then_some(data)
+    };
+    let mut nav = Vec::new();
+    if page > 0
+        && let Some(data) = nav_data(page - 1)
+    {
+        nav.push(InlineKeyboardButton::callback("◀", data));
+    }
+    if page + 1 < pages
+        && let Some(data) = nav_data(page + 1)
+    {
+        nav.push(InlineKeyboardButton::callback("▶", data));
+    }
+    if !nav.is_empty() {
+        rows.push(nav);
+    }
+
+    (
+        truncate_message(&lines.join("\n"), 4000),
+        InlineKeyboardMarkup::new(rows),
+    )
+}
+
+fn has_single_favorite_button(markup: &InlineKeyboardMarkup) -> bool {
+    markup
+        .inline_keyboard
+        .iter()
+        .flatten()
+        .filter(|button| {
+            matches!(&button.kind, InlineKeyboardButtonKind::CallbackData(data) if data.starts_with(FAVORITE_PREFIX))
+        })
+        .count()
+        == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn favorite(id: &str) -> Favorite {
+        Favorite {
+            id: id.to_string(),
+            title: format!("<{id}>"),
+            artists: Vec::new(),
+            language: Some("한국어".to_string()),
+            language_code: Some("korean".to_string()),
+            tags: Vec::new(),
+            added_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_render_page_paginates_with_owner_and_filter() {
+        let favorites: Vec<_> = (1..=25).map(|i| favorite(&i.to_string())).collect();
+        let filter = FavoriteFilter::parse("language:korean");
+        let (text, keyboard) = render_page(UserId(42), &favorites, &filter, 1);
+
+        assert!(text.contains("총 25건, 필터: language:korean, 2/3 페이지"));
+        assert!(
+            text.contains("11. <a href=\"https://hitomi.la/galleries/11.html\">&lt;11&gt;</a>")
+        );
+        let nav = keyboard.inline_keyboard.last().unwrap();
+        assert_eq!(nav.len(), 2);
+        assert!(matches!(
+            &nav[1].kind,
+            InlineKeyboardButtonKind::CallbackData(data) if data == "favp_42_2_language:korean"
+        ));
+    }
+
+    #[test]
+    fn test_single_favorite_button_detection() {
+        let single =
+            InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback("a", "save_1")]]);
+        let many = InlineKeyboardMarkup::new(vec![vec![
+            InlineKeyboardButton::callback("a", "save_1"),
+            InlineKeyboardButton::callback("b", "save_2"),
+        ]]);
+        assert!(has_single_favorite_button(&single));
+        assert!(!has_single_favorite_button(&many));
+    }
+}
@@ -0,0 +1,203 @@
+use log::error;
+use teloxide::prelude::*;
+use teloxide::types::Message;
+
+use crate::settings::{BlacklistMode, ChatSettings, normalize_tag};
+
+use super::telegram::{SendOptions, is_chat_admin, send_reply_with_fallback};
+use super::{AppState, HandlerResult};
+
+const FILTER_USAGE: &str = "선생님, 사용법입니다. (변경은 관리자만)\n\
+- /filter: 현재 설정 보기\n\
+- /filter adult on|off: 이 채팅의 갤러리 조회 허용 여부\n\
+- /filter add <태그...> / /filter remove <태그...>: 채팅 차단 태그 (공백은 _)\n\
+- /filter mode hide|refuse: 차단 태그를 숨기고 보여줄지, 아예 표시하지 않을지";
+
+const MY_FILTER_USAGE: &str = "선생님, 사용법입니다.\n\
+- /myfilter: 내 차단 태그 보기\n\
+- /myfilter add <태그...> / /myfilter remove <태그...>\n\
+- /myfilter clear: 모두 지우기";
+
+pub(crate) async fn handle_filter_command<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    args: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let (sub, rest) = split_args(args);
+    let chat_id = msg.chat.id.0;
+
+    if sub.is_empty() {
+        let text = render_chat_settings(&state.settings.chat(chat_id), state.adult_allowed(msg));
+        return reply(bot, msg, text).await;
+    }
+
+    if !matches!(sub.as_str(), "adult" | "add" | "remove" | "mode") {
+        return reply(bot, msg, FILTER_USAGE).await;
+    }
+    if !is_chat_admin(bot, msg).await {
+        return reply(bot, msg, "선생님, 필터 설정은 관리자만 변경할 수 있습니다.").await;
+    }
+
+    let update: Box<dyn FnOnce(&mut ChatSettings) + Send> = match (sub.as_str(), rest.as_str()) {
+        ("adult", "on") => Box::new(|chat| chat.adult = Some(true)),
+        ("adult", "off") => Box::new(|chat| chat.adult = Some(false)),
+        ("mode", "hide") => Box::new(|chat| chat.blacklist_mode = BlacklistMode::Hide),
+        ("mode", "refuse") => Box::new(|chat| chat.blacklist_mode = BlacklistMode::Refuse),
+        ("add", _) | ("remove", _) if !parse_tags(&rest).is_empty() => {
+            let tags = parse_tags(&rest);
+            let adding = sub == "add";
+            Box::new(move |chat| apply_tags(&mut chat.blacklist, tags, adding))
+        }
+        _ => return reply(bot, msg, FILTER_USAGE).await,
+    };
+
+    match state.settings.update_chat(chat_id, update).await {
+        Ok(settings) => {
+            let text = render_chat_settings(&settings, state.adult_allowed(msg));
+            reply(
+                bot,
+                msg,
+                format!("선생님, 설정을 변경했습니다.\n\n{}", text),
+            )
+            .await
+        }
+        Err(err) => {
+            error!("채팅 필터 저장 실패 (chat {}): {}", chat_id, err);
+            reply(bot, msg, "선생님, 설정을 저장하지 못했습니다.").await
+        }
+    }
+}
+
+pub(crate) async fn handle_my_filter_command<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    args: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let Some(user) = msg.from.as_ref() else {
+        return reply(bot, msg, "선생님, 사용자 정보를 확인할 수 없습니다.").await;
+    };
+    let (sub, rest) = split_args(args);
+
+    let result = match sub.as_str() {
+        "" | "list" => Ok(state.settings.user(user.id.0)),
+        "clear" => {
+            state
+                .settings
+                .update_user(user.id.0, |settings| settings.blacklist.clear())
+                .await
+        }
+        "add" | "remove" if !parse_tags(&rest).is_empty() => {
+            let tags = parse_tags(&rest);
+            let adding = sub == "add";
+            state
+                .settings
+                .update_user(user.id.0, move |settings| {
+                    apply_tags(&mut settings.blacklist, tags, adding)
+                })
+                .await
+        }
+        _ => return reply(bot, msg, MY_FILTER_USAGE).await,
+    };
+
+    match result {
+        Ok(settings) if settings.blacklist.is_empty() => {
+            reply(bot, msg, "선생님, 등록된 개인 차단 태그가 없습니다.").await
+        }
+        Ok(settings) => {
+            reply(
+                bot,
+                msg,
+                format!(
+                    "선생님의 개인 차단 태그입니다. (모든 채팅에 적용)\n{}",
+                    settings.blacklist.join(", ")
+                ),
+            )
+            .await
+        }
+        Err(err) => {
+            error!("개인 필터 저장 실패 (user {}): {}", user.id, err);
+            reply(bot, msg, "선생님, 설정을 저장하지 못했습니다.").await
+        }
+    }
+}
+
+fn split_args(args: &str) -> (String, String) {
+    let args = args.trim();
+    let (sub, rest) = args
+        .split_once(char::is_whitespace)
+        .map(|(sub, rest)| (sub, rest.trim()))
+        .unwrap_or((args, ""));
+    (sub.to_lowercase(), rest.to_lowercase())
+}
+
+fn parse_tags(raw: &str) -> Vec<String> {
+    raw.split_whitespace().filter_map(normalize_tag).collect()
+}
+
+fn apply_tags(blacklist: &mut Vec<String>, tags: Vec<String>, adding: bool) {
+    for tag in tags {
+        if adding {
+            if !blacklist.contains(&tag) {
+                blacklist.push(tag);
+            }
+        } else {
+            blacklist.retain(|existing| *existing != tag);
+        }
+    }
+}
+
+fn render_chat_settings(settings: &ChatSettings, adult_allowed: bool) -> String {
+    let mode = match settings.blacklist_mode {
+        BlacklistMode::Hide => "태그만 숨김 (스포일러 경고)",
+        BlacklistMode::Refuse => "표시 거부",
+    };
+    let blacklist = if settings.blacklist.is_empty() {
+        "없음".to_string()
+    } else {
+        settings.blacklist.join(", ")
+    };
+    format!(
+        "갤러리 조회: {}\n차단 태그: {}\n차단 방식: {}\n조회 기록: {}",
+        if adult_allowed { "허용" } else { "꺼짐" },
+        blacklist,
+        mode,
+        if settings.history_disabled {
+            "남기지 않음 (/recent on)"
+        } else {
+            "남김"
+        }
+    )
+}
+
+async fn reply<B>(bot: &B, msg: &Message, text: impl Into<String>) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    send_reply_with_fallback(bot, msg, text, SendOptions::default()).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_tags_adds_once_and_removes() {
+        let mut blacklist = vec!["guro".to_string()];
+        apply_tags(&mut blacklist, parse_tags("Guro female:Big_Breasts"), true);
+        assert_eq!(blacklist, vec!["guro", "female:big breasts"]);
+        apply_tags(&mut blacklist, parse_tags("guro"), false);
+        assert_eq!(blacklist, vec!["female:big breasts"]);
+    }
+}
@@ -0,0 +1,145 @@
+use log::error;
+use teloxide::prelude::*;
+use teloxide::types::Message;
+
+use crate::follow::{FollowOutcome, FollowStore};
+use crate::hitomi::SearchTerm;
+
+use super::gallery::adult_disabled_message;
+use super::telegram::{SendOptions, is_chat_admin, send_reply_with_fallback};
+use super::{AppState, HandlerResult};
+
+const FOLLOW_USAGE: &str = "선생님, 사용법입니다. (그룹에서는 관리자만 변경할 수 있습니다)\n\
+- /follow: 이 채팅의 구독 목록\n\
+- /follow artist:<작가> 또는 /follow tag:<태그>: 새 작품이 올라오면 알림 (group:, series:, character:도 가능, 공백은 _)\n\
+- /unfollow <검색어>: 구독 해제";
+
+pub(crate) async fn handle_follow_command<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    args: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let args = args.trim();
+    if args.is_empty() {
+        return reply(
+            bot,
+            msg,
+            render_follow_list(&state.follows.list(msg.chat.id.0)),
+        )
+        .await;
+    }
+    let Some(term) = parse_term(args) else {
+        return reply(bot, msg, FOLLOW_USAGE).await;
+    };
+    if !state.adult_allowed(msg) {
+        return reply(bot, msg, adult_disabled_message()).await;
+    }
+    if !is_chat_admin(bot, msg).await {
+        return reply(bot, msg, "선생님, 구독 설정은 관리자만 변경할 수 있습니다.").await;
+    }
+
+    let text = match state.follows.follow(msg.chat.id.0, &term).await {
+        Ok(FollowOutcome::Added) => format!(
+            "선생님, 이제 {}의 새 작품이 올라오면 이 채팅에 알려드리겠습니다.",
+            term
+        ),
+        Ok(FollowOutcome::AlreadyFollowing) => {
+            format!("선생님, 이미 {}을(를) 구독 중입니다.", term)
+        }
+        Ok(FollowOutcome::LimitReached) => format!(
+            "선생님, 한 채팅에서는 최대 {}개까지 구독할 수 있습니다. /unfollow 로 정리해 주십시오.",
+            state.follows.max_per_chat()
+        ),
+        Err(err) => {
+            error!("구독 저장 실패 (chat {}): {}", msg.chat.id, err);
+            "선생님, 구독을 저장하지 못했습니다.".to_string()
+        }
+    };
+    reply(bot, msg, text).await
+}
+
+pub(crate) async fn handle_unfollow_command<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    args: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let Some(term) = parse_term(args) else {
+        return reply(bot, msg, FOLLOW_USAGE).await;
+    };
+    if !is_chat_admin(bot, msg).await {
+        return reply(bot, msg, "선생님, 구독 설정은 관리자만 변경할 수 있습니다.").await;
+    }
+
+    let text = match state.follows.unfollow(msg.chat.id.0, &term).await {
+        Ok(true) => format!("선생님, {} 구독을 해제했습니다.", term),
+        Ok(false) => format!("선생님, {}은(는) 구독 중이 아닙니다.", term),
+        Err(err) => {
+            error!("구독 해제 저장 실패 (chat {}): {}", msg.chat.id, err);
+            "선생님, 구독을 저장하지 못했습니다.".to_string()
+        }
+    };
+    reply(bot, msg, text).await
+}
+
+/// 검색어 하나만 받습니다. 언어·유형처럼 너무 넓은 검색어는 거절합니다.
+fn parse_term(args: &str) -> Option<SearchTerm> {
+    let mut tokens = args.split_whitespace();
+    let term = SearchTerm::parse(tokens.next()?)?;
+    (tokens.next().is_none() && FollowStore::is_followable(&term)).then_some(term)
+}
+
+fn render_follow_list(terms: &[String]) -> String {
+    if terms.is_empty() {
+        return format!(
+            "선생님, 이 채팅에서 구독 중인 작가·태그가 없습니다.\n\n{}",
+            FOLLOW_USAGE
+        );
+    }
+    format!(
+        "선생님, 이 채팅의 구독 목록입니다.\n{}",
+        terms
+            .iter()
+            .map(|term| format!("- {}", term))
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
+}
+
+async fn reply<B>(bot: &B, msg: &Message, text: impl Into<String>) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    send_reply_with_fallback(bot, msg, text, SendOptions::default()).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_term_accepts_single_narrow_term() {
+        assert_eq!(
+            parse_term("artist:foo_bar").unwrap().to_string(),
+            "artist:foo_bar"
+        );
+        assert_eq!(
+            parse_term("female:glasses").unwrap().to_string(),
+            "female:glasses"
+        );
+        assert!(parse_term("artist:a tag:b").is_none());
+        assert!(parse_term("language:korean").is_none());
+        assert!(parse_term("type:manga").is_none());
+    }
+}
@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use log::{error, info, warn};
+use teloxide::prelude::*;
+use teloxide::types::ParseMode;
+use teloxide::utils::html;
+
+use crate::follow::{NewRelease, collect_new_releases};
+use crate::util::env_number;
+
+use super::AppState;
+use super::gallery::{
+    FavoriteButton, build_gallery_keyboard, lookup_concurrency, render_gallery_filtered,
+};
+
+/// 구독 폴링 설정.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PollerConfig {
+    pub interval: Duration,
+    /// 한 번의 폴링에서 검색어 하나당 채팅에 보낼 최대 갤러리 수.
+    pub max_posts: usize,
+}
+
+impl PollerConfig {
+    /// `PLANABOT_FOLLOW_INTERVAL_SECS`가 `0`이면 `None`(폴링 안 함)입니다.
+    pub(crate) fn from_env() -> Option<Self> {
+        let interval = env_number("PLANABOT_FOLLOW_INTERVAL_SECS").unwrap_or(1800);
+        let max_posts = env_number("PLANABOT_FOLLOW_MAX_POSTS").unwrap_or(5).max(1);
+        (interval > 0).then(|| Self {
+            interval: Duration::from_secs(interval as u64),
+            max_posts,
+        })
+    }
+}
+
+/// 구독한 작가·태그의 새 작품을 주기적으로 확인해 알리는 작업을 띄웁니다.
+pub fn spawn_follow_poller<B>(bot: B, state: AppState)
+where
+    B: Requester + Send + Sync + 'static,
+    B::Err: std::error::Error + Send + Sync + 'static,
+    B::SendMessage: Send,
+{
+    let Some(config) = PollerConfig::from_env() else {
+        info!("구독 폴링이 꺼져 있습니다");
+        return;
+    };
+    tokio::spawn(async move {
+        loop {
+            poll_once(&bot, &state, config).await;
+            tokio::time::sleep(config.interval).await;
+        }
+    });
+}
+
+async fn poll_once<B>(bot: &B, state: &AppState, config: PollerConfig)
+where
+    B: Requester + Send + Sync,
+    B::Err: std::error::Error + Send + Sync + 'static,
+    B::SendMessage: Send,
+{
+    for release in collect_new_releases(&state.gallery_client, &state.follows).await {
+        post_release(bot, state, &release, config.max_posts).await;
+    }
+}
+
+async fn post_release<B>(bot: &B, state: &AppState, release: &NewRelease, max_posts: usize)
+where
+    B: Requester + Send + Sync,
+    B::Err: std::error::Error + Send + Sync + 'static,
+    B::SendMessage: Send,
+{
+    let ids: Vec<String> = release
+        .ids
+        .iter()
+        .take(max_posts)
+        .rev()
+        .map(ToString::to_string)
+        .collect();
+    let infos: Vec<_> = state
+        .gallery_client
+        .get_many(&ids, lookup_concurrency())
+        .await
+        .into_iter()
+        .filter_map(|(id, result)| {
+            result
+                .inspect_err(|err| warn!("구독 알림 갤러리 조회 실패 (ID {}): {}", id, err))
+                .ok()
+        })
+        .collect();
+    let skipped = release.ids.len().saturating_sub(max_posts);
+
+    for &chat_id in &release.chats {
+        // 구독 후 설정이 바뀌었을 수 있으므로 보낼 때마다 확인합니다.
+        if !state.settings.adult_allowed(chat_id, chat_id > 0) {
+            continue;
+        }
+        let policy = state.settings.content_policy(Some(chat_id), None);
+        for info in &infos {
+            let Ok(rendered) = render_gallery_filtered(info, &policy) else {
+                continue;
+            };
+            let text = format!(
+                "🔔 새 작품 알림: {}\n\n{}",
+                html::escape(&release.term),
+                rendered.text
+            );
+            let sent = bot
+                .send_message(ChatId(chat_id), text)
+                .parse_mode(ParseMode::Html)
+                .reply_markup(build_gallery_keyboard(
+                    info,
+                    &state.mirror_links(Some(ChatId(chat_id)), &info.id),
+                    FavoriteButton::Toggle,
+                ))
+                .await;
+            if let Err(err) = sent {
+                if is_unreachable(&err.to_string()) {
+                    warn!(
+                        "보낼 수 없는 채팅의 구독을 지웁니다 (chat {}): {}",
+                        chat_id, err
+                    );
+                    if let Err(err) = state.follows.remove_chat(chat_id).await {
+                        error!("구독 삭제 실패 (chat {}): {}", chat_id, err);
+                    }
+                    break;
+                }
+                error!(
+                    "구독 알림 전송 실패 (chat {}, ID {}): {}",
+                    chat_id, info.id, err
+                );
+            }
+        }
+        if skipped > 0 {
+            let text = format!(
+                "선생님, {}의 새 작품이 {}개 더 있습니다. /search {} 로 확인해 주십시오.",
+                release.term, skipped, release.term
+            );
+            if let Err(err) = bot.send_message(ChatId(chat_id), text).await {
+                error!("구독 알림 전송 실패 (chat {}): {}", chat_id, err);
+            }
+        }
+    }
+}
+
+fn is_unreachable(error: &str) -> bool {
+    let error = error.to_lowercase();
+    [
+        "bot was kicked",
+        "bot was blocked",
+        "chat not found",
+        "bot is not a member",
+    ]
+    .iter()
+    .any(|needle| error.contains(needle))
+}
@@ -1,89 +1,460 @@
+use log::warn;
 use once_cell::sync::Lazy;
-use reqwest::Url;
-use teloxide::types::{ChatKind, InlineKeyboardButton, InlineKeyboardMarkup, Message, PublicChatKind};
+use teloxide::prelude::*;
+use teloxide::types::{
+    ChatKind, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, Message, ParseMode,
+    PublicChatKind, ReplyParameters, UserId,
+};
 use teloxide::utils::html;
 
-use crate::hitomi::GalleryInfo;
+use crate::hitomi::{GalleryError, GalleryInfo, TagKind};
+use crate::mirrors::MirrorLink;
+use crate::settings::{ContentPolicy, Verdict};
+use crate::sources::{GalleryRef, SourceKind};
 
-pub(crate) fn extract_gallery_id(text: &str, msg: &Message, bot_username: &str) -> Option<String> {
-    static BANG_RE: Lazy<regex::Regex> = Lazy::new(|| regex::Regex::new(r"^!(\d+)$").unwrap());
+use super::AppState;
 
-    if let Some(cap) = BANG_RE.captures(text) {
-        return Some(cap[1].to_string());
+/// 즐겨찾기 추가/해제 콜백. 예전 "개인 메시지로 저장" 버튼과 같은 접두사를 씁니다.
+pub(crate) const FAVORITE_PREFIX: &str = "save_";
+
+/// 한 메시지에서 처리할 최대 갤러리 수.
+pub(crate) const MAX_GALLERIES_PER_MESSAGE: usize = 10;
+
+/// 메시지에 들어 있는 갤러리 참조를 순서대로(중복 제외) 모두 찾습니다.
+///
+/// - `!12345`, `!nh 177013`, `!e <gid>/<token>` 형식과 Hitomi·nhentai·E-Hentai 갤러리 URL은
+///   어느 채팅에서나 인식합니다. 돌려주는 ID는 `GalleryRef` 문자열 형식입니다.
+/// - 숫자만 있는 토큰(Hitomi)은 개인 채팅이거나, 그룹에서 메시지가 `@봇이름`으로 시작할 때만 인식합니다.
+///   이때도 메시지 전체가 숫자 목록일 때만 ID로 봅니다. ("3시에 2명" 같은 문장의 숫자는 무시)
+pub(crate) fn extract_gallery_ids(text: &str, msg: &Message, bot_username: &str) -> Vec<String> {
+    let accepts_bare = match &msg.chat.kind {
+        ChatKind::Private(_) => true,
+        ChatKind::Public(public) => {
+            matches!(
+                public.kind,
+                PublicChatKind::Group | PublicChatKind::Supergroup(_)
+            ) && is_addressed_to(text, bot_username)
+        }
+    };
+    scan_gallery_ids(text, accepts_bare)
+}
+
+/// `!ID` 형식이나 `@봇이름`으로 직접 요청한 메시지인지 봅니다. URL만 붙여 넣은 메시지는 아닙니다.
+pub(crate) fn is_explicit_request(text: &str, bot_username: &str) -> bool {
+    is_addressed_to(text, bot_username)
+        || text.split_whitespace().any(|token| token.starts_with('!'))
+}
+
+fn is_addressed_to(text: &str, bot_username: &str) -> bool {
+    if bot_username.is_empty() {
+        return false;
     }
+    text.split_whitespace()
+        .next()
+        .and_then(|first| first.strip_prefix('@'))
+        .is_some_and(|name| name.eq_ignore_ascii_case(bot_username))
+}
+
+pub(crate) fn scan_gallery_ids(text: &str, accepts_bare: bool) -> Vec<String> {
+    // !123, !nh177013, !e618395/0439fa3666 (접두사만 있으면 다음 토큰이 ID)
+    static BANG_RE: Lazy<regex::Regex> =
+        Lazy::new(|| regex::Regex::new(r"(?i)^!(nh|e)?(\S*)$").unwrap());
+
+    let mut tokens = text
+        .split_whitespace()
+        .map(|token| token.trim_matches(|c: char| matches!(c, ',' | '(' | ')' | '<' | '>')));
+    let accepts_bare = accepts_bare && is_number_list(text);
+    let mut ids: Vec<String> = Vec::new();
+    while let Some(token) = tokens.next() {
+        let found = if let Some(gallery) = GalleryRef::from_url(token) {
+            Some(gallery)
+        } else if let Some(cap) = BANG_RE.captures(token) {
+            let prefix = cap.get(1).map_or("", |m| m.as_str());
+            match &cap[2] {
+                "" if !prefix.is_empty() => tokens
+                    .next()
+                    .and_then(|id| GalleryRef::from_prefixed(prefix, id)),
+                id => GalleryRef::from_prefixed(prefix, id),
+            }
+        } else if accepts_bare {
+            GalleryRef::new(SourceKind::Hitomi, token)
+        } else {
+            None
+        };
 
-    match &msg.chat.kind {
-        ChatKind::Private(_) => {
-            if text.chars().all(|c| c.is_ascii_digit()) {
-                return Some(text.to_string());
+        if let Some(id) = found.map(|gallery| gallery.to_string())
+            && !ids.contains(&id)
+        {
+            ids.push(id);
+            if ids.len() >= MAX_GALLERIES_PER_MESSAGE {
+                break;
             }
         }
-        ChatKind::Public(public) => match &public.kind {
-            PublicChatKind::Group | PublicChatKind::Supergroup(_) => {
-                if bot_username.is_empty() {
-                    return None;
-                }
+    }
+    ids
+}
 
-                let pattern = format!(r"^@{}\s+(\d+)", regex::escape(bot_username));
-                if let Ok(re) = regex::Regex::new(&pattern) {
-                    if let Some(cap) = re.captures(text) {
-                        return Some(cap[1].to_string());
-                    }
+/// 앞의 `@봇이름`을 빼고 남은 토큰이 모두 숫자인지 봅니다.
+fn is_number_list(text: &str) -> bool {
+    let mut numbers = text
+        .split_whitespace()
+        .skip_while(|token| token.starts_with('@'))
+        .map(|token| token.trim_matches(','))
+        .filter(|token| !token.is_empty())
+        .peekable();
+    numbers.peek().is_some() && numbers.all(|token| token.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// 일괄 조회 동시 요청 수 (`PLANABOT_GALLERY_CONCURRENCY`, 기본 4).
+pub(crate) fn lookup_concurrency() -> usize {
+    static CONCURRENCY: Lazy<usize> = Lazy::new(|| {
+        std::env::var("PLANABOT_GALLERY_CONCURRENCY")
+            .ok()
+            .and_then(|raw| raw.trim().parse().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(4)
+    });
+    *CONCURRENCY
+}
+
+/// 여러 갤러리를 한 메시지에 요약합니다. 실패한 ID는 사유만 짧게 붙입니다.
+pub(crate) fn render_gallery_batch(
+    results: &[(String, Result<GalleryInfo, GalleryError>)],
+    policy: &ContentPolicy,
+) -> String {
+    let found = results
+        .iter()
+        .filter(|(_, result)| result.as_ref().is_ok_and(|info| is_visible(info, policy)))
+        .count();
+    let mut blocks = vec![format!(
+        "<b>선생님, 요청하신 {}건 중 {}건의 분석 결과입니다.</b>",
+        results.len(),
+        found
+    )];
+
+    for (index, (gallery_id, result)) in results.iter().enumerate() {
+        let block = match result {
+            Ok(info) if !is_visible(info, policy) => {
+                format!("<b>{}.</b> ID {}: 차단된 태그 포함", index + 1, gallery_id)
+            }
+            Ok(info) => {
+                let mut details = vec![join_or_unknown(&info.artists)];
+                if let Some(gallery_type) = &info.gallery_type {
+                    details.push(html::escape(gallery_type));
+                }
+                if let Some(language) = &info.language {
+                    details.push(html::escape(language));
+                }
+                if info.page_count > 0 {
+                    details.push(format!("{}p", info.page_count));
                 }
+                format!(
+                    "<b>{}. <a href=\"{}\">{}</a></b> ({})\n{}",
+                    index + 1,
+                    html::escape(&info.page_url()),
+                    html::escape(&info.title),
+                    gallery_id,
+                    details.join(" · ")
+                )
             }
-            _ => {}
-        },
+            Err(err) => format!(
+                "<b>{}.</b> ID {}: {}",
+                index + 1,
+                gallery_id,
+                batch_error_label(err)
+            ),
+        };
+        blocks.push(block);
     }
 
-    None
+    blocks.join("\n\n")
 }
 
-pub(crate) fn render_gallery_message(info: &GalleryInfo, saved: bool) -> String {
-    let title = html::escape(&info.title);
-    let artists = html::escape(&info.artists);
-    let language = html::escape(&info.language);
-    let tags = if info.tags.is_empty() {
-        "태그 정보 없음".to_string()
-    } else {
-        info.tags
-            .iter()
-            .map(|t| html::escape(t))
-            .collect::<Vec<_>>()
-            .join(", ")
-    };
+pub(crate) fn is_visible(info: &GalleryInfo, policy: &ContentPolicy) -> bool {
+    !matches!(policy.check(info), Verdict::Refused(_))
+}
+
+fn batch_error_label(err: &GalleryError) -> &'static str {
+    match err {
+        GalleryError::NotFound => "존재하지 않음",
+        GalleryError::Upstream(_) => "서버 오류",
+        GalleryError::Network(_) => "연결 실패",
+        GalleryError::Timeout => "시간 초과",
+        GalleryError::Parse(_) => "데이터 해석 실패",
+    }
+}
 
-    let header = if saved {
-        format!(
-            "<b>선생님, ID {}에 대한 분석 결과입니다. (#저장됨)</b>",
-            info.id
-        )
+/// 일괄 결과에 붙는 버튼. 찾은 갤러리마다 즐겨찾기 추가/해제 버튼을 둡니다.
+pub(crate) fn build_batch_keyboard(
+    results: &[(String, Result<GalleryInfo, GalleryError>)],
+    policy: &ContentPolicy,
+) -> InlineKeyboardMarkup {
+    let buttons: Vec<InlineKeyboardButton> = results
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, result))| result.as_ref().is_ok_and(|info| is_visible(info, policy)))
+        .map(|(index, (gallery_id, _))| {
+            InlineKeyboardButton::callback(
+                format!("⭐ {}", index + 1),
+                format!("{}{}", FAVORITE_PREFIX, gallery_id),
+            )
+        })
+        .collect();
+    InlineKeyboardMarkup::new(
+        buttons
+            .chunks(5)
+            .map(|row| row.to_vec())
+            .collect::<Vec<_>>(),
+    )
+}
+
+pub(crate) fn render_gallery_message(info: &GalleryInfo) -> String {
+    let header = format!("<b>선생님, ID {}에 대한 분석 결과입니다.</b>", info.id);
+
+    let mut lines = vec![header, String::new()];
+    lines.push(field("제목", &html::escape(&info.title)));
+    if let Some(japanese_title) = &info.japanese_title {
+        lines.push(field("원제", &html::escape(japanese_title)));
+    }
+    if let Some(gallery_type) = &info.gallery_type {
+        lines.push(field("유형", &html::escape(gallery_type)));
+    }
+    lines.push(field("작가", &join_or_unknown(&info.artists)));
+    if !info.groups.is_empty() {
+        lines.push(field("그룹", &join_escaped(&info.groups)));
+    }
+    if !info.series.is_empty() {
+        lines.push(field("원작", &join_escaped(&info.series)));
+    }
+    if !info.characters.is_empty() {
+        lines.push(field("캐릭터", &join_escaped(&info.characters)));
+    }
+    lines.push(field(
+        "언어",
+        &html::escape(info.language.as_deref().unwrap_or("정보 없음")),
+    ));
+    if info.page_count > 0 {
+        lines.push(field("페이지", &info.page_count.to_string()));
+    }
+    if let Some(date) = &info.date {
+        lines.push(field("업로드", &html::escape(date)));
+    }
+
+    if info.tags.is_empty() {
+        lines.push(field("태그", "태그 정보 없음"));
     } else {
-        format!("<b>선생님, ID {}에 대한 분석 결과입니다.</b>", info.id)
-    };
+        for (kind, label) in [
+            (TagKind::Female, "여성 태그"),
+            (TagKind::Male, "남성 태그"),
+            (TagKind::Plain, "태그"),
+        ] {
+            let names: Vec<String> = info.tags_of(kind).map(|tag| tag.name.clone()).collect();
+            if !names.is_empty() {
+                lines.push(field(label, &join_escaped(&names)));
+            }
+        }
+    }
+
+    lines.join("\n")
+}
 
+/// 콘텐츠 정책을 적용해 렌더링한 갤러리 정보.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FilteredGallery {
+    pub text: String,
+    /// 차단 태그를 숨겼으면 `true`. 표지도 스포일러로 가립니다.
+    pub spoiler: bool,
+}
+
+/// 정책에 따라 갤러리 정보를 렌더링합니다. 거부되면 걸린 태그를 `Err`로 돌려줍니다.
+pub(crate) fn render_gallery_filtered(
+    info: &GalleryInfo,
+    policy: &ContentPolicy,
+) -> Result<FilteredGallery, Vec<String>> {
+    match policy.check(info) {
+        Verdict::Allowed => Ok(FilteredGallery {
+            text: render_gallery_message(info),
+            spoiler: false,
+        }),
+        Verdict::Hidden(tags) => Ok(FilteredGallery {
+            text: format!(
+                "⚠️ 차단된 태그 {}개를 숨겼습니다: <tg-spoiler>{}</tg-spoiler>\n\n{}",
+                tags.len(),
+                join_escaped(&tags),
+                render_gallery_message(&policy.redact(info))
+            ),
+            spoiler: true,
+        }),
+        Verdict::Refused(tags) => Err(tags),
+    }
+}
+
+pub(crate) fn refused_message(gallery_id: &str, tags: &[String]) -> String {
     format!(
-        "{header}\n\n<b>제목:</b> {title}\n<b>작가:</b> {artists}\n<b>언어:</b> {language}\n<b>태그:</b> {tags}"
+        "선생님, ID {}에는 이 채팅에서 차단된 태그({})가 포함되어 있어 표시하지 않습니다.",
+        gallery_id,
+        tags.join(", ")
     )
 }
 
-pub(crate) fn build_gallery_keyboard(
+pub(crate) fn adult_disabled_message() -> &'static str {
+    "선생님, 이 채팅에서는 갤러리 조회가 꺼져 있습니다. 관리자가 /filter adult on 으로 켤 수 있습니다."
+}
+
+/// 조회 실패 사유별 안내 문구.
+pub(crate) fn gallery_error_message(gallery_id: &str, err: &GalleryError) -> String {
+    match err {
+        GalleryError::NotFound => format!(
+            "선생님, ID {}에 해당하는 갤러리가 존재하지 않는 것으로 확인됩니다.",
+            gallery_id
+        ),
+        GalleryError::Upstream(status) => format!(
+            "선생님, Hitomi 서버가 오류 응답({})을 보냈습니다. 잠시 후 다시 시도해 주십시오.",
+            status.as_u16()
+        ),
+        GalleryError::Network(_) => {
+            "선생님, Hitomi 서버에 연결하지 못했습니다. 잠시 후 다시 시도해 주십시오.".to_string()
+        }
+        GalleryError::Timeout => {
+            "선생님, Hitomi 서버의 응답이 지연되어 요청 시간이 초과되었습니다.".to_string()
+        }
+        GalleryError::Parse(_) => format!(
+            "선생님, ID {}의 데이터 형식을 해석하지 못했습니다. 제목 등 필수 데이터가 누락되었을 수 있습니다.",
+            gallery_id
+        ),
+    }
+}
+
+/// `PLANABOT_GALLERY_SEND_COVER`가 켜져 있으면 갤러리 정보를 표지 사진의 캡션으로 보냅니다.
+pub(crate) fn cover_enabled() -> bool {
+    static ENABLED: Lazy<bool> = Lazy::new(|| {
+        std::env::var("PLANABOT_GALLERY_SEND_COVER")
+            .map(|raw| raw == "1" || raw.eq_ignore_ascii_case("true"))
+            .unwrap_or(false)
+    });
+    *ENABLED
+}
+
+/// 표지 사진에 갤러리 정보를 캡션으로 붙여 답장합니다. 표지를 받지 못했거나 캡션이
+/// 너무 길거나 전송에 실패하면 `false`를 돌려주고, 호출부는 텍스트로 대신 보냅니다.
+pub(crate) async fn send_gallery_cover<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
     info: &GalleryInfo,
-    include_save: bool,
-) -> InlineKeyboardMarkup {
-    let hitomi = Url::parse(&info.hitomi_url()).expect("hitomi url should be valid");
-    let k_hentai = Url::parse(&info.k_hentai_url()).expect("k-hentai url should be valid");
+    rendered: &FilteredGallery,
+    keyboard: InlineKeyboardMarkup,
+) -> bool
+where
+    B: Requester + ?Sized,
+{
+    // 사진 캡션은 최대 1024자입니다.
+    if rendered.text.chars().count() > 1024 {
+        return false;
+    }
+    let Some(cover) = state.gallery_client.fetch_cover(info).await else {
+        return false;
+    };
+
+    let photo = InputFile::memory(cover).file_name(format!("{}.webp", info.id));
+    let mut req = bot
+        .send_photo(msg.chat.id, photo)
+        .caption(rendered.text.clone())
+        .parse_mode(ParseMode::Html)
+        .has_spoiler(rendered.spoiler)
+        .reply_markup(keyboard)
+        .reply_parameters(ReplyParameters::new(msg.id).allow_sending_without_reply());
+    if let Some(thread_id) = msg.thread_id {
+        req = req.message_thread_id(thread_id);
+    }
+
+    match req.await {
+        Ok(_) => true,
+        Err(err) => {
+            warn!("표지 사진 전송 실패 (ID {}): {}", info.id, err);
+            false
+        }
+    }
+}
+
+fn field(label: &str, value: &str) -> String {
+    format!("<b>{label}:</b> {value}")
+}
 
-    let mut rows = vec![
-        vec![InlineKeyboardButton::url("Hitomi.la에서 보기", hitomi)],
-        vec![InlineKeyboardButton::url("K-Hentai에서 보기", k_hentai)],
-    ];
+fn join_escaped(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|v| html::escape(v))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
-    if include_save {
-        rows.push(vec![InlineKeyboardButton::callback(
-            "저장: 제 개인 메시지로 보내기",
-            format!("save_{}", info.id),
-        )]);
+fn join_or_unknown(values: &[String]) -> String {
+    if values.is_empty() {
+        "정보 없음".to_string()
+    } else {
+        join_escaped(values)
     }
+}
+
+/// 즐겨찾기 버튼 표시 방식. 그룹처럼 보는 사람마다 상태가 다른 곳에서는 `Toggle`을 씁니다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FavoriteButton {
+    Add,
+    Remove,
+    Toggle,
+}
+
+impl FavoriteButton {
+    /// 개인 채팅에서는 사용자의 현재 즐겨찾기 상태를, 그 밖에서는 `Toggle`을 돌려줍니다.
+    pub(crate) async fn for_chat(
+        state: &AppState,
+        is_private: bool,
+        user: Option<UserId>,
+        gallery_id: &str,
+    ) -> Self {
+        let Some(user) = user.filter(|_| is_private) else {
+            return FavoriteButton::Toggle;
+        };
+        match state.favorites.contains(user.0, gallery_id).await {
+            Ok(true) => FavoriteButton::Remove,
+            Ok(false) => FavoriteButton::Add,
+            Err(err) => {
+                warn!("즐겨찾기 상태 확인 실패 (user {}): {}", user, err);
+                FavoriteButton::Toggle
+            }
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            FavoriteButton::Add => "☆ 즐겨찾기 추가",
+            FavoriteButton::Remove => "★ 즐겨찾기 해제",
+            FavoriteButton::Toggle => "⭐ 즐겨찾기 추가/해제",
+        }
+    }
+}
+
+/// 미러 링크 버튼(한 줄에 하나)과 즐겨찾기 버튼. 링크는 `AppState::mirror_links`로 구합니다.
+pub(crate) fn build_gallery_keyboard(
+    info: &GalleryInfo,
+    links: &[MirrorLink],
+    favorite: FavoriteButton,
+) -> InlineKeyboardMarkup {
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = links
+        .iter()
+        .map(|link| {
+            vec![InlineKeyboardButton::url(
+                format!("{}에서 보기", link.name),
+                link.url.clone(),
+            )]
+        })
+        .collect();
+
+    rows.push(vec![InlineKeyboardButton::callback(
+        favorite.label(),
+        format!("{}{}", FAVORITE_PREFIX, info.id),
+    )]);
 
     InlineKeyboardMarkup::new(rows)
 }
@@ -91,3 +462,170 @@ pub(crate) fn build_gallery_keyboard(
 pub(crate) fn is_private_chat(msg: &Message) -> bool {
     matches!(msg.chat.kind, ChatKind::Private(_))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitomi::GalleryTag;
+    use crate::settings::BlacklistMode;
+
+    #[test]
+    fn test_scan_gallery_ids_finds_every_reference() {
+        let text = "!123 !456, https://hitomi.la/galleries/789.html \
+                    hitomi.la/reader/1011.html#3 https://hitomi.la/doujinshi/some-title-korean-1213.html !123";
+        assert_eq!(
+            scan_gallery_ids(text, false),
+            vec!["123", "456", "789", "1011", "1213"]
+        );
+    }
+
+    #[test]
+    fn test_scan_gallery_ids_bare_numbers_need_permission() {
+        assert!(scan_gallery_ids("12345 67890", false).is_empty());
+        assert_eq!(
+            scan_gallery_ids("12345 67890", true),
+            vec!["12345", "67890"]
+        );
+        assert!(scan_gallery_ids("hello!123", true).is_empty());
+        assert_eq!(
+            scan_gallery_ids("@planabot 12345, 67890", true),
+            vec!["12345", "67890"]
+        );
+    }
+
+    #[test]
+    fn test_explicit_request_excludes_pasted_urls() {
+        assert!(is_explicit_request("!123", "planabot"));
+        assert!(is_explicit_request("@PlanaBot 123", "planabot"));
+        assert!(!is_explicit_request(
+            "https://hitomi.la/galleries/123.html",
+            "planabot"
+        ));
+    }
+
+    #[test]
+    fn test_scan_gallery_ids_ignores_numbers_inside_sentences() {
+        assert!(scan_gallery_ids("오늘 3 시에 2 명 모여요", true).is_empty());
+        assert!(scan_gallery_ids("@planabot 내일 10 시", true).is_empty());
+        assert_eq!(
+            scan_gallery_ids("이거 봐 !123 그리고 456", true),
+            vec!["123"]
+        );
+    }
+
+    #[test]
+    fn test_scan_gallery_ids_is_capped() {
+        let text = (1..=20)
+            .map(|i| format!("!{i}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(
+            scan_gallery_ids(&text, false).len(),
+            MAX_GALLERIES_PER_MESSAGE
+        );
+    }
+
+    #[test]
+    fn test_scan_gallery_ids_selects_source_by_prefix_or_url() {
+        let text = "!nh 177013 !NH42 !e 618395/0439fa3666 https://nhentai.net/g/7/ !e nope !123";
+        assert_eq!(
+            scan_gallery_ids(text, false),
+            vec!["nh:177013", "nh:42", "e:618395/0439fa3666", "nh:7", "123"]
+        );
+    }
+
+    #[test]
+    fn test_is_addressed_to_bot() {
+        assert!(is_addressed_to("@PlanaBot 123 456", "planabot"));
+        assert!(!is_addressed_to("hi @planabot 123", "planabot"));
+        assert!(!is_addressed_to("@planabot 123", ""));
+    }
+
+    #[test]
+    fn test_render_gallery_batch_lists_failures() {
+        let results = vec![
+            (
+                "1".to_string(),
+                Ok(GalleryInfo {
+                    id: "1".to_string(),
+                    title: "A & B".to_string(),
+                    page_count: 20,
+                    ..GalleryInfo::default()
+                }),
+            ),
+            ("2".to_string(), Err(GalleryError::NotFound)),
+        ];
+        let text = render_gallery_batch(&results, &ContentPolicy::default());
+        assert!(text.contains("2건 중 1건"));
+        assert!(text.contains("A &amp; B"));
+        assert!(text.contains("20p"));
+        assert!(text.contains("ID 2: 존재하지 않음"));
+        let keyboard = build_batch_keyboard(&results, &ContentPolicy::default());
+        assert_eq!(keyboard.inline_keyboard[0].len(), 1);
+    }
+
+    #[test]
+    fn test_render_gallery_filtered_hides_or_refuses() {
+        let info = GalleryInfo {
+            id: "1".to_string(),
+            title: "T".to_string(),
+            tags: vec![
+                GalleryTag::parse("female:guro"),
+                GalleryTag::parse("full color"),
+            ],
+            ..GalleryInfo::default()
+        };
+        let mut policy = ContentPolicy {
+            blacklist: vec!["guro".to_string()],
+            mode: BlacklistMode::Hide,
+        };
+
+        let rendered = render_gallery_filtered(&info, &policy).unwrap();
+        assert!(rendered.spoiler);
+        assert!(
+            rendered.text.starts_with(
+                "⚠️ 차단된 태그 1개를 숨겼습니다: <tg-spoiler>female:guro</tg-spoiler>"
+            )
+        );
+        assert!(!rendered.text.contains("<b>여성 태그:</b>"));
+        assert!(rendered.text.contains("<b>태그:</b> full color"));
+
+        policy.mode = BlacklistMode::Refuse;
+        assert_eq!(
+            render_gallery_filtered(&info, &policy).unwrap_err(),
+            vec!["female:guro"]
+        );
+        let results = vec![("1".to_string(), Ok(info))];
+        assert!(render_gallery_batch(&results, &policy).contains("ID 1: 차단된 태그 포함"));
+        assert!(
+            build_batch_keyboard(&results, &policy)
+                .inline_keyboard
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_render_gallery_message_groups_tags_by_kind() {
+        let info = GalleryInfo {
+            id: "1".to_string(),
+            title: "<Title>".to_string(),
+            gallery_type: Some("manga".to_string()),
+            page_count: 20,
+            tags: vec![
+                GalleryTag::parse("female:stockings"),
+                GalleryTag::parse("male:glasses"),
+                GalleryTag::parse("full color"),
+            ],
+            ..GalleryInfo::default()
+        };
+
+        let text = render_gallery_message(&info);
+        assert!(text.contains("<b>제목:</b> &lt;Title&gt;"));
+        assert!(text.contains("<b>유형:</b> manga"));
+        assert!(text.contains("<b>페이지:</b> 20"));
+        assert!(text.contains("<b>여성 태그:</b> stockings"));
+        assert!(text.contains("<b>남성 태그:</b> glasses"));
+        assert!(text.contains("<b>태그:</b> full color"));
+        assert!(text.contains("<b>작가:</b> 정보 없음"));
+    }
+}
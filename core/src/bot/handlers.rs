@@ -1,25 +1,53 @@
 use std::time::Instant;
 
-use chrono::{Datelike, Local, Timelike, Weekday};
 use log::error;
+use teloxide::net::Download;
 use teloxide::prelude::*;
-use teloxide::types::{CallbackQuery, ChatAction, Message, ParseMode};
+use teloxide::types::{CallbackQuery, LinkPreviewOptions, Message, ParseMode};
 use teloxide::utils::html;
-use tokio::time::{self, Duration};
 
+use crate::history::LookupSource;
 use crate::planabrain;
+use crate::urlchanger;
 
 use super::commands::Command;
+use super::favorite_commands::{
+    handle_favorite_callback, handle_favs_command, is_favorite_callback,
+};
+use super::filter_commands::{handle_filter_command, handle_my_filter_command};
+use super::follow_commands::{handle_follow_command, handle_unfollow_command};
 use super::gallery::{
-    build_gallery_keyboard, extract_gallery_id, is_private_chat, render_gallery_message,
+    FavoriteButton, adult_disabled_message, build_batch_keyboard, build_gallery_keyboard,
+    cover_enabled, extract_gallery_ids, gallery_error_message, is_explicit_request,
+    is_private_chat, is_visible, lookup_concurrency, refused_message, render_gallery_batch,
+    render_gallery_filtered, send_gallery_cover,
+};
+use super::history_commands::{handle_recent_command, handle_stats_command};
+use super::knowledge_commands::handle_kb_command;
+use super::memory_commands::handle_memory_command;
+use super::mirror_commands::handle_mirrors_command;
+use super::planabrain_actions::{
+    Turn, ask_with_typing, handle_planabrain_callback, is_planabrain_callback,
+    send_planabrain_answer,
 };
-use super::telegram::{send_reply_with_fallback, SendOptions};
+use super::planabrain_sessions::PlanabrainSession;
+use super::search::{handle_search_callback, handle_search_command, is_search_callback};
+use super::telegram::{SendOptions, send_reply_with_fallback};
 use super::{AppState, HandlerResult};
 
-pub(crate) async fn handle_command<B>(bot: B, msg: Message, cmd: Command, state: AppState) -> HandlerResult
+pub(crate) async fn handle_command<B>(
+    bot: B,
+    msg: Message,
+    cmd: Command,
+    state: AppState,
+) -> HandlerResult
 where
-    B: Requester + Send + Sync + 'static,
-    B::Err: std::error::Error + Send + Sync + 'static,
+    B: Requester + Download + Send + Sync + 'static,
+    <B as Requester>::Err: std::error::Error + Send + Sync + 'static,
+    for<'dst> <B as Download>::Err<'dst>: std::fmt::Display,
+    <B as Requester>::SendDocument: Send,
+    <B as Requester>::GetChatMember: Send,
+    <B as Requester>::GetFile: Send,
 {
     if cmd != Command::Ping && !state.is_after_boot(&msg) {
         return Ok(());
@@ -60,47 +88,64 @@ where
             .await?;
         }
         Command::MemoryReset => {
-            let Some(user) = msg.from.as_ref() else {
-                send_reply_with_fallback(
-                    &bot,
-                    &msg,
-                    "선생님, 사용자 정보를 확인할 수 없습니다.",
-                    SendOptions::default(),
-                )
-                .await?;
+            handle_memory_command(&bot, &msg, &state, "reset").await?;
+        }
+        Command::Memory(args) => {
+            handle_memory_command(&bot, &msg, &state, &args).await?;
+        }
+        Command::Kb(args) => {
+            handle_kb_command(&bot, &msg, &state, &args).await?;
+        }
+        Command::Favs(args) => {
+            handle_favs_command(&bot, &msg, &state, &args).await?;
+        }
+        Command::Filter(args) => {
+            handle_filter_command(&bot, &msg, &state, &args).await?;
+        }
+        Command::MyFilter(args) => {
+            handle_my_filter_command(&bot, &msg, &state, &args).await?;
+        }
+        Command::Recent(args) => {
+            handle_recent_command(&bot, &msg, &state, &args).await?;
+        }
+        Command::Stats(args) => {
+            handle_stats_command(&bot, &msg, &state, &args).await?;
+        }
+        Command::Follow(args) => {
+            handle_follow_command(&bot, &msg, &state, &args).await?;
+        }
+        Command::Unfollow(args) => {
+            handle_unfollow_command(&bot, &msg, &state, &args).await?;
+        }
+        Command::Mirrors(args) => {
+            handle_mirrors_command(&bot, &msg, &state, &args).await?;
+        }
+        Command::Search(args) => {
+            handle_search_command(&bot, &msg, &state, &args).await?;
+        }
+        Command::Cache(args) => {
+            if !state.is_owner(&msg) {
                 return Ok(());
+            }
+
+            let text = if args.trim().eq_ignore_ascii_case("clear") {
+                let removed = state.gallery_client.clear_cache();
+                state.gallery_client.persist_cache().await;
+                format!("선생님, 갤러리 캐시 {}개 항목을 비웠습니다.", removed)
+            } else {
+                let stats = state.gallery_client.cache_stats();
+                format!(
+                    "선생님, 갤러리 캐시 상태입니다.\n항목: {}\n적중: {} (없음 캐시 {})\n미스: {}\n축출: {}\n적중률: {:.1}%",
+                    stats.entries,
+                    stats.hits,
+                    stats.negative_hits,
+                    stats.misses,
+                    stats.evictions,
+                    stats.hit_rate() * 100.0
+                )
             };
 
-            match planabrain::reset_user_memory(&user.id.to_string()).await {
-                Ok(true) => {
-                    send_reply_with_fallback(
-                        &bot,
-                        &msg,
-                        "선생님, 메모리를 초기화했습니다. 새 대화를 시작할 수 있습니다.",
-                        SendOptions::default(),
-                    )
-                    .await?;
-                }
-                Ok(false) => {
-                    send_reply_with_fallback(
-                        &bot,
-                        &msg,
-                        "선생님, 초기화할 메모리가 없습니다.",
-                        SendOptions::default(),
-                    )
-                    .await?;
-                }
-                Err(err) => {
-                    error!("메모리 초기화 실패: {}", err);
-                    send_reply_with_fallback(
-                        &bot,
-                        &msg,
-                        "선생님, 메모리 초기화에 실패했습니다. 잠시 후 다시 시도해 주십시오.",
-                        SendOptions::default(),
-                    )
-                    .await?;
-                }
-            }
+            send_reply_with_fallback(&bot, &msg, text, SendOptions::default()).await?;
         }
     }
 
@@ -168,42 +213,10 @@ where
         .map(|user| user.id.to_string())
         .unwrap_or_else(|| "unknown".to_string());
 
-    let question = format_question_with_timestamp(&question);
-    send_typing_in_thread(&bot, &msg).await;
-    let mut typing_interval = time::interval(Duration::from_secs(3));
-    let ask_fut = planabrain::run_planabrain_ask(&question, &user_id);
-    tokio::pin!(ask_fut);
-
-    let answer = loop {
-        tokio::select! {
-            _ = typing_interval.tick() => {
-                send_typing_in_thread(&bot, &msg).await;
-            }
-            result = &mut ask_fut => {
-                break result;
-            }
-        }
-    };
-
-    match answer {
-        Ok(answer) => {
-            let reply = planabrain::truncate_message(answer.trim(), 4000);
-            let sent =
-                send_reply_with_fallback(&bot, &msg, reply, SendOptions::default()).await?;
-            state.record_planabrain_reply(&sent).await;
-        }
-        Err(err) => {
-            error!("planabrain 응답 실패: {}", err);
-            let sent = send_reply_with_fallback(
-                &bot,
-                &msg,
-                "선생님, 응답 생성에 실패했습니다. 잠시 후 다시 시도해 주십시오.",
-                SendOptions::default(),
-            )
-            .await?;
-            state.record_planabrain_reply(&sent).await;
-        }
-    }
+    let asker = msg.from.as_ref().map(|user| user.id);
+    let answer = ask_with_typing(&bot, &msg, &state, &question, &user_id, Turn::Ask).await;
+    let session = PlanabrainSession::new(msg.chat.id, asker, user_id, question, String::new());
+    send_planabrain_answer(&bot, &msg, &state, session, answer).await?;
 
     Ok(())
 }
@@ -212,6 +225,7 @@ pub(crate) async fn handle_message<B>(bot: B, msg: Message, state: AppState) ->
 where
     B: Requester + Send + Sync + 'static,
     B::Err: std::error::Error + Send + Sync + 'static,
+    <B as Requester>::SendPhoto: Send,
 {
     if !state.is_after_boot(&msg) {
         return Ok(());
@@ -224,9 +238,26 @@ where
         None => return Ok(()),
     };
 
-    let Some(gallery_id) = extract_gallery_id(text, &msg, &state.bot_username) else {
+    let gallery_ids = extract_gallery_ids(text, &msg, &state.bot_username);
+    if gallery_ids.is_empty() {
+        return Ok(());
+    }
+    if !state.adult_allowed(&msg) {
+        // URL이 지나가기만 한 경우에는 조용히 넘어가고, 직접 요청했을 때만 안내합니다.
+        if is_explicit_request(text, &state.bot_username) {
+            send_reply_with_fallback(&bot, &msg, adult_disabled_message(), SendOptions::default())
+                .await?;
+        }
+        return Ok(());
+    }
+
+    let [gallery_id] = gallery_ids.as_slice() else {
+        if gallery_ids.len() > 1 {
+            return send_gallery_batch(&bot, &msg, &state, &gallery_ids).await;
+        }
         return Ok(());
     };
+    let gallery_id = gallery_id.clone();
 
     let chat_id = msg.chat.id;
     let initial = send_reply_with_fallback(
@@ -243,15 +274,48 @@ where
     )
     .await?;
 
-    let info = state.gallery_client.get_gallery_info(&gallery_id).await?;
+    match state.galleries.get(&gallery_id).await {
+        Ok(info) => {
+            let favorite = FavoriteButton::for_chat(
+                &state,
+                is_private_chat(&msg),
+                msg.from.as_ref().map(|user| user.id),
+                &info.id,
+            )
+            .await;
+            let keyboard = build_gallery_keyboard(
+                &info,
+                &state.mirror_links(Some(chat_id), &info.id),
+                favorite,
+            );
 
-    match info {
-        Some(info) => {
-            let response = render_gallery_message(&info, false);
-            let keyboard = build_gallery_keyboard(&info, !is_private_chat(&msg));
+            let rendered = match render_gallery_filtered(&info, &state.content_policy(&msg)) {
+                Ok(rendered) => rendered,
+                Err(tags) => {
+                    if let Err(err) = bot
+                        .edit_message_text(chat_id, initial.id, refused_message(&gallery_id, &tags))
+                        .await
+                    {
+                        error!("오류 메시지 수정 실패 (ID {}): {}", gallery_id, err);
+                    }
+                    return Ok(());
+                }
+            };
+            state
+                .record_lookup(chat_id, msg.from.as_ref(), &info, LookupSource::Message)
+                .await;
+
+            if cover_enabled()
+                && send_gallery_cover(&bot, &msg, &state, &info, &rendered, keyboard.clone()).await
+            {
+                if let Err(err) = bot.delete_message(chat_id, initial.id).await {
+                    error!("검색 안내 메시지 삭제 실패 (ID {}): {}", gallery_id, err);
+                }
+                return Ok(());
+            }
 
             if let Err(err) = bot
-                .edit_message_text(chat_id, initial.id, response)
+                .edit_message_text(chat_id, initial.id, rendered.text)
                 .parse_mode(ParseMode::Html)
                 .reply_markup(keyboard)
                 .await
@@ -259,11 +323,8 @@ where
                 error!("메시지 수정 실패 (ID {}): {}", gallery_id, err);
             }
         }
-        None => {
-            let error_text = format!(
-                "선생님, ID {}에 대한 정보를 찾을 수 없거나, 제목 데이터가 누락된 것으로 확인됩니다.",
-                gallery_id
-            );
+        Err(err) => {
+            let error_text = gallery_error_message(&gallery_id, &err);
 
             if let Err(err) = bot.edit_message_text(chat_id, initial.id, error_text).await {
                 error!("오류 메시지 수정 실패 (ID {}): {}", gallery_id, err);
@@ -274,61 +335,107 @@ where
     Ok(())
 }
 
-pub(crate) async fn handle_callback<B>(bot: B, query: CallbackQuery, state: AppState) -> HandlerResult
+async fn send_gallery_batch<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    gallery_ids: &[String],
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let initial = send_reply_with_fallback(
+        bot,
+        msg,
+        format!(
+            "선생님, 요청하신 {}건(ID {})에 대한 데이터 검색을 시작합니다. 잠시만 기다려주십시오...",
+            gallery_ids.len(),
+            gallery_ids.join(", ")
+        ),
+        SendOptions {
+            disable_notification: Some(true),
+            ..SendOptions::default()
+        },
+    )
+    .await?;
+
+    let results = state
+        .galleries
+        .get_many(gallery_ids, lookup_concurrency())
+        .await;
+
+    let policy = state.content_policy(msg);
+    for (_, result) in &results {
+        if let Ok(info) = result
+            && is_visible(info, &policy)
+        {
+            state
+                .record_lookup(msg.chat.id, msg.from.as_ref(), info, LookupSource::Message)
+                .await;
+        }
+    }
+    let keyboard = build_batch_keyboard(&results, &policy);
+    if let Err(err) = bot
+        .edit_message_text(
+            msg.chat.id,
+            initial.id,
+            render_gallery_batch(&results, &policy),
+        )
+        .parse_mode(ParseMode::Html)
+        .link_preview_options(LinkPreviewOptions {
+            is_disabled: true,
+            url: None,
+            prefer_small_media: false,
+            prefer_large_media: false,
+            show_above_text: false,
+        })
+        .reply_markup(keyboard)
+        .await
+    {
+        error!(
+            "일괄 조회 결과 메시지 수정 실패 (chat {}): {}",
+            msg.chat.id, err
+        );
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn handle_callback<B>(
+    bot: B,
+    query: CallbackQuery,
+    state: AppState,
+) -> HandlerResult
 where
     B: Requester + Send + Sync + 'static,
     B::Err: std::error::Error + Send + Sync + 'static,
+    B::SendChatAction: Send,
+    B::SendMessage: Send,
+    B::CopyMessage: Send,
 {
     let Some(data) = query.data.clone() else {
         bot.answer_callback_query(query.id).await?;
         return Ok(());
     };
 
-    if let Some(gallery_id) = data.strip_prefix("save_") {
-        let info = state.gallery_client.get_gallery_info(gallery_id).await?;
-
-        match info {
-            Some(info) => {
-                let message = render_gallery_message(&info, true);
-                let keyboard = build_gallery_keyboard(&info, false);
-
-                let user = query.from.id;
-                if let Err(err) = bot
-                    .send_message(user, message)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .disable_notification(true)
-                    .await
-                {
-                    error!(
-                        "개인 메시지 전송 실패 (user {:?}, id {}): {}",
-                        user, info.id, err
-                    );
-
-                    let _ = bot
-                        .answer_callback_query(query.id)
-                        .text("선생님, 먼저 저와 개인 대화를 시작하거나 차단을 해제해 주세요.")
-                        .show_alert(true)
-                        .await;
-                } else {
-                    let _ = bot
-                        .answer_callback_query(query.id)
-                        .text("갤러리 정보가 저와 선생님의 메시지로 전송되었습니다.")
-                        .await;
-                }
-            }
-            None => {
-                let _ = bot
-                    .answer_callback_query(query.id)
-                    .text("저장할 갤러리 정보를 찾지 못했습니다.")
-                    .show_alert(true)
-                    .await;
-            }
-        }
-    } else {
-        bot.answer_callback_query(query.id).await?;
+    if is_planabrain_callback(&data) {
+        return handle_planabrain_callback(bot, query, state, &data).await;
+    }
+
+    if is_search_callback(&data) {
+        return handle_search_callback(bot, query, state, &data).await;
+    }
+
+    if is_favorite_callback(&data) {
+        return handle_favorite_callback(bot, query, state, &data).await;
     }
 
+    if urlchanger::is_undo_callback(&data) {
+        return urlchanger::handle_undo_callback(bot, query, state).await;
+    }
+
+    bot.answer_callback_query(query.id).await?;
     Ok(())
 }
 
@@ -348,39 +455,3 @@ pub(crate) fn is_plana_trigger(msg: &Message, state: &AppState) -> bool {
 
     state.is_reply_to_planabrain(msg)
 }
-
-async fn send_typing_in_thread<B>(bot: &B, msg: &Message)
-where
-    B: Requester + ?Sized,
-    B::SendChatAction: Send,
-{
-    let mut req = bot.send_chat_action(msg.chat.id, ChatAction::Typing);
-    if let Some(thread_id) = msg.thread_id {
-        req = req.message_thread_id(thread_id);
-    }
-    let _ = req.await;
-}
-
-fn format_question_with_timestamp(question: &str) -> String {
-    let now = Local::now();
-    let weekday = match now.weekday() {
-        Weekday::Mon => "월",
-        Weekday::Tue => "화",
-        Weekday::Wed => "수",
-        Weekday::Thu => "목",
-        Weekday::Fri => "금",
-        Weekday::Sat => "토",
-        Weekday::Sun => "일",
-    };
-    let timestamp = format!(
-        "{:04}-{:02}-{:02} ({}) {:02}:{:02}:{:02}",
-        now.year(),
-        now.month(),
-        now.day(),
-        weekday,
-        now.hour(),
-        now.minute(),
-        now.second()
-    );
-    format!("현재 시각: {}\n\n{}", timestamp, question)
-}
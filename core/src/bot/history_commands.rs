@@ -0,0 +1,302 @@
+use chrono::{Local, TimeZone};
+use log::error;
+use teloxide::prelude::*;
+use teloxide::types::{Message, ParseMode};
+use teloxide::utils::html;
+
+use crate::history::{LookupRecord, LookupSource, LookupStats};
+use crate::planabrain::truncate_message;
+
+use super::gallery::adult_disabled_message;
+use super::telegram::{SendOptions, is_chat_admin, send_reply_with_fallback};
+use super::{AppState, HandlerResult};
+
+const DEFAULT_RECENT: usize = 10;
+const MAX_RECENT: usize = 50;
+const DEFAULT_STATS_DAYS: i64 = 30;
+const STATS_TOP: usize = 10;
+const TITLE_CHARS: usize = 60;
+/// Telegram 메시지 길이 제한(4096자)보다 조금 작게 잡은 메시지 하나의 길이.
+const MESSAGE_CHARS: usize = 4000;
+
+pub(crate) async fn handle_recent_command<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    args: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let args = args.trim().to_lowercase();
+    let chat_id = msg.chat.id.0;
+
+    if matches!(args.as_str(), "on" | "off" | "clear") {
+        if !is_chat_admin(bot, msg).await {
+            return reply(
+                bot,
+                msg,
+                "선생님, 조회 기록 설정은 관리자만 변경할 수 있습니다.",
+            )
+            .await;
+        }
+        let text = match args.as_str() {
+            "clear" => match state.history.clear(chat_id).await {
+                Ok(true) => "선생님, 이 채팅의 조회 기록을 모두 삭제했습니다.".to_string(),
+                Ok(false) => "선생님, 삭제할 조회 기록이 없습니다.".to_string(),
+                Err(err) => {
+                    error!("조회 기록 삭제 실패 (chat {}): {}", chat_id, err);
+                    "선생님, 조회 기록을 삭제하지 못했습니다.".to_string()
+                }
+            },
+            mode => {
+                let disabled = mode == "off";
+                match state
+                    .settings
+                    .update_chat(chat_id, |chat| chat.history_disabled = disabled)
+                    .await
+                {
+                    Ok(_) if disabled => "선생님, 이제 이 채팅의 조회 기록을 남기지 않습니다. 기존 기록은 /recent clear 로 삭제할 수 있습니다.".to_string(),
+                    Ok(_) => "선생님, 이 채팅의 조회 기록을 다시 남깁니다.".to_string(),
+                    Err(err) => {
+                        error!("조회 기록 설정 저장 실패 (chat {}): {}", chat_id, err);
+                        "선생님, 설정을 저장하지 못했습니다.".to_string()
+                    }
+                }
+            }
+        };
+        return reply(bot, msg, text).await;
+    }
+
+    let limit = if args.is_empty() {
+        DEFAULT_RECENT
+    } else {
+        match args.parse::<usize>() {
+            Ok(limit) if limit > 0 => limit.min(MAX_RECENT),
+            _ => {
+                return reply(
+                    bot,
+                    msg,
+                    "선생님, 사용법: /recent [개수], /recent on|off|clear (설정은 관리자만)",
+                )
+                .await;
+            }
+        }
+    };
+
+    if !state.adult_allowed(msg) {
+        return reply(bot, msg, adult_disabled_message()).await;
+    }
+
+    let records = match state.history.recent(chat_id, limit).await {
+        Ok(records) => records,
+        Err(err) => {
+            error!("조회 기록 읽기 실패 (chat {}): {}", chat_id, err);
+            return reply(bot, msg, "선생님, 조회 기록을 불러오지 못했습니다.").await;
+        }
+    };
+    if records.is_empty() {
+        let text = if state.settings.history_enabled(chat_id) {
+            "선생님, 아직 이 채팅의 조회 기록이 없습니다."
+        } else {
+            "선생님, 이 채팅은 조회 기록을 남기지 않도록 설정되어 있습니다. (/recent on)"
+        };
+        return reply(bot, msg, text).await;
+    }
+
+    // 최대 개수를 요청하면 한 메시지에 다 들어가지 않으므로 나눠서 보냅니다.
+    for text in render_recent(&records) {
+        send_html(bot, msg, text).await?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn handle_stats_command<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    args: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let args = args.trim();
+    let days = if args.is_empty() {
+        DEFAULT_STATS_DAYS
+    } else {
+        match args.trim_end_matches('d').parse::<i64>() {
+            Ok(days) if days > 0 => days,
+            _ => return reply(bot, msg, "선생님, 사용법: /stats [일 수] (예: /stats 7)").await,
+        }
+    };
+    // 보관 기간보다 긴 기간은 의미가 없으므로 줄여서 안내합니다.
+    let days = state
+        .history
+        .retention_days()
+        .map_or(days, |retention| days.min(retention));
+
+    if !state.adult_allowed(msg) {
+        return reply(bot, msg, adult_disabled_message()).await;
+    }
+
+    let stats = match state.history.stats(msg.chat.id.0, days, STATS_TOP).await {
+        Ok(stats) => stats,
+        Err(err) => {
+            error!("조회 통계 계산 실패 (chat {}): {}", msg.chat.id, err);
+            return reply(bot, msg, "선생님, 조회 기록을 불러오지 못했습니다.").await;
+        }
+    };
+    if stats.lookups == 0 {
+        return reply(
+            bot,
+            msg,
+            format!("선생님, 최근 {}일 동안의 조회 기록이 없습니다.", days),
+        )
+        .await;
+    }
+
+    send_html(bot, msg, render_stats(&stats, days)).await
+}
+
+fn render_recent(records: &[LookupRecord]) -> Vec<String> {
+    let lines = records.iter().map(|record| {
+        let at = Local
+            .timestamp_opt(record.at, 0)
+            .single()
+            .map(|dt| dt.format("%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let who = record.user_name.as_deref().unwrap_or("알 수 없음");
+        let via = match record.source {
+            LookupSource::Message => "",
+            LookupSource::Inline => " (인라인)",
+        };
+        format!(
+            "[{}] <a href=\"{}\">{}</a> <code>{}</code> — {}{}",
+            at,
+            html::escape(&record.page_url()),
+            html::escape(&truncate_message(&record.title, TITLE_CHARS)),
+            record.gallery_id,
+            html::escape(who),
+            via
+        )
+    });
+
+    let mut messages = vec![format!(
+        "선생님, 이 채팅의 최근 조회 {}건입니다.\n",
+        records.len()
+    )];
+    for line in lines {
+        let current = messages.last_mut().expect("messages starts non-empty");
+        if current.chars().count() + line.chars().count() + 1 > MESSAGE_CHARS {
+            messages.push(line);
+        } else {
+            current.push('\n');
+            current.push_str(&line);
+        }
+    }
+    messages
+}
+
+fn render_stats(stats: &LookupStats, days: i64) -> String {
+    let mut text = format!(
+        "선생님, 최근 {}일 동안의 조회 통계입니다.\n조회 {}회 · 갤러리 {}개",
+        days, stats.lookups, stats.galleries
+    );
+    for (label, counts) in [
+        ("태그", &stats.tags),
+        ("작가", &stats.artists),
+        ("언어", &stats.languages),
+    ] {
+        if counts.is_empty() {
+            continue;
+        }
+        let items: Vec<String> = counts
+            .iter()
+            .map(|(name, count)| format!("{} ({})", html::escape(name), count))
+            .collect();
+        text.push_str(&format!("\n\n<b>{}</b>\n{}", label, items.join(", ")));
+    }
+    text
+}
+
+async fn send_html<B>(bot: &B, msg: &Message, text: String) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    send_reply_with_fallback(
+        bot,
+        msg,
+        text,
+        SendOptions {
+            disable_preview: Some(true),
+            parse_mode: Some(ParseMode::Html),
+            ..SendOptions::default()
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+async fn reply<B>(bot: &B, msg: &Message, text: impl Into<String>) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    send_reply_with_fallback(bot, msg, text, SendOptions::default()).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_recent_splits_long_lists_under_message_limit() {
+        let records: Vec<_> = (0..MAX_RECENT)
+            .map(|i| LookupRecord {
+                gallery_id: (1_000_000 + i).to_string(),
+                title: "가".repeat(TITLE_CHARS * 2),
+                artists: Vec::new(),
+                language: None,
+                tags: Vec::new(),
+                user_id: None,
+                user_name: Some("선생님".repeat(10)),
+                source: LookupSource::Message,
+                at: 0,
+            })
+            .collect();
+        let messages = render_recent(&records);
+
+        assert!(messages.len() > 1);
+        assert!(
+            messages
+                .iter()
+                .all(|text| text.chars().count() <= MESSAGE_CHARS)
+        );
+        assert!(messages[0].starts_with("선생님, 이 채팅의 최근 조회 50건입니다.\n\n["));
+        let total: usize = messages
+            .iter()
+            .map(|text| text.matches("<code>").count())
+            .sum();
+        assert_eq!(total, MAX_RECENT);
+    }
+
+    #[test]
+    fn test_render_stats_lists_sections_in_order() {
+        let stats = LookupStats {
+            lookups: 3,
+            galleries: 2,
+            tags: vec![("female:glasses".to_string(), 3)],
+            artists: Vec::new(),
+            languages: vec![("korean".to_string(), 3)],
+        };
+        let text = render_stats(&stats, 7);
+        assert!(text.contains("조회 3회 · 갤러리 2개"));
+        assert!(text.contains("<b>태그</b>\nfemale:glasses (3)"));
+        assert!(!text.contains("작가"));
+        assert!(text.find("태그").unwrap() < text.find("언어").unwrap());
+    }
+}
@@ -0,0 +1,215 @@
+use log::error;
+use teloxide::prelude::*;
+use teloxide::types::{
+    ChatId, ChatType, ChosenInlineResult, InlineQuery, InlineQueryResult, InlineQueryResultArticle,
+    InputMessageContent, InputMessageContentText, LinkPreviewOptions, ParseMode,
+};
+
+use crate::history::LookupSource;
+use crate::hitomi::GalleryInfo;
+use crate::mirrors::MirrorLink;
+use crate::settings::ContentPolicy;
+use crate::urlchanger::{LinkRewriter, apply_conversions, message_preview};
+
+use super::gallery::{
+    FavoriteButton, build_gallery_keyboard, lookup_concurrency, render_gallery_filtered,
+    scan_gallery_ids,
+};
+use super::{AppState, HandlerResult};
+
+/// 인라인 결과를 Telegram이 캐시하는 시간(초).
+const INLINE_CACHE_SECS: u32 = 300;
+
+const GALLERY_RESULT_PREFIX: &str = "gallery_";
+
+/// `@봇계정 <ID 또는 URL>` 인라인 질의에 갤러리 정보나 변환된 링크로 답합니다.
+pub(crate) async fn handle_inline_query<B>(
+    bot: B,
+    query: InlineQuery,
+    state: AppState,
+) -> HandlerResult
+where
+    B: Requester + Send + Sync + 'static,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let text = query.query.trim();
+    let results = if text.is_empty() {
+        Vec::new()
+    } else {
+        let gallery_ids = scan_gallery_ids(text, true);
+        if gallery_ids.is_empty() {
+            link_results(&state.links, text).await
+        } else if !inline_adult_allowed(query.chat_type.as_ref(), state.settings.adult_default()) {
+            Vec::new()
+        } else {
+            // 인라인 질의는 어느 채팅에서 보낼지 알 수 없으므로 사용자 차단 목록만 적용합니다.
+            let policy = state.settings.content_policy(None, Some(query.from.id.0));
+            state
+                .galleries
+                .get_many(&gallery_ids, lookup_concurrency())
+                .await
+                .into_iter()
+                .filter_map(|(_, result)| result.ok())
+                .filter_map(|info| {
+                    let links = state.mirror_links(None, &info.id);
+                    gallery_result(&info, &links, &policy)
+                })
+                .collect()
+        }
+    };
+
+    if let Err(err) = bot
+        .answer_inline_query(query.id, results)
+        .cache_time(INLINE_CACHE_SECS)
+        .is_personal(true)
+        .await
+    {
+        error!("인라인 질의 응답 실패 ({:?}): {}", query.query, err);
+    }
+
+    Ok(())
+}
+
+/// 인라인 질의는 보낼 채팅의 종류만 알려 주고 어느 채팅인지는 알려 주지 않습니다.
+/// 그래서 그룹·채널에서는 `/filter adult on`을 켠 채팅이어도 `PLANABOT_ADULT_DEFAULT`만 보고
+/// 판단합니다. 종류를 알 수 없는 질의(비밀 채팅 등)도 그룹과 같이 취급합니다.
+fn inline_adult_allowed(chat_type: Option<&ChatType>, adult_default: bool) -> bool {
+    adult_default || matches!(chat_type, Some(ChatType::Sender | ChatType::Private))
+}
+
+/// 사용자가 인라인 갤러리 결과를 실제로 보냈을 때 조회 기록을 남깁니다.
+/// 보낸 채팅은 알 수 없으므로 사용자의 개인 채팅 기록에 남깁니다.
+/// (BotFather에서 `/setinlinefeedback`을 켜야 전달됩니다)
+pub(crate) async fn handle_chosen_inline_result(
+    chosen: ChosenInlineResult,
+    state: AppState,
+) -> HandlerResult {
+    let Some(gallery_id) = chosen.result_id.strip_prefix(GALLERY_RESULT_PREFIX) else {
+        return Ok(());
+    };
+    // 방금 질의에 답하면서 캐시에 들어 있으므로 다시 내려받지 않습니다.
+    if let Ok(info) = state.galleries.get(gallery_id).await {
+        let chat_id = ChatId(chosen.from.id.0 as i64);
+        state
+            .record_lookup(chat_id, Some(&chosen.from), &info, LookupSource::Inline)
+            .await;
+    }
+    Ok(())
+}
+
+fn gallery_result(
+    info: &GalleryInfo,
+    links: &[MirrorLink],
+    policy: &ContentPolicy,
+) -> Option<InlineQueryResult> {
+    let rendered = render_gallery_filtered(info, policy).ok()?;
+    let content = InputMessageContentText::new(rendered.text).parse_mode(ParseMode::Html);
+    let mut description = vec![format!("ID {}", info.id)];
+    if !info.artists.is_empty() {
+        description.push(info.artists.join(", "));
+    }
+    if let Some(language) = &info.language {
+        description.push(language.clone());
+    }
+
+    let article = InlineQueryResultArticle::new(
+        format!("{}{}", GALLERY_RESULT_PREFIX, info.id),
+        info.title.clone(),
+        InputMessageContent::Text(content),
+    )
+    .description(description.join(" · "))
+    .reply_markup(build_gallery_keyboard(info, links, FavoriteButton::Toggle));
+    Some(article.into())
+}
+
+async fn link_results(rewriter: &LinkRewriter, text: &str) -> Vec<InlineQueryResult> {
+    let links = rewriter.rewrite_expanded(text).await;
+    if links.is_empty() {
+        return Vec::new();
+    }
+
+    let converted = apply_conversions(text, &links);
+    let mut results = vec![article(
+        "links_all",
+        "변환된 링크로 보내기",
+        &converted,
+        &converted,
+        message_preview(&links),
+    )];
+    if links.len() > 1 {
+        results.extend(links.iter().enumerate().map(|(index, link)| {
+            let url = &link.converted;
+            let preview = message_preview(std::slice::from_ref(link));
+            article(
+                &format!("links_{}", index),
+                &format!("링크 #{}", index + 1),
+                url,
+                url,
+                preview,
+            )
+        }));
+    }
+    results
+}
+
+fn article(
+    id: &str,
+    title: &str,
+    description: &str,
+    message: &str,
+    preview: LinkPreviewOptions,
+) -> InlineQueryResult {
+    let content = InputMessageContentText {
+        message_text: message.to_string(),
+        parse_mode: None,
+        entities: None,
+        link_preview_options: Some(preview),
+    };
+    InlineQueryResultArticle::new(id, title, InputMessageContent::Text(content))
+        .description(description)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_galleries_follow_adult_default_outside_private_chats() {
+        assert!(inline_adult_allowed(Some(&ChatType::Sender), false));
+        assert!(inline_adult_allowed(Some(&ChatType::Private), false));
+        assert!(!inline_adult_allowed(Some(&ChatType::Group), false));
+        assert!(!inline_adult_allowed(Some(&ChatType::Supergroup), false));
+        assert!(!inline_adult_allowed(None, false));
+        assert!(inline_adult_allowed(Some(&ChatType::Supergroup), true));
+    }
+
+    #[tokio::test]
+    async fn test_link_results_offer_whole_text_and_each_link() {
+        let results = link_results(
+            &LinkRewriter::default(),
+            "보세요 https://x.com/a/status/1?s=20 https://youtu.be/b?si=c",
+        )
+        .await;
+        assert_eq!(results.len(), 3);
+        let InlineQueryResult::Article(first) = &results[0] else {
+            panic!("article expected");
+        };
+        let InputMessageContent::Text(content) = &first.input_message_content else {
+            panic!("text content expected");
+        };
+        assert_eq!(
+            content.message_text,
+            "보세요 https://fxtwitter.com/a/status/1 https://youtu.be/b"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_link_results_ignore_plain_text() {
+        assert!(
+            link_results(&LinkRewriter::default(), "그냥 텍스트")
+                .await
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use anyhow::{Result, anyhow};
+use chrono::{Local, TimeZone};
+use log::error;
+use teloxide::net::Download;
+use teloxide::prelude::*;
+use teloxide::types::{Document, Message};
+
+use crate::planabrain::{is_planabrain_allowed, truncate_message};
+
+use super::telegram::{SendOptions, is_chat_admin, send_reply_with_fallback};
+use super::{AppState, HandlerResult};
+
+const MAX_DOCUMENT_BYTES: u32 = 1024 * 1024;
+const TEXT_EXTENSIONS: [&str; 6] = ["txt", "md", "markdown", "csv", "json", "log"];
+const USAGE: &str = "선생님, 사용법입니다.\n\
+- /kb add: 문서나 텍스트 메시지에 답장하여 지식 베이스에 추가 (관리자)\n\
+- /kb ask <질문>: 지식 베이스 기반 답변\n\
+- /kb list: 등록된 항목 목록\n\
+- /kb remove <번호>: 항목 삭제 (관리자)";
+
+pub(crate) async fn handle_kb_command<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    args: &str,
+) -> HandlerResult
+where
+    B: Requester + Download + Send + Sync + 'static,
+    <B as Requester>::Err: std::error::Error + Send + Sync + 'static,
+    for<'dst> <B as Download>::Err<'dst>: std::fmt::Display,
+{
+    let args = args.trim();
+    let (sub, rest) = args
+        .split_once(char::is_whitespace)
+        .map(|(sub, rest)| (sub, rest.trim()))
+        .unwrap_or((args, ""));
+
+    let user_id = msg
+        .from
+        .as_ref()
+        .and_then(|user| i64::try_from(user.id.0).ok());
+    if !is_planabrain_allowed(msg.chat.id.0, user_id, msg.chat.is_private()) {
+        return reply(
+            bot,
+            msg,
+            "선생님, 지식 베이스 기능은 프라나 AI 베타가 허용된 그룹 또는 사용자에게만 제공됩니다.",
+        )
+        .await;
+    }
+
+    match sub.to_lowercase().as_str() {
+        "add" => add_entry(bot, msg, state).await,
+        "ask" => ask(bot, msg, state, rest).await,
+        "list" => list_entries(bot, msg, state).await,
+        "remove" => remove_entry(bot, msg, state, rest).await,
+        _ => reply(bot, msg, USAGE).await,
+    }
+}
+
+async fn add_entry<B>(bot: &B, msg: &Message, state: &AppState) -> HandlerResult
+where
+    B: Requester + Download + ?Sized,
+    <B as Requester>::Err: std::error::Error + Send + Sync + 'static,
+    for<'dst> <B as Download>::Err<'dst>: std::fmt::Display,
+{
+    if !is_chat_admin(bot, msg).await {
+        return reply(
+            bot,
+            msg,
+            "선생님, 지식 베이스 추가는 관리자만 할 수 있습니다.",
+        )
+        .await;
+    }
+
+    let Some(provider) = state.llm.as_deref() else {
+        return reply(bot, msg, "선생님, LLM API 키가 설정되어 있지 않습니다.").await;
+    };
+
+    let Some(source) = msg.reply_to_message() else {
+        return reply(
+            bot,
+            msg,
+            "선생님, 추가할 문서나 텍스트 메시지에 답장하면서 /kb add 를 입력해 주십시오.",
+        )
+        .await;
+    };
+
+    let (title, text) = match source.document() {
+        Some(document) => match read_document(bot, document).await {
+            Ok(content) => content,
+            Err(err) => {
+                return reply(
+                    bot,
+                    msg,
+                    format!("선생님, 문서를 읽지 못했습니다. ({})", err),
+                )
+                .await;
+            }
+        },
+        None => match source.text().or_else(|| source.caption()) {
+            Some(text) => (title_from_text(text), text.to_string()),
+            None => {
+                return reply(
+                    bot,
+                    msg,
+                    "선생님, 텍스트가 있는 메시지나 문서가 필요합니다.",
+                )
+                .await;
+            }
+        },
+    };
+
+    let added_by = msg
+        .from
+        .as_ref()
+        .and_then(|user| i64::try_from(user.id.0).ok())
+        .unwrap_or_default();
+
+    match state
+        .knowledge
+        .add(msg.chat.id.0, provider, &title, &text, added_by)
+        .await
+    {
+        Ok(entry) => {
+            reply(
+                bot,
+                msg,
+                format!(
+                    "선생님, 지식 베이스에 #{} \"{}\"을(를) 추가했습니다. ({}개 조각)",
+                    entry.id,
+                    entry.title,
+                    entry.chunks.len()
+                ),
+            )
+            .await
+        }
+        Err(err) => {
+            error!("지식 베이스 추가 실패 (chat {}): {}", msg.chat.id, err);
+            reply(bot, msg, format!("선생님, 추가에 실패했습니다. ({})", err)).await
+        }
+    }
+}
+
+async fn ask<B>(bot: &B, msg: &Message, state: &AppState, question: &str) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    if question.is_empty() {
+        return reply(bot, msg, "선생님, /kb ask 뒤에 질문을 입력해 주십시오.").await;
+    }
+
+    let Some(provider) = state.llm.as_deref() else {
+        return reply(bot, msg, "선생님, LLM API 키가 설정되어 있지 않습니다.").await;
+    };
+
+    match state.knowledge.ask(msg.chat.id.0, provider, question).await {
+        Ok(Some(answer)) => {
+            let sources = answer
+                .citations
+                .iter()
+                .map(|c| format!("[{}] #{} {}", c.number, c.entry_id, c.title))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let text = format!("{}\n\n출처\n{}", answer.text, sources);
+            reply(bot, msg, truncate_message(&text, 4000)).await
+        }
+        Ok(None) => {
+            reply(
+                bot,
+                msg,
+                "선생님, 이 채팅의 지식 베이스에서 관련 자료를 찾지 못했습니다.",
+            )
+            .await
+        }
+        Err(err) => {
+            error!("지식 베이스 질의 실패 (chat {}): {}", msg.chat.id, err);
+            reply(
+                bot,
+                msg,
+                "선생님, 답변 생성에 실패했습니다. 잠시 후 다시 시도해 주십시오.",
+            )
+            .await
+        }
+    }
+}
+
+async fn list_entries<B>(bot: &B, msg: &Message, state: &AppState) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let index = match state.knowledge.load(msg.chat.id.0).await {
+        Ok(index) => index,
+        Err(err) => {
+            error!("지식 베이스 목록 조회 실패 (chat {}): {}", msg.chat.id, err);
+            return reply(bot, msg, "선생님, 지식 베이스를 불러오지 못했습니다.").await;
+        }
+    };
+
+    let entries = index.map(|index| index.entries).unwrap_or_default();
+    if entries.is_empty() {
+        return reply(bot, msg, "선생님, 이 채팅의 지식 베이스가 비어 있습니다.").await;
+    }
+
+    let lines = entries
+        .iter()
+        .map(|entry| {
+            let added = Local
+                .timestamp_opt(entry.added_at, 0)
+                .single()
+                .map(|dt| dt.format("%Y-%m-%d").to_string())
+                .unwrap_or_default();
+            format!(
+                "#{} {} ({}개 조각, {})",
+                entry.id,
+                entry.title,
+                entry.chunks.len(),
+                added
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    reply(
+        bot,
+        msg,
+        truncate_message(
+            &format!("선생님, 등록된 지식 베이스 항목입니다.\n\n{}", lines),
+            4000,
+        ),
+    )
+    .await
+}
+
+async fn remove_entry<B>(bot: &B, msg: &Message, state: &AppState, arg: &str) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    if !is_chat_admin(bot, msg).await {
+        return reply(
+            bot,
+            msg,
+            "선생님, 지식 베이스 삭제는 관리자만 할 수 있습니다.",
+        )
+        .await;
+    }
+
+    let Ok(entry_id) = arg.trim_start_matches('#').parse::<u32>() else {
+        return reply(
+            bot,
+            msg,
+            "선생님, 삭제할 항목 번호를 입력해 주십시오. (예: /kb remove 3)",
+        )
+        .await;
+    };
+
+    match state.knowledge.remove(msg.chat.id.0, entry_id).await {
+        Ok(Some(entry)) => {
+            reply(
+                bot,
+                msg,
+                format!(
+                    "선생님, #{} \"{}\"을(를) 삭제했습니다.",
+                    entry.id, entry.title
+                ),
+            )
+            .await
+        }
+        Ok(None) => reply(bot, msg, format!("선생님, #{} 항목이 없습니다.", entry_id)).await,
+        Err(err) => {
+            error!("지식 베이스 삭제 실패 (chat {}): {}", msg.chat.id, err);
+            reply(bot, msg, "선생님, 삭제에 실패했습니다.").await
+        }
+    }
+}
+
+async fn read_document<B>(bot: &B, document: &Document) -> Result<(String, String)>
+where
+    B: Requester + Download + ?Sized,
+    <B as Requester>::Err: std::error::Error + Send + Sync + 'static,
+    for<'dst> <B as Download>::Err<'dst>: std::fmt::Display,
+{
+    let name = document
+        .file_name
+        .clone()
+        .unwrap_or_else(|| "문서".to_string());
+    let is_text = document
+        .mime_type
+        .as_ref()
+        .is_some_and(|mime| mime.type_() == "text")
+        || name
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| TEXT_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+    if !is_text {
+        return Err(anyhow!("텍스트 문서만 지원합니다"));
+    }
+    if document.file.size > MAX_DOCUMENT_BYTES {
+        return Err(anyhow!("문서는 1MB 이하만 지원합니다"));
+    }
+
+    let file = bot.get_file(document.file.id.clone()).await?;
+    let mut buffer = Vec::with_capacity(file.size as usize);
+    bot.download_file(&file.path, &mut buffer)
+        .await
+        .map_err(|err| anyhow!("다운로드 실패: {}", err))?;
+
+    let text = String::from_utf8(buffer).map_err(|_| anyhow!("UTF-8 텍스트가 아닙니다"))?;
+    Ok((name, text))
+}
+
+fn title_from_text(text: &str) -> String {
+    let first_line = text
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("");
+    let title: String = first_line.trim().chars().take(40).collect();
+    if title.is_empty() {
+        "메시지".to_string()
+    } else {
+        title
+    }
+}
+
+async fn reply<B>(bot: &B, msg: &Message, text: impl Into<String>) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    send_reply_with_fallback(bot, msg, text, SendOptions::default()).await?;
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use chrono::{Local, TimeZone};
+use log::error;
+use teloxide::prelude::*;
+use teloxide::types::{InputFile, Message, User};
+
+use crate::planabrain::{MemoryRole, MemoryTurn, truncate_message};
+
+use super::telegram::{SendOptions, send_reply_with_fallback};
+use super::{AppState, HandlerResult};
+
+const SHOW_LIMIT: usize = 10;
+const SHOW_TURN_CHARS: usize = 200;
+
+pub(crate) async fn handle_memory_command<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    args: &str,
+) -> HandlerResult
+where
+    B: Requester + Send + Sync + 'static,
+    B::Err: std::error::Error + Send + Sync + 'static,
+    <B as Requester>::SendDocument: Send,
+{
+    let Some(user) = msg.from.as_ref() else {
+        send_reply_with_fallback(
+            bot,
+            msg,
+            "선생님, 사용자 정보를 확인할 수 없습니다.",
+            SendOptions::default(),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    match args.trim().to_lowercase().as_str() {
+        "reset" => reset_memory(bot, msg, state, user).await,
+        "show" | "" => show_memory(bot, msg, state, user).await,
+        "export" => export_memory(bot, msg, state, user).await,
+        _ => {
+            send_reply_with_fallback(
+                bot,
+                msg,
+                "선생님, 사용법: /memory show | /memory export | /memory reset",
+                SendOptions::default(),
+            )
+            .await?;
+            Ok(())
+        }
+    }
+}
+
+async fn reset_memory<B>(bot: &B, msg: &Message, state: &AppState, user: &User) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let text = match state.memory.reset(&user.id.to_string()).await {
+        Ok(true) => "선생님, 메모리를 초기화했습니다. 새 대화를 시작할 수 있습니다.",
+        Ok(false) => "선생님, 초기화할 메모리가 없습니다.",
+        Err(err) => {
+            error!("메모리 초기화 실패: {}", err);
+            "선생님, 메모리 초기화에 실패했습니다. 잠시 후 다시 시도해 주십시오."
+        }
+    };
+
+    send_reply_with_fallback(bot, msg, text, SendOptions::default()).await?;
+    Ok(())
+}
+
+async fn show_memory<B>(bot: &B, msg: &Message, state: &AppState, user: &User) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let history = match state.memory.history(&user.id.to_string()).await {
+        Ok(history) => history,
+        Err(err) => {
+            error!("메모리 조회 실패: {}", err);
+            send_reply_with_fallback(
+                bot,
+                msg,
+                "선생님, 메모리를 불러오지 못했습니다. 잠시 후 다시 시도해 주십시오.",
+                SendOptions::default(),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let text = if history.is_empty() {
+        "선생님, 기억하고 있는 대화가 없습니다.".to_string()
+    } else {
+        let skipped = history.len().saturating_sub(SHOW_LIMIT);
+        let lines = history[skipped..]
+            .iter()
+            .map(render_turn)
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        format!(
+            "선생님, 최근 기억 중인 대화입니다. (전체 {}개 중 {}개)\n\n{}",
+            history.len(),
+            history.len() - skipped,
+            lines
+        )
+    };
+
+    send_privately(
+        bot,
+        msg,
+        user,
+        truncate_message(&text, 4000),
+        "기억 중인 대화를",
+    )
+    .await
+}
+
+/// 대화 내용이 그룹에 노출되지 않도록 개인 메시지로 보내고, 그룹에는 보냈다는 안내만 남깁니다.
+async fn send_privately<B>(
+    bot: &B,
+    msg: &Message,
+    user: &User,
+    text: String,
+    what: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    if msg.chat.is_private() {
+        send_reply_with_fallback(bot, msg, text, SendOptions::default()).await?;
+        return Ok(());
+    }
+
+    if let Err(err) = bot.send_message(user.id, text).await {
+        error!("메모리 개인 메시지 전송 실패 (user {}): {}", user.id, err);
+        send_reply_with_fallback(
+            bot,
+            msg,
+            "선생님, 먼저 저와 개인 대화를 시작하거나 차단을 해제해 주세요.",
+            SendOptions::default(),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    send_reply_with_fallback(
+        bot,
+        msg,
+        format!("선생님, {} 개인 메시지로 보냈습니다.", what),
+        SendOptions::default(),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn export_memory<B>(bot: &B, msg: &Message, state: &AppState, user: &User) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let history = match state.memory.history(&user.id.to_string()).await {
+        Ok(history) => history,
+        Err(err) => {
+            error!("메모리 내보내기 실패: {}", err);
+            send_reply_with_fallback(
+                bot,
+                msg,
+                "선생님, 메모리를 불러오지 못했습니다. 잠시 후 다시 시도해 주십시오.",
+                SendOptions::default(),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if history.is_empty() {
+        send_reply_with_fallback(
+            bot,
+            msg,
+            "선생님, 내보낼 메모리가 없습니다.",
+            SendOptions::default(),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let payload = serde_json::to_vec_pretty(&serde_json::json!({
+        "version": 1,
+        "user_id": user.id.0,
+        "messages": history,
+    }))?;
+    let file = InputFile::memory(payload).file_name(format!("planabrain-memory-{}.json", user.id));
+
+    // 대화 내용이 그룹에 노출되지 않도록 항상 개인 메시지로 보냅니다.
+    if let Err(err) = bot.send_document(user.id, file).await {
+        error!("메모리 파일 전송 실패 (user {}): {}", user.id, err);
+        send_reply_with_fallback(
+            bot,
+            msg,
+            "선생님, 먼저 저와 개인 대화를 시작하거나 차단을 해제해 주세요.",
+            SendOptions::default(),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if !msg.chat.is_private() {
+        send_reply_with_fallback(
+            bot,
+            msg,
+            "선생님, 메모리 파일을 개인 메시지로 보냈습니다.",
+            SendOptions::default(),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+fn render_turn(turn: &MemoryTurn) -> String {
+    let speaker = match turn.role {
+        MemoryRole::Human => "선생님",
+        MemoryRole::Ai => "프라나",
+    };
+    let at = Local
+        .timestamp_millis_opt(turn.at)
+        .single()
+        .map(|dt| dt.format("%m-%d %H:%M").to_string())
+        .unwrap_or_default();
+    // 질문 앞에 붙는 "현재 시각: ..." 줄은 목록에서 생략합니다.
+    let content = truncate_message(turn.body().trim(), SHOW_TURN_CHARS);
+    format!("[{}] {}: {}", at, speaker, content)
+}
@@ -0,0 +1,113 @@
+use log::error;
+use teloxide::prelude::*;
+use teloxide::types::Message;
+
+use crate::mirrors::Mirrors;
+
+use super::telegram::{SendOptions, is_chat_admin, send_reply_with_fallback};
+use super::{AppState, HandlerResult};
+
+const MIRRORS_USAGE: &str = "선생님, 사용법입니다. (변경은 관리자만)\n\
+- /mirrors: 미러 목록과 상태\n\
+- /mirrors use <이름...>: 이 채팅에서 보일 미러와 순서 지정\n\
+- /mirrors reset: 모든 미러 보이기";
+
+pub(crate) async fn handle_mirrors_command<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    args: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let mut tokens = args.split_whitespace();
+    let sub = tokens.next().map(str::to_lowercase).unwrap_or_default();
+    let names: Vec<&str> = tokens.collect();
+    let chat_id = msg.chat.id.0;
+
+    let selection = match (sub.as_str(), names.is_empty()) {
+        ("", _) => {
+            let text = render_mirrors(
+                &state.mirrors,
+                state.settings.chat(chat_id).mirrors.as_deref(),
+            );
+            return reply(bot, msg, text).await;
+        }
+        ("use", false) => match state.mirrors.resolve_names(names) {
+            Ok(selection) => Some(selection),
+            Err(unknown) => {
+                let text = format!(
+                    "선생님, {} 미러는 설정되어 있지 않습니다. /mirrors 로 목록을 확인해 주십시오.",
+                    unknown
+                );
+                return reply(bot, msg, text).await;
+            }
+        },
+        ("reset", true) => None,
+        _ => return reply(bot, msg, MIRRORS_USAGE).await,
+    };
+
+    if !is_chat_admin(bot, msg).await {
+        return reply(bot, msg, "선생님, 미러 설정은 관리자만 변경할 수 있습니다.").await;
+    }
+
+    match state
+        .settings
+        .update_chat(chat_id, |chat| chat.mirrors = selection)
+        .await
+    {
+        Ok(settings) => {
+            let text = render_mirrors(&state.mirrors, settings.mirrors.as_deref());
+            reply(
+                bot,
+                msg,
+                format!("선생님, 설정을 변경했습니다.\n\n{}", text),
+            )
+            .await
+        }
+        Err(err) => {
+            error!("미러 설정 저장 실패 (chat {}): {}", chat_id, err);
+            reply(bot, msg, "선생님, 설정을 저장하지 못했습니다.").await
+        }
+    }
+}
+
+fn render_mirrors(mirrors: &Mirrors, selection: Option<&[String]>) -> String {
+    let lines: Vec<String> = mirrors
+        .templates()
+        .iter()
+        .map(|template| {
+            let status = if mirrors.is_up(&template.name) {
+                "✅"
+            } else {
+                "⛔ 응답 없음"
+            };
+            let shown = selection.is_none_or(|names| names.contains(&template.name));
+            format!(
+                "{} {}{}",
+                status,
+                template.name,
+                if shown {
+                    ""
+                } else {
+                    " (이 채팅에서 숨김)"
+                }
+            )
+        })
+        .collect();
+    format!(
+        "선생님, 갤러리 링크 미러 목록입니다. 응답이 없는 미러는 자동으로 숨깁니다.\n{}",
+        lines.join("\n")
+    )
+}
+
+async fn reply<B>(bot: &B, msg: &Message, text: impl Into<String>) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    send_reply_with_fallback(bot, msg, text, SendOptions::default()).await?;
+    Ok(())
+}
@@ -1,38 +1,65 @@
+mod admin_cache;
 mod commands;
+mod favorite_commands;
+mod filter_commands;
+mod follow_commands;
+mod follow_poller;
 mod gallery;
 mod handlers;
+mod history_commands;
+mod inline;
+mod knowledge_commands;
+mod memory_commands;
+mod mirror_commands;
+mod planabrain_actions;
+mod planabrain_sessions;
+mod search;
 mod state;
 mod telegram;
+mod token_store;
 
 use anyhow::Result;
 use log::warn;
 use teloxide::dispatching::UpdateFilterExt;
 use teloxide::filter_command;
+use teloxide::net::Download;
 use teloxide::prelude::*;
 use teloxide::types::Message;
 use teloxide::utils::command::BotCommands;
 
 use crate::urlchanger;
 
-pub(crate) use telegram::{send_in_thread, send_reply_with_fallback, SendOptions};
+pub use follow_poller::spawn_follow_poller;
+pub(crate) use gallery::extract_gallery_ids;
 pub use state::AppState;
+pub(crate) use telegram::{SendOptions, send_in_thread, send_reply_with_fallback};
 
 pub type HandlerResult = Result<()>;
 
 pub async fn run<B>(bot: B, state: AppState) -> Result<()>
 where
-    B: Requester + Clone + Send + Sync + 'static,
-    B::Err: std::error::Error + Send + Sync + 'static,
+    B: Requester + Download + Clone + Send + Sync + 'static,
+    <B as Requester>::Err: std::error::Error + Send + Sync + 'static,
+    for<'dst> <B as Download>::Err<'dst>: std::fmt::Display,
     B::SendChatAction: Send,
     <B as Requester>::GetUpdates: Send,
     <B as Requester>::GetChatMember: Send,
+    <B as Requester>::CopyMessage: Send,
+    <B as Requester>::SendMessage: Send,
+    <B as Requester>::SendDocument: Send,
+    <B as Requester>::GetFile: Send,
+    <B as Requester>::SendPhoto: Send,
 {
-    bot.set_my_commands(commands::Command::bot_commands()).await?;
+    bot.set_my_commands(commands::Command::bot_commands())
+        .await?;
 
     let handler = dptree::entry()
         .branch(
             Update::filter_message()
-                .branch(filter_command::<commands::Command, _>().endpoint(handlers::handle_command::<B>))
+                .branch(
+                    filter_command::<commands::Command, _>()
+                        .endpoint(handlers::handle_command::<B>),
+                )
                 .branch(
                     dptree::filter(|msg: Message, state: AppState| {
                         handlers::is_plana_trigger(&msg, &state)
@@ -42,7 +69,10 @@ where
                 .branch(urlchanger::url_handlers::<B>())
                 .branch(dptree::endpoint(handlers::handle_message::<B>)),
         )
-        .branch(Update::filter_callback_query().endpoint(handlers::handle_callback::<B>));
+        .branch(Update::filter_callback_query().endpoint(handlers::handle_callback::<B>))
+        .branch(Update::filter_inline_query().endpoint(inline::handle_inline_query::<B>))
+        .branch(Update::filter_chosen_inline_result().endpoint(inline::handle_chosen_inline_result))
+        .branch(Update::filter_my_chat_member().endpoint(admin_cache::handle_my_chat_member));
 
     Dispatcher::builder(bot, handler)
         .dependencies(dptree::deps![state])
@@ -0,0 +1,337 @@
+use anyhow::Result;
+use chrono::{Datelike, Local, Timelike, Weekday};
+use log::{error, warn};
+use teloxide::prelude::*;
+use teloxide::types::{CallbackQuery, ChatAction, Message};
+use tokio::time::{self, Duration};
+
+use crate::planabrain::{self, MemoryEdit, MemoryTurn, last_exchange_start};
+
+use super::planabrain_sessions::{
+    CONTINUE_PREFIX, DELETE_PREFIX, PlanabrainSession, REGENERATE_PREFIX, build_answer_keyboard,
+};
+use super::telegram::{SendOptions, send_reply_with_fallback};
+use super::{AppState, HandlerResult};
+
+const REPLY_LIMIT: usize = 4000;
+
+pub(crate) fn is_planabrain_callback(data: &str) -> bool {
+    data.starts_with(REGENERATE_PREFIX)
+        || data.starts_with(CONTINUE_PREFIX)
+        || data.starts_with(DELETE_PREFIX)
+}
+
+/// planabrain 답변(또는 실패 안내)을 보내고, 다시 생성/계속/삭제 버튼을 붙입니다.
+pub(crate) async fn send_planabrain_answer<B>(
+    bot: &B,
+    reply_to: &Message,
+    state: &AppState,
+    session: PlanabrainSession,
+    answer: Result<String>,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let (text, answer, can_continue) = match answer {
+        Ok(answer) => {
+            let answer = answer.trim().to_string();
+            (
+                planabrain::truncate_message(&answer, REPLY_LIMIT),
+                answer,
+                true,
+            )
+        }
+        Err(err) => {
+            error!("planabrain 응답 실패: {}", err);
+            (
+                "선생님, 응답 생성에 실패했습니다. 잠시 후 다시 시도해 주십시오.".to_string(),
+                String::new(),
+                false,
+            )
+        }
+    };
+
+    let token = state.store_planabrain_session(session.with_answer(answer));
+    let sent = send_reply_with_fallback(
+        bot,
+        reply_to,
+        text,
+        SendOptions {
+            reply_markup: Some(build_answer_keyboard(&token, can_continue)),
+            ..SendOptions::default()
+        },
+    )
+    .await?;
+    state.record_planabrain_reply(&sent).await;
+
+    Ok(())
+}
+
+/// 질문을 대화 메모리에 어떻게 남길지.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Turn<'a> {
+    /// 새 질문
+    Ask,
+    /// 같은 질문의 마지막 문답을 새 답변으로 바꿉니다.
+    Regenerate,
+    /// `question`에 대한 마지막 답변을 이어서 씁니다. 질문으로는 이어쓰기 요청을 보냅니다.
+    Continue { question: &'a str },
+}
+
+/// 타이핑 표시를 유지하면서 planabrain에 질문하고, 성공하면 대화 메모리에 기록합니다.
+pub(crate) async fn ask_with_typing<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    question: &str,
+    user_id: &str,
+    turn: Turn<'_>,
+) -> Result<String>
+where
+    B: Requester + ?Sized,
+    B::SendChatAction: Send,
+{
+    let mut history = state.memory.history(user_id).await.unwrap_or_else(|err| {
+        warn!("대화 메모리 읽기 실패 (user {}): {}", user_id, err);
+        Vec::new()
+    });
+    // 다시 생성할 때는 이전 답변을 보지 않도록 그 문답을 빼고 묻습니다.
+    if turn == Turn::Regenerate
+        && let Some(start) = last_exchange_start(&history, question)
+    {
+        history.truncate(start);
+    }
+    let original = question;
+    let question = format_question_with_timestamp(question);
+
+    let answer = {
+        send_typing_in_thread(bot, msg).await;
+        let mut typing_interval = time::interval(Duration::from_secs(3));
+        let ask_fut = planabrain::run_planabrain_ask(&question, user_id, &history);
+        tokio::pin!(ask_fut);
+
+        loop {
+            tokio::select! {
+                _ = typing_interval.tick() => {
+                    send_typing_in_thread(bot, msg).await;
+                }
+                result = &mut ask_fut => {
+                    break result;
+                }
+            }
+        }
+    }?;
+
+    let exchange = || vec![MemoryTurn::human(&question), MemoryTurn::ai(answer.trim())];
+    let edit = match turn {
+        Turn::Ask => MemoryEdit::Append(exchange()),
+        Turn::Regenerate => MemoryEdit::ReplaceExchange {
+            question: original.to_string(),
+            turns: exchange(),
+        },
+        Turn::Continue {
+            question: continued,
+        } => MemoryEdit::ExtendAnswer {
+            question: continued.to_string(),
+            text: answer.trim().to_string(),
+        },
+    };
+    if let Err(err) = state.memory.update(user_id, edit).await {
+        warn!("대화 메모리 저장 실패 (user {}): {}", user_id, err);
+    }
+
+    Ok(answer)
+}
+
+pub(crate) async fn handle_planabrain_callback<B>(
+    bot: B,
+    query: CallbackQuery,
+    state: AppState,
+    data: &str,
+) -> HandlerResult
+where
+    B: Requester + Send + Sync + 'static,
+    B::Err: std::error::Error + Send + Sync + 'static,
+    B::SendChatAction: Send,
+{
+    let (action, token) = if let Some(token) = data.strip_prefix(REGENERATE_PREFIX) {
+        (Action::Regenerate, token)
+    } else if let Some(token) = data.strip_prefix(CONTINUE_PREFIX) {
+        (Action::Continue, token)
+    } else if let Some(token) = data.strip_prefix(DELETE_PREFIX) {
+        (Action::Delete, token)
+    } else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+
+    // 세션을 만든 채팅의 메시지에 달린 버튼만 받습니다.
+    let message = query.regular_message().cloned();
+    let session = state.planabrain_session(token).filter(|session| {
+        message
+            .as_ref()
+            .is_some_and(|message| message.chat.id == session.chat_id)
+    });
+    let (Some(session), Some(message)) = (session, message) else {
+        let _ = bot
+            .answer_callback_query(query.id)
+            .text("선생님, 이 답변의 버튼은 만료되었습니다. 다시 질문해 주십시오.")
+            .show_alert(true)
+            .await;
+        return Ok(());
+    };
+
+    match action {
+        Action::Delete => {
+            if !session.is_asker(query.from.id) {
+                let _ = bot
+                    .answer_callback_query(query.id)
+                    .text("선생님, 질문하신 분만 이 답변을 삭제할 수 있습니다.")
+                    .show_alert(true)
+                    .await;
+                return Ok(());
+            }
+
+            if let Err(err) = bot.delete_message(message.chat.id, message.id).await {
+                warn!("planabrain 답변 삭제 실패: {}", err);
+                let _ = bot
+                    .answer_callback_query(query.id)
+                    .text("선생님, 답변을 삭제하지 못했습니다.")
+                    .show_alert(true)
+                    .await;
+                return Ok(());
+            }
+
+            state.remove_planabrain_session(token);
+            let _ = bot.answer_callback_query(query.id).await;
+        }
+        Action::Regenerate => {
+            let _ = bot
+                .answer_callback_query(query.id)
+                .text("답변을 다시 생성하고 있습니다...")
+                .await;
+
+            let answer = ask_with_typing(
+                &bot,
+                &message,
+                &state,
+                &session.question,
+                &session.memory_user_id,
+                Turn::Regenerate,
+            )
+            .await;
+            let (text, can_continue) = match answer {
+                Ok(answer) => {
+                    let answer = answer.trim().to_string();
+                    let text = planabrain::truncate_message(&answer, REPLY_LIMIT);
+                    state.update_planabrain_session(token, answer);
+                    (text, true)
+                }
+                Err(err) => {
+                    error!("planabrain 재생성 실패: {}", err);
+                    (
+                        "선생님, 응답 생성에 실패했습니다. 잠시 후 다시 시도해 주십시오."
+                            .to_string(),
+                        false,
+                    )
+                }
+            };
+
+            if let Err(err) = bot
+                .edit_message_text(message.chat.id, message.id, text)
+                .reply_markup(build_answer_keyboard(token, can_continue))
+                .await
+            {
+                error!("planabrain 답변 수정 실패: {}", err);
+            }
+        }
+        Action::Continue => {
+            if session.answer.is_empty() {
+                let _ = bot
+                    .answer_callback_query(query.id)
+                    .text("선생님, 이어서 작성할 답변이 없습니다.")
+                    .show_alert(true)
+                    .await;
+                return Ok(());
+            }
+
+            let _ = bot
+                .answer_callback_query(query.id)
+                .text("답변을 이어서 작성하고 있습니다...")
+                .await;
+
+            let prompt = continuation_prompt(&session);
+            let answer = ask_with_typing(
+                &bot,
+                &message,
+                &state,
+                &prompt,
+                &session.memory_user_id,
+                Turn::Continue {
+                    question: &session.question,
+                },
+            )
+            .await;
+            let next = PlanabrainSession::new(
+                session.chat_id,
+                session.asker,
+                session.memory_user_id.clone(),
+                session.question.clone(),
+                String::new(),
+            );
+            send_planabrain_answer(&bot, &message, &state, next, answer).await?;
+        }
+    }
+
+    Ok(())
+}
+
+enum Action {
+    Regenerate,
+    Continue,
+    Delete,
+}
+
+fn continuation_prompt(session: &PlanabrainSession) -> String {
+    format!(
+        "직전 답변에 이어서 계속 작성해 주십시오. 이미 작성한 내용은 반복하지 마십시오.\n\n[이전 질문]\n{}\n\n[이전 답변]\n{}",
+        session.question, session.answer
+    )
+}
+
+async fn send_typing_in_thread<B>(bot: &B, msg: &Message)
+where
+    B: Requester + ?Sized,
+    B::SendChatAction: Send,
+{
+    let mut req = bot.send_chat_action(msg.chat.id, ChatAction::Typing);
+    if let Some(thread_id) = msg.thread_id {
+        req = req.message_thread_id(thread_id);
+    }
+    let _ = req.await;
+}
+
+fn format_question_with_timestamp(question: &str) -> String {
+    let now = Local::now();
+    let weekday = match now.weekday() {
+        Weekday::Mon => "월",
+        Weekday::Tue => "화",
+        Weekday::Wed => "수",
+        Weekday::Thu => "목",
+        Weekday::Fri => "금",
+        Weekday::Sat => "토",
+        Weekday::Sun => "일",
+    };
+    let timestamp = format!(
+        "{:04}-{:02}-{:02} ({}) {:02}:{:02}:{:02}",
+        now.year(),
+        now.month(),
+        now.day(),
+        weekday,
+        now.hour(),
+        now.minute(),
+        now.second()
+    );
+    format!("현재 시각: {}\n\n{}", timestamp, question)
+}
@@ -0,0 +1,105 @@
+use teloxide::types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup, UserId};
+
+use super::token_store::TokenStore;
+
+pub(crate) const REGENERATE_PREFIX: &str = "ai_regen_";
+pub(crate) const CONTINUE_PREFIX: &str = "ai_cont_";
+pub(crate) const DELETE_PREFIX: &str = "ai_del_";
+
+#[derive(Debug, Clone)]
+pub(crate) struct PlanabrainSession {
+    pub chat_id: ChatId,
+    pub asker: Option<UserId>,
+    pub memory_user_id: String,
+    pub question: String,
+    pub answer: String,
+}
+
+impl PlanabrainSession {
+    pub(crate) fn new(
+        chat_id: ChatId,
+        asker: Option<UserId>,
+        memory_user_id: String,
+        question: String,
+        answer: String,
+    ) -> Self {
+        Self {
+            chat_id,
+            asker,
+            memory_user_id,
+            question,
+            answer,
+        }
+    }
+
+    pub(crate) fn with_answer(mut self, answer: String) -> Self {
+        self.answer = answer;
+        self
+    }
+
+    pub(crate) fn is_asker(&self, user: UserId) -> bool {
+        self.asker == Some(user)
+    }
+}
+
+/// 답변 버튼의 콜백 토큰과 원래 질문/대화 정보를 짧은 시간 동안 보관합니다.
+pub(crate) type PlanabrainSessionStore = TokenStore<PlanabrainSession>;
+
+pub(crate) fn build_answer_keyboard(token: &str, can_continue: bool) -> InlineKeyboardMarkup {
+    let mut row = vec![InlineKeyboardButton::callback(
+        "🔄 다시 생성",
+        format!("{REGENERATE_PREFIX}{token}"),
+    )];
+    if can_continue {
+        row.push(InlineKeyboardButton::callback(
+            "➕ 계속",
+            format!("{CONTINUE_PREFIX}{token}"),
+        ));
+    }
+    row.push(InlineKeyboardButton::callback(
+        "🗑 삭제",
+        format!("{DELETE_PREFIX}{token}"),
+    ));
+
+    InlineKeyboardMarkup::new(vec![row])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn session(question: &str) -> PlanabrainSession {
+        PlanabrainSession::new(
+            ChatId(1),
+            Some(UserId(7)),
+            "7".to_string(),
+            question.to_string(),
+            "answer".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_store_roundtrip_and_asker_check() {
+        let mut store = PlanabrainSessionStore::new(Duration::from_secs(60), 10);
+        let token = store.insert(session("q1"));
+        let found = store.get(&token).expect("session should exist");
+        assert_eq!(found.question, "q1");
+        assert_eq!(found.chat_id, ChatId(1));
+        assert!(found.is_asker(UserId(7)));
+        assert!(!found.is_asker(UserId(8)));
+    }
+
+    #[test]
+    fn test_callback_data_fits_telegram_limit() {
+        let keyboard = build_answer_keyboard(&format!("{:016x}", u64::MAX), true);
+        for row in keyboard.inline_keyboard {
+            for button in row {
+                if let teloxide::types::InlineKeyboardButtonKind::CallbackData(data) = button.kind {
+                    assert!(data.len() <= 64);
+                }
+            }
+        }
+    }
+}
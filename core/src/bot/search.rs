@@ -0,0 +1,456 @@
+use log::error;
+use teloxide::prelude::*;
+use teloxide::types::{
+    CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, LinkPreviewOptions, Message,
+    ParseMode,
+};
+use teloxide::utils::html;
+
+use crate::hitomi::{GalleryError, GalleryInfo, SearchTerm, parse_query};
+use crate::settings::{ContentPolicy, Verdict};
+
+use super::gallery::{
+    FavoriteButton, adult_disabled_message, build_gallery_keyboard, gallery_error_message,
+    lookup_concurrency, refused_message, render_gallery_filtered,
+};
+use super::telegram::{SendOptions, send_reply_with_fallback};
+use super::token_store::TokenStore;
+use super::{AppState, HandlerResult};
+
+pub(crate) const SEARCH_PAGE_PREFIX: &str = "srch_";
+pub(crate) const GALLERY_DETAIL_PREFIX: &str = "gal_";
+
+const PAGE_SIZE: usize = 10;
+const USAGE: &str = "선생님, 사용법: /search <검색어...>\n\
+예시: /search female:glasses artist:foo_bar language:korean\n\
+- 접두사: artist, group, series, character, type, language, female, male (없으면 일반 태그)\n\
+- 공백은 _로 입력하고, 여러 검색어는 모두 만족하는 결과만 보여드립니다.";
+
+#[derive(Debug, Clone)]
+pub(crate) struct SearchSession {
+    pub query: String,
+    pub ids: Vec<u32>,
+}
+
+/// 페이지 버튼이 참조하는 검색 결과를 짧은 시간 동안 보관합니다.
+pub(crate) type SearchSessionStore = TokenStore<SearchSession>;
+
+pub(crate) fn is_search_callback(data: &str) -> bool {
+    data.starts_with(SEARCH_PAGE_PREFIX) || data.starts_with(GALLERY_DETAIL_PREFIX)
+}
+
+pub(crate) async fn handle_search_command<B>(
+    bot: &B,
+    msg: &Message,
+    state: &AppState,
+    args: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    if !state.adult_allowed(msg) {
+        send_reply_with_fallback(bot, msg, adult_disabled_message(), SendOptions::default())
+            .await?;
+        return Ok(());
+    }
+
+    let terms = match parse_query(args) {
+        Ok(terms) if !terms.is_empty() => terms,
+        Ok(_) => {
+            send_reply_with_fallback(bot, msg, USAGE, SendOptions::default()).await?;
+            return Ok(());
+        }
+        Err(invalid) => {
+            send_reply_with_fallback(
+                bot,
+                msg,
+                format!(
+                    "선생님, 검색어 \"{}\"을(를) 해석하지 못했습니다.\n\n{}",
+                    invalid, USAGE
+                ),
+                SendOptions::default(),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let query = render_query(&terms);
+
+    let initial = send_reply_with_fallback(
+        bot,
+        msg,
+        format!(
+            "선생님, \"{}\" 검색을 시작합니다. 잠시만 기다려주십시오...",
+            query
+        ),
+        SendOptions {
+            disable_notification: Some(true),
+            ..SendOptions::default()
+        },
+    )
+    .await?;
+
+    let ids = match state.gallery_client.search(&terms).await {
+        Ok(ids) => ids,
+        Err(err) => {
+            error!("검색 실패 ({}): {}", query, err);
+            bot.edit_message_text(
+                msg.chat.id,
+                initial.id,
+                format!("선생님, 검색에 실패했습니다. ({})", err),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if ids.is_empty() {
+        bot.edit_message_text(
+            msg.chat.id,
+            initial.id,
+            format!("선생님, \"{}\"에 해당하는 갤러리를 찾지 못했습니다.", query),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let (token, session) = state.store_search_session(query, ids);
+    let policy = state.content_policy(msg);
+    show_page(bot, &initial, state, &policy, &token, &session, 0).await
+}
+
+pub(crate) async fn handle_search_callback<B>(
+    bot: B,
+    query: CallbackQuery,
+    state: AppState,
+    data: &str,
+) -> HandlerResult
+where
+    B: Requester + Send + Sync + 'static,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    if let Some(gallery_id) = data.strip_prefix(GALLERY_DETAIL_PREFIX) {
+        return send_gallery_detail(&bot, query, &state, gallery_id).await;
+    }
+
+    let Some((token, page)) = data
+        .strip_prefix(SEARCH_PAGE_PREFIX)
+        .and_then(|rest| rest.split_once('_'))
+        .and_then(|(token, page)| Some((token, page.parse::<usize>().ok()?)))
+    else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+
+    let (Some(session), Some(message)) = (
+        state.search_session(token),
+        query.regular_message().cloned(),
+    ) else {
+        let _ = bot
+            .answer_callback_query(query.id)
+            .text("선생님, 검색 결과가 만료되었습니다. 다시 검색해 주십시오.")
+            .show_alert(true)
+            .await;
+        return Ok(());
+    };
+
+    let _ = bot.answer_callback_query(query.id).await;
+    let policy = state
+        .settings
+        .content_policy(Some(message.chat.id.0), Some(query.from.id.0));
+    show_page(&bot, &message, &state, &policy, token, &session, page).await
+}
+
+async fn show_page<B>(
+    bot: &B,
+    message: &Message,
+    state: &AppState,
+    policy: &ContentPolicy,
+    token: &str,
+    session: &SearchSession,
+    page: usize,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let pages = session.ids.len().div_ceil(PAGE_SIZE);
+    let page = page.min(pages.saturating_sub(1));
+    let page_ids: Vec<String> = session
+        .ids
+        .iter()
+        .skip(page * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .map(u32::to_string)
+        .collect();
+
+    let results = state
+        .gallery_client
+        .get_many(&page_ids, lookup_concurrency())
+        .await;
+
+    let text = render_search_page(session, page, pages, &results, policy);
+    let keyboard = build_search_keyboard(token, page, pages, &results, policy);
+
+    if let Err(err) = bot
+        .edit_message_text(message.chat.id, message.id, text)
+        .parse_mode(ParseMode::Html)
+        .link_preview_options(LinkPreviewOptions {
+            is_disabled: true,
+            url: None,
+            prefer_small_media: false,
+            prefer_large_media: false,
+            show_above_text: false,
+        })
+        .reply_markup(keyboard)
+        .await
+    {
+        error!(
+            "검색 결과 메시지 수정 실패 (chat {}): {}",
+            message.chat.id, err
+        );
+    }
+
+    Ok(())
+}
+
+async fn send_gallery_detail<B>(
+    bot: &B,
+    query: CallbackQuery,
+    state: &AppState,
+    gallery_id: &str,
+) -> HandlerResult
+where
+    B: Requester + ?Sized,
+    B::Err: std::error::Error + Send + Sync + 'static,
+{
+    let Some(message) = query.regular_message() else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+
+    if !state
+        .settings
+        .adult_allowed(message.chat.id.0, message.chat.is_private())
+    {
+        let _ = bot
+            .answer_callback_query(query.id.clone())
+            .text(adult_disabled_message())
+            .show_alert(true)
+            .await;
+        return Ok(());
+    }
+
+    let policy = state
+        .settings
+        .content_policy(Some(message.chat.id.0), Some(query.from.id.0));
+    match state.galleries.get(gallery_id).await {
+        Ok(info) => {
+            let rendered = match render_gallery_filtered(&info, &policy) {
+                Ok(rendered) => rendered,
+                Err(tags) => {
+                    let _ = bot
+                        .answer_callback_query(query.id.clone())
+                        .text(refused_message(gallery_id, &tags))
+                        .show_alert(true)
+                        .await;
+                    return Ok(());
+                }
+            };
+            let _ = bot.answer_callback_query(query.id.clone()).await;
+            let favorite = FavoriteButton::for_chat(
+                state,
+                message.chat.is_private(),
+                Some(query.from.id),
+                &info.id,
+            )
+            .await;
+            let keyboard = build_gallery_keyboard(
+                &info,
+                &state.mirror_links(Some(message.chat.id), &info.id),
+                favorite,
+            );
+            send_reply_with_fallback(
+                bot,
+                message,
+                rendered.text,
+                SendOptions {
+                    parse_mode: Some(ParseMode::Html),
+                    reply_markup: Some(keyboard),
+                    ..SendOptions::default()
+                },
+            )
+            .await?;
+        }
+        Err(err) => {
+            let _ = bot
+                .answer_callback_query(query.id.clone())
+                .text(gallery_error_message(gallery_id, &err))
+                .show_alert(true)
+                .await;
+        }
+    }
+
+    Ok(())
+}
+
+fn render_query(terms: &[SearchTerm]) -> String {
+    terms
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn render_search_page(
+    session: &SearchSession,
+    page: usize,
+    pages: usize,
+    results: &[(String, Result<GalleryInfo, GalleryError>)],
+    policy: &ContentPolicy,
+) -> String {
+    let mut lines = vec![format!(
+        "<b>선생님, \"{}\" 검색 결과입니다. (총 {}건, {}/{} 페이지)</b>",
+        html::escape(&session.query),
+        session.ids.len(),
+        page + 1,
+        pages
+    )];
+
+    for (index, (gallery_id, result)) in results.iter().enumerate() {
+        let number = page * PAGE_SIZE + index + 1;
+        let line = match result {
+            Ok(info) if matches!(policy.check(info), Verdict::Refused(_)) => {
+                format!("{}. ID {} (차단된 태그 포함)", number, gallery_id)
+            }
+            Ok(info) => {
+                let mut details = Vec::new();
+                if !info.artists.is_empty() {
+                    details.push(html::escape(&info.artists.join(", ")));
+                }
+                if let Some(language) = &info.language {
+                    details.push(html::escape(language));
+                }
+                let details = if details.is_empty() {
+                    String::new()
+                } else {
+                    format!(" · {}", details.join(" · "))
+                };
+                format!(
+                    "{}. <a href=\"{}\">{}</a> ({}){}",
+                    number,
+                    html::escape(&info.hitomi_url()),
+                    html::escape(&info.title),
+                    gallery_id,
+                    details
+                )
+            }
+            Err(_) => format!("{}. ID {} (정보를 불러오지 못했습니다)", number, gallery_id),
+        };
+        lines.push(line);
+    }
+
+    lines.join("\n")
+}
+
+fn build_search_keyboard(
+    token: &str,
+    page: usize,
+    pages: usize,
+    results: &[(String, Result<GalleryInfo, GalleryError>)],
+    policy: &ContentPolicy,
+) -> InlineKeyboardMarkup {
+    let buttons: Vec<InlineKeyboardButton> = results
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, result))| {
+            result
+                .as_ref()
+                .is_ok_and(|info| !matches!(policy.check(info), Verdict::Refused(_)))
+        })
+        .map(|(index, (gallery_id, _))| {
+            InlineKeyboardButton::callback(
+                (page * PAGE_SIZE + index + 1).to_string(),
+                format!("{}{}", GALLERY_DETAIL_PREFIX, gallery_id),
+            )
+        })
+        .collect();
+    let mut rows: Vec<Vec<InlineKeyboardButton>> =
+        buttons.chunks(5).map(|row| row.to_vec()).collect();
+
+    let mut nav = Vec::new();
+    if page > 0 {
+        nav.push(InlineKeyboardButton::callback(
+            "◀",
+            format!("{}{}_{}", SEARCH_PAGE_PREFIX, token, page - 1),
+        ));
+    }
+    if page + 1 < pages {
+        nav.push(InlineKeyboardButton::callback(
+            "▶",
+            format!("{}{}_{}", SEARCH_PAGE_PREFIX, token, page + 1),
+        ));
+    }
+    if !nav.is_empty() {
+        rows.push(nav);
+    }
+
+    InlineKeyboardMarkup::new(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use teloxide::types::InlineKeyboardButtonKind;
+
+    fn found(id: &str) -> (String, Result<GalleryInfo, GalleryError>) {
+        (
+            id.to_string(),
+            Ok(GalleryInfo {
+                id: id.to_string(),
+                title: format!("title {id}"),
+                ..GalleryInfo::default()
+            }),
+        )
+    }
+
+    #[test]
+    fn test_search_keyboard_has_detail_buttons_and_navigation() {
+        let results = vec![
+            found("1"),
+            ("2".to_string(), Err(GalleryError::NotFound)),
+            found("3"),
+        ];
+        let keyboard = build_search_keyboard("a", 1, 3, &results, &ContentPolicy::default());
+        let rows = &keyboard.inline_keyboard;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].len(), 2);
+        assert_eq!(rows[0][0].text, "11");
+        assert_eq!(rows[1][0].text, "◀");
+        assert_eq!(rows[1][1].text, "▶");
+    }
+
+    #[test]
+    fn test_last_page_has_no_next_button() {
+        let keyboard = build_search_keyboard("a", 0, 1, &[found("1")], &ContentPolicy::default());
+        assert_eq!(keyboard.inline_keyboard.len(), 1);
+    }
+
+    #[test]
+    fn test_session_tokens_fit_callback_data() {
+        let mut store = SearchSessionStore::new(std::time::Duration::from_secs(60), 10);
+        let token = store.insert(SearchSession {
+            query: "a".to_string(),
+            ids: vec![1],
+        });
+        assert_eq!(store.get(&token).unwrap().ids, vec![1]);
+
+        let keyboard = build_search_keyboard(&token, 1, 1000, &[], &ContentPolicy::default());
+        for button in keyboard.inline_keyboard.iter().flatten() {
+            if let InlineKeyboardButtonKind::CallbackData(data) = &button.kind {
+                assert!(data.len() <= 64);
+            }
+        }
+    }
+}
@@ -1,14 +1,29 @@
 use std::collections::{HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use log::warn;
+use log::{error, warn};
 use serde::{Deserialize, Serialize};
-use teloxide::types::{ChatId, ChatKind, Message, MessageId, PublicChatKind};
+use teloxide::types::{ChatId, ChatKind, Message, MessageId, PublicChatKind, User, UserId};
 use tokio::fs;
+use url::Url;
 
-use crate::hitomi::GalleryClient;
+use crate::favorites::FavoriteStore;
+use crate::follow::FollowStore;
+use crate::history::{HistoryStore, LookupRecord, LookupSource};
+use crate::hitomi::{GalleryClient, GalleryInfo};
+use crate::knowledge::KnowledgeBase;
+use crate::llm::{self, LlmProvider};
+use crate::mirrors::{MirrorLink, MirrorTemplate, Mirrors};
+use crate::planabrain::{FileMemoryStore, MemoryStore};
+use crate::settings::{ContentPolicy, SettingsStore};
+use crate::sources::{GalleryRef, GallerySources, SourceKind};
+use crate::urlchanger::{LinkRewriter, MetadataConfig, MetadataFetcher, UndoStore};
+
+use super::admin_cache::AdminCache;
+use super::planabrain_sessions::{PlanabrainSession, PlanabrainSessionStore};
+use super::search::{SearchSession, SearchSessionStore};
 
 #[derive(Debug)]
 struct PlanabrainReplyTracker {
@@ -76,16 +91,44 @@ struct PlanabrainReplyRecord {
 #[derive(Clone)]
 pub struct AppState {
     pub bot_username: String,
+    pub bot_id: UserId,
     pub gallery_client: GalleryClient,
+    /// 출처 접두사(`nh:`, `e:`)까지 처리하는 갤러리 조회. 검색·캐시 관리 외에는 이쪽을 씁니다.
+    pub(crate) galleries: GallerySources,
+    pub(crate) memory: Arc<dyn MemoryStore>,
+    pub(crate) llm: Option<Arc<dyn LlmProvider>>,
+    pub(crate) knowledge: KnowledgeBase,
+    pub(crate) favorites: FavoriteStore,
+    pub(crate) settings: SettingsStore,
+    pub(crate) history: HistoryStore,
+    pub(crate) follows: FollowStore,
+    pub(crate) mirrors: Mirrors,
+    pub(crate) links: Arc<LinkRewriter>,
+    /// 관리자 권한으로 다시 올린 링크 메시지의 원본 (복원 버튼용)
+    pub(crate) link_undo: UndoStore,
+    /// 정리한 링크의 제목·채널 정보 (`PLANABOT_LINK_CARDS`)
+    pub(crate) link_cards: Option<MetadataFetcher>,
+    /// 채팅별 봇의 메시지 삭제 권한
+    pub(crate) admin_cache: AdminCache,
+    owner_ids: Arc<HashSet<i64>>,
     booted_at: i64,
     planabrain_replies: Arc<RwLock<PlanabrainReplyTracker>>,
     planabrain_replies_path: PathBuf,
+    planabrain_sessions: Arc<RwLock<PlanabrainSessionStore>>,
+    search_sessions: Arc<RwLock<SearchSessionStore>>,
     group_registry: Arc<RwLock<HashSet<ChatId>>>,
     group_registry_path: PathBuf,
 }
 
 impl AppState {
-    pub fn new(bot_username: String, gallery_client: GalleryClient) -> Self {
+    pub(crate) fn new(
+        bot_username: String,
+        bot_id: UserId,
+        gallery_client: GalleryClient,
+        owner_ids: HashSet<i64>,
+        mirrors: Vec<MirrorTemplate>,
+        links: LinkRewriter,
+    ) -> Self {
         let booted_at = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
@@ -98,15 +141,101 @@ impl AppState {
 
         Self {
             bot_username,
+            bot_id,
+            galleries: GallerySources::from_env(gallery_client.clone()),
             gallery_client,
+            memory: Arc::new(FileMemoryStore::from_env()),
+            llm: llm::provider_from_env().map(|p| Arc::new(p) as Arc<dyn LlmProvider>),
+            knowledge: KnowledgeBase::from_env(),
+            favorites: FavoriteStore::from_env(),
+            settings: SettingsStore::from_env(),
+            history: HistoryStore::from_env(),
+            follows: FollowStore::from_env(),
+            mirrors: Mirrors::new(mirrors),
+            links: Arc::new(links),
+            link_undo: UndoStore::from_env(),
+            link_cards: MetadataConfig::from_env().map(MetadataFetcher::new),
+            admin_cache: AdminCache::from_env(),
+            owner_ids: Arc::new(owner_ids),
             booted_at,
             planabrain_replies: Arc::new(RwLock::new(planabrain_replies)),
             planabrain_replies_path,
+            planabrain_sessions: Arc::new(RwLock::new(PlanabrainSessionStore::new(
+                Duration::from_secs(60 * 60),
+                500,
+            ))),
+            search_sessions: Arc::new(RwLock::new(SearchSessionStore::new(
+                Duration::from_secs(60 * 60),
+                200,
+            ))),
             group_registry: Arc::new(RwLock::new(group_registry)),
             group_registry_path,
         }
     }
 
+    pub(crate) fn is_owner(&self, msg: &Message) -> bool {
+        msg.from
+            .as_ref()
+            .and_then(|user| i64::try_from(user.id.0).ok())
+            .is_some_and(|id| self.owner_ids.contains(&id))
+    }
+
+    /// 채팅이 기록을 끄지 않았다면 조회 기록을 남깁니다. 실패해도 조회 흐름은 계속합니다.
+    pub(crate) async fn record_lookup(
+        &self,
+        chat_id: ChatId,
+        user: Option<&User>,
+        info: &GalleryInfo,
+        source: LookupSource,
+    ) {
+        if !self.settings.history_enabled(chat_id.0) {
+            return;
+        }
+        let record = LookupRecord::new(
+            info,
+            user.map(|user| user.id.0),
+            user.map(|user| user.full_name()),
+            source,
+        );
+        if let Err(err) = self.history.append(chat_id.0, &record).await {
+            error!("조회 기록 저장 실패 (chat {}): {}", chat_id, err);
+        }
+    }
+
+    /// 채팅이 고른 미러(없으면 전체) 중 살아 있는 것의 링크. 채팅을 모르면 전체를 씁니다.
+    pub(crate) fn mirror_links(
+        &self,
+        chat_id: Option<ChatId>,
+        gallery_id: &str,
+    ) -> Vec<MirrorLink> {
+        // 미러 템플릿은 Hitomi ID 기준이므로 다른 출처는 원래 페이지만 보입니다.
+        if let Some(gallery) = GalleryRef::parse(gallery_id)
+            && gallery.kind != SourceKind::Hitomi
+        {
+            return Url::parse(&gallery.page_url())
+                .map(|url| {
+                    vec![MirrorLink {
+                        name: gallery.kind.label().to_string(),
+                        url,
+                    }]
+                })
+                .unwrap_or_default();
+        }
+        let selection = chat_id.and_then(|chat_id| self.settings.chat(chat_id.0).mirrors);
+        self.mirrors.links(selection.as_deref(), gallery_id)
+    }
+
+    pub(crate) fn adult_allowed(&self, msg: &Message) -> bool {
+        self.settings
+            .adult_allowed(msg.chat.id.0, msg.chat.is_private())
+    }
+
+    /// 메시지가 온 채팅과 보낸 사람의 차단 목록을 합친 정책.
+    pub(crate) fn content_policy(&self, msg: &Message) -> ContentPolicy {
+        self.settings
+            .content_policy(Some(msg.chat.id.0), msg.from.as_ref().map(|user| user.id.0))
+    }
+
     pub(crate) fn is_after_boot(&self, msg: &Message) -> bool {
         msg.date.timestamp() >= self.booted_at
     }
@@ -131,13 +260,56 @@ impl AppState {
             tracker.records()
         };
 
-        if let Err(err) =
-            persist_planabrain_replies(&self.planabrain_replies_path, &snapshot).await
+        if let Err(err) = persist_planabrain_replies(&self.planabrain_replies_path, &snapshot).await
         {
             warn!("planabrain 응답 기록 저장 실패: {}", err);
         }
     }
 
+    pub(crate) fn store_planabrain_session(&self, session: PlanabrainSession) -> String {
+        match self.planabrain_sessions.write() {
+            Ok(mut sessions) => sessions.insert(session),
+            Err(_) => String::new(),
+        }
+    }
+
+    pub(crate) fn planabrain_session(&self, token: &str) -> Option<PlanabrainSession> {
+        let mut sessions = self.planabrain_sessions.write().ok()?;
+        sessions.get(token)
+    }
+
+    pub(crate) fn update_planabrain_session(&self, token: &str, answer: String) {
+        if let Ok(mut sessions) = self.planabrain_sessions.write()
+            && let Some(session) = sessions.get_mut(token)
+        {
+            session.answer = answer;
+        }
+    }
+
+    pub(crate) fn remove_planabrain_session(&self, token: &str) {
+        if let Ok(mut sessions) = self.planabrain_sessions.write() {
+            sessions.remove(token);
+        }
+    }
+
+    /// 검색 결과를 보관하고 (토큰, 결과)를 돌려줍니다.
+    pub(crate) fn store_search_session(
+        &self,
+        query: String,
+        ids: Vec<u32>,
+    ) -> (String, SearchSession) {
+        let mut sessions = match self.search_sessions.write() {
+            Ok(sessions) => sessions,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let session = SearchSession { query, ids };
+        (sessions.insert(session.clone()), session)
+    }
+
+    pub(crate) fn search_session(&self, token: &str) -> Option<SearchSession> {
+        self.search_sessions.write().ok()?.get(token)
+    }
+
     pub(crate) fn group_chat_ids(&self) -> Vec<ChatId> {
         let registry = self.group_registry.read().ok();
         registry
@@ -245,7 +417,6 @@ async fn persist_planabrain_replies(
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).await?;
     }
-    let payload =
-        serde_json::to_string_pretty(records).unwrap_or_else(|_| "[]".to_string());
+    let payload = serde_json::to_string_pretty(records).unwrap_or_else(|_| "[]".to_string());
     fs::write(path, payload).await
 }
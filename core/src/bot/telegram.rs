@@ -1,13 +1,18 @@
 use anyhow::Result;
 use teloxide::prelude::*;
 use teloxide::sugar::request::RequestLinkPreviewExt;
-use teloxide::types::{InlineKeyboardMarkup, Message, ReplyParameters};
+use teloxide::types::{
+    InlineKeyboardMarkup, LinkPreviewOptions, Message, ParseMode, ReplyParameters,
+};
 
 #[derive(Clone, Default)]
 pub(crate) struct SendOptions {
     pub reply_markup: Option<InlineKeyboardMarkup>,
     pub disable_preview: Option<bool>,
+    /// 있으면 `disable_preview`보다 우선합니다.
+    pub link_preview: Option<LinkPreviewOptions>,
     pub disable_notification: Option<bool>,
+    pub parse_mode: Option<ParseMode>,
 }
 
 pub(crate) fn send_in_thread<B>(bot: &B, msg: &Message, text: impl Into<String>) -> B::SendMessage
@@ -21,28 +26,20 @@ where
     req
 }
 
-pub(crate) fn reply_in_thread<B>(
-    bot: &B,
-    msg: &Message,
-    text: impl Into<String>,
-) -> B::SendMessage
+pub(crate) fn reply_in_thread<B>(bot: &B, msg: &Message, text: impl Into<String>) -> B::SendMessage
 where
     B: Requester + ?Sized,
 {
-    let mut req = bot.send_message(msg.chat.id, text.into()).reply_parameters(
-        ReplyParameters::new(msg.id).allow_sending_without_reply(),
-    );
+    let mut req = bot
+        .send_message(msg.chat.id, text.into())
+        .reply_parameters(ReplyParameters::new(msg.id).allow_sending_without_reply());
     if let Some(thread_id) = msg.thread_id {
         req = req.message_thread_id(thread_id);
     }
     req
 }
 
-pub(crate) fn reply_in_chat<B>(
-    bot: &B,
-    msg: &Message,
-    text: impl Into<String>,
-) -> B::SendMessage
+pub(crate) fn reply_in_chat<B>(bot: &B, msg: &Message, text: impl Into<String>) -> B::SendMessage
 where
     B: Requester + ?Sized,
 {
@@ -69,7 +66,12 @@ where
             let fallback = apply_send_options::<B>(send_in_thread(bot, msg, text), &opts);
             Ok(fallback.await?)
         }
-        Err(err) if err.to_string().to_lowercase().contains("message thread not found") => {
+        Err(err)
+            if err
+                .to_string()
+                .to_lowercase()
+                .contains("message thread not found") =>
+        {
             let fallback = apply_send_options::<B>(reply_in_chat(bot, msg, text.clone()), &opts);
             match fallback.await {
                 Ok(message) => Ok(message),
@@ -84,6 +86,23 @@ where
     }
 }
 
+/// 메시지 보낸 사람이 채팅 관리자인지 확인합니다. 개인 채팅에서는 항상 `true`입니다.
+pub(crate) async fn is_chat_admin<B>(bot: &B, msg: &Message) -> bool
+where
+    B: Requester + ?Sized,
+{
+    if msg.chat.is_private() {
+        return true;
+    }
+    let Some(user) = msg.from.as_ref() else {
+        return false;
+    };
+    bot.get_chat_member(msg.chat.id, user.id)
+        .await
+        .map(|member| member.kind.is_privileged())
+        .unwrap_or(false)
+}
+
 fn apply_send_options<B>(mut req: B::SendMessage, opts: &SendOptions) -> B::SendMessage
 where
     B: Requester + ?Sized,
@@ -91,11 +110,16 @@ where
     if let Some(markup) = &opts.reply_markup {
         req = req.reply_markup(markup.clone());
     }
-    if let Some(disable_preview) = opts.disable_preview {
+    if let Some(options) = &opts.link_preview {
+        req = req.link_preview_options(options.clone());
+    } else if let Some(disable_preview) = opts.disable_preview {
         req = req.disable_link_preview(disable_preview);
     }
     if let Some(disable_notification) = opts.disable_notification {
         req = req.disable_notification(disable_notification);
     }
+    if let Some(parse_mode) = opts.parse_mode {
+        req = req.parse_mode(parse_mode);
+    }
     req
 }
@@ -0,0 +1,139 @@
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 콜백 버튼이 가리키는 값을 짧은 시간 동안 보관합니다.
+///
+/// 토큰은 프로세스마다 무작위로 시드되는 해시로 만들어서, 재시작 뒤 예전 버튼이
+/// 새 세션을 가리키지 않습니다.
+#[derive(Debug)]
+pub(crate) struct TokenStore<T> {
+    ttl: Duration,
+    max: usize,
+    salt: RandomState,
+    counter: u64,
+    order: VecDeque<String>,
+    items: HashMap<String, (Instant, T)>,
+}
+
+impl<T> TokenStore<T> {
+    pub(crate) fn new(ttl: Duration, max: usize) -> Self {
+        Self {
+            ttl,
+            max,
+            salt: RandomState::new(),
+            counter: 0,
+            order: VecDeque::new(),
+            items: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, value: T) -> String {
+        self.evict_expired();
+
+        let token = loop {
+            let token = self.next_token();
+            if !self.items.contains_key(&token) {
+                break token;
+            }
+        };
+        self.order.push_back(token.clone());
+        self.items.insert(token.clone(), (Instant::now(), value));
+
+        while self.order.len() > self.max {
+            if let Some(oldest) = self.order.pop_front() {
+                self.items.remove(&oldest);
+            }
+        }
+
+        token
+    }
+
+    pub(crate) fn get_mut(&mut self, token: &str) -> Option<&mut T> {
+        self.evict_expired();
+        self.items.get_mut(token).map(|(_, value)| value)
+    }
+
+    pub(crate) fn remove(&mut self, token: &str) {
+        self.items.remove(token);
+        self.order.retain(|t| t != token);
+    }
+
+    fn next_token(&mut self) -> String {
+        self.counter = self.counter.wrapping_add(1);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default();
+        let mut hasher = self.salt.build_hasher();
+        hasher.write_u64(self.counter);
+        hasher.write_u128(nanos);
+        format!("{:016x}", hasher.finish())
+    }
+
+    fn evict_expired(&mut self) {
+        let ttl = self.ttl;
+        while let Some(oldest) = self.order.front() {
+            let expired = self
+                .items
+                .get(oldest)
+                .is_none_or(|(created_at, _)| created_at.elapsed() > ttl);
+            if !expired {
+                break;
+            }
+            if let Some(token) = self.order.pop_front() {
+                self.items.remove(&token);
+            }
+        }
+    }
+}
+
+impl<T: Clone> TokenStore<T> {
+    pub(crate) fn get(&mut self, token: &str) -> Option<T> {
+        self.get_mut(token).map(|value| value.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_get_roundtrip() {
+        let mut store = TokenStore::new(Duration::from_secs(60), 10);
+        let token = store.insert("q1");
+        assert_eq!(store.get(&token), Some("q1"));
+        store.remove(&token);
+        assert_eq!(store.get(&token), None);
+    }
+
+    #[test]
+    fn test_capacity_evicts_oldest() {
+        let mut store = TokenStore::new(Duration::from_secs(60), 2);
+        let first = store.insert(1);
+        let second = store.insert(2);
+        let third = store.insert(3);
+        assert!(store.get(&first).is_none());
+        assert_eq!(store.get(&second), Some(2));
+        assert_eq!(store.get(&third), Some(3));
+    }
+
+    #[test]
+    fn test_expired_entries_are_evicted_on_get() {
+        let mut store = TokenStore::new(Duration::ZERO, 10);
+        let token = store.insert(1);
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(store.get(&token).is_none());
+        assert!(store.items.is_empty());
+    }
+
+    #[test]
+    fn test_tokens_differ_between_stores() {
+        // 재시작한 프로세스의 첫 토큰이 이전 프로세스의 첫 토큰과 겹치지 않아야 합니다.
+        let first = TokenStore::new(Duration::from_secs(60), 10).insert(1);
+        let second = TokenStore::new(Duration::from_secs(60), 10).insert(1);
+        assert_ne!(first, second);
+        assert_eq!(first.len(), 16);
+    }
+}
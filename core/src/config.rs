@@ -1,12 +1,22 @@
+use std::collections::HashSet;
 use std::fs;
 use std::path::PathBuf;
 
 use anyhow::{Context, Result, anyhow};
 use dotenvy::dotenv;
 
+use crate::mirrors::{self, MirrorTemplate};
+use crate::urlchanger::LinkRewriter;
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub telegram_api_token: String,
+    /// 운영자 전용 명령어(`/cache` 등)를 쓸 수 있는 사용자 ID
+    pub owner_ids: HashSet<i64>,
+    /// 갤러리 키보드의 미러 링크 템플릿 (`PLANABOT_MIRRORS`)
+    pub(crate) mirrors: Vec<MirrorTemplate>,
+    /// 링크 변환 규칙 (`PLANABOT_LINK_RULES_PATH`, 없으면 기본 규칙)
+    pub(crate) link_rules: LinkRewriter,
 }
 
 impl Config {
@@ -17,6 +27,9 @@ impl Config {
         match std::env::var("TELEGRAM_API_TOKEN") {
             Ok(token) if is_valid(&token) => Ok(Self {
                 telegram_api_token: token,
+                owner_ids: parse_id_list(&std::env::var("PLANABOT_OWNER_IDS").unwrap_or_default()),
+                mirrors: mirrors::templates_from_env()?,
+                link_rules: LinkRewriter::from_env()?,
             }),
             _ => {
                 ensure_env_exists()?;
@@ -28,6 +41,12 @@ impl Config {
     }
 }
 
+fn parse_id_list(raw: &str) -> HashSet<i64> {
+    raw.split(|ch: char| ch == ',' || ch == ';' || ch.is_whitespace())
+        .filter_map(|item| item.trim().parse::<i64>().ok())
+        .collect()
+}
+
 fn is_valid(token: &str) -> bool {
     !token.trim().is_empty() && !token.to_lowercase().contains("your")
 }
@@ -0,0 +1,339 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+use crate::hitomi::GalleryInfo;
+use crate::sources::GalleryRef;
+use crate::util::now_secs;
+
+/// 사용자 한 명이 저장할 수 있는 최대 즐겨찾기 수.
+pub(crate) const MAX_FAVORITES: usize = 1000;
+
+/// 저장 시점의 갤러리 요약. 목록·검색·내보내기를 다시 조회 없이 처리하려고 함께 저장합니다.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Favorite {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub artists: Vec<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    /// 필터용 영문 언어 이름. 이 필드가 생기기 전에 저장한 항목에는 없습니다.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_code: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub added_at: i64,
+}
+
+impl Favorite {
+    pub(crate) fn from_info(info: &GalleryInfo) -> Self {
+        Self {
+            id: info.id.clone(),
+            title: info.title.clone(),
+            artists: info.artists.clone(),
+            language: info.language.clone(),
+            language_code: info.language_code.clone(),
+            tags: info.tags.iter().map(ToString::to_string).collect(),
+            added_at: now_secs(),
+        }
+    }
+
+    pub(crate) fn page_url(&self) -> String {
+        GalleryRef::parse(&self.id)
+            .map(|gallery| gallery.page_url())
+            .unwrap_or_else(|| format!("https://hitomi.la/galleries/{}.html", self.id))
+    }
+}
+
+/// `/favs` 목록 필터. 모든 조건을 만족하는 항목만 남깁니다.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct FavoriteFilter {
+    pub tags: Vec<String>,
+    pub language: Option<String>,
+}
+
+impl FavoriteFilter {
+    /// `tag:<태그>`, `language:<언어>`, 접두사 없는 단어(태그)를 해석합니다. 공백은 `_`로 입력합니다.
+    pub(crate) fn parse(raw: &str) -> Self {
+        let mut filter = Self::default();
+        for token in raw.split_whitespace() {
+            let token = token.to_lowercase().replace('_', " ");
+            if let Some(language) = token
+                .strip_prefix("language:")
+                .or_else(|| token.strip_prefix("lang:"))
+            {
+                filter.language = Some(language.to_string());
+            } else {
+                let tag = token.strip_prefix("tag:").unwrap_or(&token);
+                filter.tags.push(tag.to_string());
+            }
+        }
+        filter
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.language.is_none()
+    }
+
+    pub(crate) fn matches(&self, favorite: &Favorite) -> bool {
+        // `language:korean`과 표시 이름(`language:한국어`) 모두 받습니다.
+        let language_ok = self.language.as_ref().is_none_or(|language| {
+            [&favorite.language_code, &favorite.language]
+                .into_iter()
+                .flatten()
+                .any(|l| l.eq_ignore_ascii_case(language))
+        });
+        // `glasses`는 `female:glasses`에도 맞고, `female:glasses`는 정확히 그 태그에만 맞습니다.
+        let tags_ok = self.tags.iter().all(|wanted| {
+            favorite.tags.iter().any(|tag| {
+                let tag = tag.to_lowercase();
+                tag == *wanted || tag.split_once(':').is_some_and(|(_, name)| name == wanted)
+            })
+        });
+        language_ok && tags_ok
+    }
+}
+
+impl std::fmt::Display for FavoriteFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts: Vec<String> = self
+            .tags
+            .iter()
+            .map(|tag| format!("tag:{}", tag.replace(' ', "_")))
+            .collect();
+        if let Some(language) = &self.language {
+            parts.push(format!("language:{}", language));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FavoritesFile {
+    version: u32,
+    favorites: Vec<Favorite>,
+}
+
+/// 사용자별 즐겨찾기. 사용자마다 JSON 파일 하나를 씁니다.
+#[derive(Clone)]
+pub(crate) struct FavoriteStore {
+    dir: PathBuf,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl FavoriteStore {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub(crate) fn from_env() -> Self {
+        Self::new(resolve_favorites_dir())
+    }
+
+    /// 최근에 저장한 순서로 돌려줍니다.
+    pub(crate) async fn list(&self, user_id: u64) -> Result<Vec<Favorite>> {
+        let mut favorites = load_file(&self.file_path(user_id)).await?.favorites;
+        favorites.reverse();
+        Ok(favorites)
+    }
+
+    pub(crate) async fn contains(&self, user_id: u64, gallery_id: &str) -> Result<bool> {
+        let file = load_file(&self.file_path(user_id)).await?;
+        Ok(file.favorites.iter().any(|f| f.id == gallery_id))
+    }
+
+    /// 이미 있으면 빼고, 없으면 `info`로 추가합니다. 추가했으면 `true`를 돌려줍니다.
+    /// `info`가 없으면 제거만 할 수 있습니다.
+    pub(crate) async fn toggle(
+        &self,
+        user_id: u64,
+        gallery_id: &str,
+        info: Option<&GalleryInfo>,
+    ) -> Result<bool> {
+        let _guard = self.write_lock.lock().await;
+        let path = self.file_path(user_id);
+        let mut file = load_file(&path).await?;
+
+        let added = if let Some(pos) = file.favorites.iter().position(|f| f.id == gallery_id) {
+            file.favorites.remove(pos);
+            false
+        } else {
+            let info = info.ok_or_else(|| anyhow!("갤러리 정보를 불러오지 못했습니다"))?;
+            if file.favorites.len() >= MAX_FAVORITES {
+                return Err(anyhow!(
+                    "즐겨찾기는 최대 {}개까지 저장할 수 있습니다",
+                    MAX_FAVORITES
+                ));
+            }
+            file.favorites.push(Favorite::from_info(info));
+            true
+        };
+
+        file.version = 1;
+        persist_file(&path, &file).await?;
+        Ok(added)
+    }
+
+    fn file_path(&self, user_id: u64) -> PathBuf {
+        self.dir.join(format!("{}.json", user_id))
+    }
+}
+
+pub(crate) fn export_json(user_id: u64, favorites: &[Favorite]) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec_pretty(&serde_json::json!({
+        "version": 1,
+        "user_id": user_id,
+        "favorites": favorites,
+    }))?)
+}
+
+pub(crate) fn export_csv(favorites: &[Favorite]) -> Vec<u8> {
+    let mut out = String::from("id,title,artists,language,tags,added_at,url\n");
+    for favorite in favorites {
+        let row = [
+            favorite.id.clone(),
+            favorite.title.clone(),
+            favorite.artists.join("; "),
+            favorite.language.clone().unwrap_or_default(),
+            favorite.tags.join("; "),
+            favorite.added_at.to_string(),
+            favorite.page_url(),
+        ];
+        let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out.into_bytes()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+async fn load_file(path: &Path) -> Result<FavoritesFile> {
+    let raw = match fs::read_to_string(path).await {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(FavoritesFile::default());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    serde_json::from_str(&raw).context("즐겨찾기 파일 형식이 올바르지 않습니다")
+}
+
+async fn persist_file(path: &Path, file: &FavoritesFile) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(path, serde_json::to_string(file)?).await?;
+    Ok(())
+}
+
+fn resolve_favorites_dir() -> PathBuf {
+    let raw = std::env::var("PLANABOT_FAVORITES_DIR")
+        .unwrap_or_else(|_| ".planabot/favorites".to_string());
+    let path = PathBuf::from(raw);
+    if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitomi::GalleryTag;
+
+    /// Hitomi 갤러리처럼 표시용 현지어 이름과 영문 이름을 함께 채웁니다.
+    fn info(id: &str, language: &str, tags: &[&str]) -> GalleryInfo {
+        let localname = match language {
+            "korean" => "한국어",
+            "japanese" => "日本語",
+            other => other,
+        };
+        GalleryInfo {
+            id: id.to_string(),
+            title: format!("title, \"{id}\""),
+            language: Some(localname.to_string()),
+            language_code: Some(language.to_string()),
+            tags: tags.iter().map(|tag| GalleryTag::parse(tag)).collect(),
+            ..GalleryInfo::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_toggle_adds_then_removes() {
+        let dir = std::env::temp_dir().join(format!("planabot-favs-test-{}", now_secs()));
+        let store = FavoriteStore::new(dir.clone());
+
+        assert!(
+            store
+                .toggle(1, "10", Some(&info("10", "korean", &[])))
+                .await
+                .unwrap()
+        );
+        assert!(
+            store
+                .toggle(1, "20", Some(&info("20", "japanese", &[])))
+                .await
+                .unwrap()
+        );
+        let ids: Vec<_> = store
+            .list(1)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|f| f.id)
+            .collect();
+        assert_eq!(ids, vec!["20", "10"]);
+
+        assert!(!store.toggle(1, "10", None).await.unwrap());
+        assert!(!store.contains(1, "10").await.unwrap());
+        assert!(store.toggle(1, "30", None).await.is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_filter_matches_tags_and_language() {
+        let favorite = Favorite::from_info(&info("1", "korean", &["female:glasses", "full color"]));
+
+        assert!(FavoriteFilter::parse("glasses").matches(&favorite));
+        assert!(FavoriteFilter::parse("tag:female:glasses language:Korean").matches(&favorite));
+        assert!(FavoriteFilter::parse("full_color").matches(&favorite));
+        assert!(!FavoriteFilter::parse("male:glasses").matches(&favorite));
+        assert!(FavoriteFilter::parse("language:한국어").matches(&favorite));
+        assert!(!FavoriteFilter::parse("language:english").matches(&favorite));
+
+        // language_code가 없던 예전 항목은 표시 이름으로만 맞춥니다.
+        let legacy = Favorite {
+            language_code: None,
+            ..favorite
+        };
+        assert!(FavoriteFilter::parse("language:한국어").matches(&legacy));
+        assert!(!FavoriteFilter::parse("language:korean").matches(&legacy));
+    }
+
+    #[test]
+    fn test_export_csv_escapes_fields() {
+        let favorite = Favorite::from_info(&info("1", "korean", &["female:a", "b"]));
+        let csv = String::from_utf8(export_csv(&[favorite])).unwrap();
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.starts_with("1,\"title, \"\"1\"\"\",,한국어,female:a; b,"));
+        assert!(row.ends_with(",https://hitomi.la/galleries/1.html"));
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use anyhow::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+use crate::hitomi::{GalleryClient, GalleryError, SearchArea, SearchTerm};
+use crate::util::env_number;
+
+/// 검색어마다 기억해 두는 최신 갤러리 ID 수. 한 번의 폴링 사이에 이보다 많이 올라오면 놓칩니다.
+const SEEN_WINDOW: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FollowOutcome {
+    Added,
+    AlreadyFollowing,
+    LimitReached,
+}
+
+/// 한 검색어에 새로 올라온 갤러리와 그 검색어를 구독한 채팅.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NewRelease {
+    pub term: String,
+    pub chats: Vec<i64>,
+    /// 최신순.
+    pub ids: Vec<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FollowFile {
+    #[serde(default)]
+    chats: HashMap<i64, Vec<String>>,
+    /// 검색어별로 마지막 폴링 때 본 최신 ID. 없으면 아직 기준점을 잡지 않은 검색어입니다.
+    #[serde(default)]
+    seen: HashMap<String, Vec<u32>>,
+}
+
+/// 채팅별 작가·태그 구독. 메모리에 들고 있다가 바뀔 때마다 JSON 파일로 저장합니다.
+#[derive(Clone)]
+pub(crate) struct FollowStore {
+    path: PathBuf,
+    max_per_chat: usize,
+    inner: Arc<RwLock<FollowFile>>,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl FollowStore {
+    pub(crate) fn new(path: PathBuf, max_per_chat: usize) -> Self {
+        let file = load_file(&path);
+        Self {
+            path,
+            max_per_chat,
+            inner: Arc::new(RwLock::new(file)),
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub(crate) fn from_env() -> Self {
+        let max_per_chat = env_number("PLANABOT_FOLLOW_MAX_PER_CHAT").unwrap_or(20);
+        Self::new(resolve_follows_path(), max_per_chat)
+    }
+
+    pub(crate) fn max_per_chat(&self) -> usize {
+        self.max_per_chat
+    }
+
+    /// 구독할 수 있는 검색어인지. 언어·유형 인덱스는 너무 넓어서 받지 않습니다.
+    pub(crate) fn is_followable(term: &SearchTerm) -> bool {
+        !matches!(term.area, SearchArea::Language | SearchArea::Type)
+    }
+
+    pub(crate) fn list(&self, chat_id: i64) -> Vec<String> {
+        self.inner
+            .read()
+            .ok()
+            .and_then(|file| file.chats.get(&chat_id).cloned())
+            .unwrap_or_default()
+    }
+
+    pub(crate) async fn follow(&self, chat_id: i64, term: &SearchTerm) -> Result<FollowOutcome> {
+        let key = term.to_string();
+        self.update(|file| {
+            let terms = file.chats.entry(chat_id).or_default();
+            if terms.contains(&key) {
+                FollowOutcome::AlreadyFollowing
+            } else if self.max_per_chat > 0 && terms.len() >= self.max_per_chat {
+                FollowOutcome::LimitReached
+            } else {
+                terms.push(key);
+                FollowOutcome::Added
+            }
+        })
+        .await
+    }
+
+    pub(crate) async fn unfollow(&self, chat_id: i64, term: &SearchTerm) -> Result<bool> {
+        let key = term.to_string();
+        self.update(|file| {
+            let Some(terms) = file.chats.get_mut(&chat_id) else {
+                return false;
+            };
+            let before = terms.len();
+            terms.retain(|existing| *existing != key);
+            let removed = terms.len() != before;
+            prune(file);
+            removed
+        })
+        .await
+    }
+
+    /// 봇이 더 이상 보낼 수 없는 채팅의 구독을 모두 지웁니다.
+    pub(crate) async fn remove_chat(&self, chat_id: i64) -> Result<()> {
+        self.update(|file| {
+            file.chats.remove(&chat_id);
+            prune(file);
+        })
+        .await
+    }
+
+    /// 검색어별 구독 채팅. 검색어 순서로 정렬합니다.
+    pub(crate) fn subscriptions(&self) -> BTreeMap<String, Vec<i64>> {
+        let mut subscriptions: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+        if let Ok(file) = self.inner.read() {
+            for (chat_id, terms) in &file.chats {
+                for term in terms {
+                    subscriptions
+                        .entry(term.clone())
+                        .or_default()
+                        .push(*chat_id);
+                }
+            }
+        }
+        for chats in subscriptions.values_mut() {
+            chats.sort_unstable();
+        }
+        subscriptions
+    }
+
+    fn seen(&self, term: &str) -> Option<Vec<u32>> {
+        self.inner
+            .read()
+            .ok()
+            .and_then(|file| file.seen.get(term).cloned())
+    }
+
+    async fn set_seen(&self, term: &str, ids: Vec<u32>) -> Result<()> {
+        self.update(|file| {
+            file.seen.insert(term.to_string(), ids);
+        })
+        .await
+    }
+
+    async fn update<T>(&self, update: impl FnOnce(&mut FollowFile) -> T) -> Result<T> {
+        let _guard = self.write_lock.lock().await;
+        let (result, payload) = {
+            let mut file = self
+                .inner
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let result = update(&mut file);
+            (result, serde_json::to_string_pretty(&*file)?)
+        };
+        persist(&self.path, payload).await?;
+        Ok(result)
+    }
+}
+
+/// 빈 채팅 항목과 아무도 구독하지 않는 검색어의 기준점을 지웁니다.
+fn prune(file: &mut FollowFile) {
+    file.chats.retain(|_, terms| !terms.is_empty());
+    let followed: HashSet<&String> = file.chats.values().flatten().collect();
+    file.seen.retain(|term, _| followed.contains(term));
+}
+
+/// 구독 중인 검색어마다 최신 인덱스를 받아 지난번 이후 새로 올라온 갤러리를 찾습니다.
+/// 처음 보는 검색어는 기준점만 저장하고 알리지 않습니다.
+pub(crate) async fn collect_new_releases(
+    client: &GalleryClient,
+    store: &FollowStore,
+) -> Vec<NewRelease> {
+    let mut releases = Vec::new();
+    for (key, chats) in store.subscriptions() {
+        let Some(term) = SearchTerm::parse(&key) else {
+            continue;
+        };
+        let latest = match client.latest(&term, SEEN_WINDOW).await {
+            Ok(ids) => ids,
+            // 아직 인덱스가 없는 검색어는 기준점을 잡지 않고, 인덱스가 생긴 뒤의 첫 폴링을 기준으로 삼습니다.
+            Err(GalleryError::NotFound) => continue,
+            Err(err) => {
+                warn!("구독 인덱스 확인 실패 ({}): {}", key, err);
+                continue;
+            }
+        };
+
+        let new_ids = match store.seen(&key) {
+            Some(seen) => {
+                let seen: HashSet<u32> = seen.into_iter().collect();
+                latest
+                    .iter()
+                    .copied()
+                    .take_while(|id| !seen.contains(id))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        if let Err(err) = store.set_seen(&key, latest).await {
+            warn!("구독 기준점 저장 실패 ({}): {}", key, err);
+        }
+        if !new_ids.is_empty() {
+            releases.push(NewRelease {
+                term: key,
+                chats,
+                ids: new_ids,
+            });
+        }
+    }
+    releases
+}
+
+fn load_file(path: &Path) -> FollowFile {
+    let Ok(raw) = std::fs::read_to_string(path) else {
+        return FollowFile::default();
+    };
+    serde_json::from_str(&raw).unwrap_or_else(|err| {
+        warn!(
+            "구독 파일 형식이 올바르지 않습니다 ({}): {}",
+            path.display(),
+            err
+        );
+        FollowFile::default()
+    })
+}
+
+async fn persist(path: &Path, payload: String) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(path, payload).await?;
+    Ok(())
+}
+
+fn resolve_follows_path() -> PathBuf {
+    let raw = std::env::var("PLANABOT_FOLLOWS_PATH")
+        .unwrap_or_else(|_| ".planabot/follows.json".to_string());
+    let path = PathBuf::from(raw);
+    if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitomi::GalleryClient;
+    use crate::test_support::{Routes, mock_server};
+
+    fn nozomi(ids: &[u32]) -> Vec<u8> {
+        ids.iter().flat_map(|id| id.to_be_bytes()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_collect_new_releases_against_mock_index() {
+        let routes: Routes = Arc::default();
+        routes.lock().unwrap().insert(
+            "/n/artist/foo%20bar-all.nozomi".to_string(),
+            nozomi(&[30, 20, 10]),
+        );
+        let base_url = mock_server(Arc::clone(&routes));
+        let client = GalleryClient::new().with_data_base_url(&base_url);
+
+        let path = std::env::temp_dir().join(format!(
+            "planabot-follows-test-{}-{}.json",
+            std::process::id(),
+            base_url.rsplit(':').next().unwrap()
+        ));
+        let store = FollowStore::new(path.clone(), 2);
+        let term = SearchTerm::parse("artist:foo_bar").unwrap();
+        assert_eq!(store.follow(-1, &term).await.unwrap(), FollowOutcome::Added);
+        assert_eq!(store.follow(-2, &term).await.unwrap(), FollowOutcome::Added);
+        let missing = SearchTerm::parse("tag:nothing").unwrap();
+        store.follow(-1, &missing).await.unwrap();
+
+        // 첫 폴링은 기준점만 잡습니다.
+        assert!(collect_new_releases(&client, &store).await.is_empty());
+        assert!(store.seen(&missing.to_string()).is_none());
+
+        routes.lock().unwrap().insert(
+            "/n/artist/foo%20bar-all.nozomi".to_string(),
+            nozomi(&[50, 40, 30, 20, 10]),
+        );
+        let releases = collect_new_releases(&client, &store).await;
+        assert_eq!(
+            releases,
+            vec![NewRelease {
+                term: "artist:foo_bar".to_string(),
+                chats: vec![-2, -1],
+                ids: vec![50, 40],
+            }]
+        );
+        assert!(collect_new_releases(&client, &store).await.is_empty());
+
+        // 다시 불러와도 기준점이 유지됩니다.
+        let reloaded = FollowStore::new(path.clone(), 2);
+        assert!(collect_new_releases(&client, &reloaded).await.is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_follow_limits_and_unfollow_prunes() {
+        let path = std::env::temp_dir().join(format!(
+            "planabot-follows-limit-test-{}.json",
+            std::process::id()
+        ));
+        let store = FollowStore::new(path.clone(), 1);
+        let a = SearchTerm::parse("artist:a").unwrap();
+        let b = SearchTerm::parse("female:glasses").unwrap();
+
+        assert_eq!(store.follow(-1, &a).await.unwrap(), FollowOutcome::Added);
+        assert_eq!(
+            store.follow(-1, &a).await.unwrap(),
+            FollowOutcome::AlreadyFollowing
+        );
+        assert_eq!(
+            store.follow(-1, &b).await.unwrap(),
+            FollowOutcome::LimitReached
+        );
+        assert!(store.unfollow(-1, &a).await.unwrap());
+        assert!(!store.unfollow(-1, &a).await.unwrap());
+        assert!(store.subscriptions().is_empty());
+        assert!(!FollowStore::is_followable(
+            &SearchTerm::parse("language:korean").unwrap()
+        ));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::hitomi::GalleryInfo;
+use crate::sources::GalleryRef;
+use crate::util::{env_number, now_secs};
+
+/// 채팅마다 이만큼 추가할 때마다 보관 기간·개수 제한에 맞춰 파일을 정리합니다.
+const COMPACT_EVERY: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LookupSource {
+    Message,
+    Inline,
+}
+
+/// 조회 기록 한 줄. 통계를 다시 조회 없이 낼 수 있도록 요약 정보를 함께 남깁니다.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct LookupRecord {
+    pub gallery_id: String,
+    pub title: String,
+    #[serde(default)]
+    pub artists: Vec<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub user_id: Option<u64>,
+    #[serde(default)]
+    pub user_name: Option<String>,
+    pub source: LookupSource,
+    pub at: i64,
+}
+
+impl LookupRecord {
+    pub(crate) fn new(
+        info: &GalleryInfo,
+        user_id: Option<u64>,
+        user_name: Option<String>,
+        source: LookupSource,
+    ) -> Self {
+        Self {
+            gallery_id: info.id.clone(),
+            title: info.title.clone(),
+            artists: info.artists.clone(),
+            language: info.language.clone(),
+            tags: info.tags.iter().map(ToString::to_string).collect(),
+            user_id,
+            user_name,
+            source,
+            at: now_secs(),
+        }
+    }
+
+    pub(crate) fn page_url(&self) -> String {
+        GalleryRef::parse(&self.gallery_id)
+            .map(|gallery| gallery.page_url())
+            .unwrap_or_else(|| format!("https://hitomi.la/galleries/{}.html", self.gallery_id))
+    }
+}
+
+/// 기간 내 조회 통계.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LookupStats {
+    pub lookups: usize,
+    pub galleries: usize,
+    pub tags: Vec<(String, usize)>,
+    pub artists: Vec<(String, usize)>,
+    pub languages: Vec<(String, usize)>,
+}
+
+impl LookupStats {
+    pub(crate) fn collect(records: &[LookupRecord], top: usize) -> Self {
+        let mut galleries = std::collections::HashSet::new();
+        let mut tags = HashMap::new();
+        let mut artists = HashMap::new();
+        let mut languages = HashMap::new();
+        for record in records {
+            galleries.insert(record.gallery_id.as_str());
+            for tag in &record.tags {
+                *tags.entry(tag.clone()).or_insert(0) += 1;
+            }
+            for artist in &record.artists {
+                *artists.entry(artist.clone()).or_insert(0) += 1;
+            }
+            if let Some(language) = &record.language {
+                *languages.entry(language.clone()).or_insert(0) += 1;
+            }
+        }
+        Self {
+            lookups: records.len(),
+            galleries: galleries.len(),
+            tags: top_counts(tags, top),
+            artists: top_counts(artists, top),
+            languages: top_counts(languages, top),
+        }
+    }
+}
+
+fn top_counts(counts: HashMap<String, usize>, top: usize) -> Vec<(String, usize)> {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(top);
+    counts
+}
+
+/// 채팅별 갤러리 조회 기록. 채팅마다 JSON Lines 파일 하나에 덧붙여 씁니다.
+#[derive(Clone)]
+pub(crate) struct HistoryStore {
+    dir: PathBuf,
+    /// 초 단위. `None`이면 기간 제한 없음.
+    max_age: Option<i64>,
+    max_entries: usize,
+    appended: Arc<Mutex<HashMap<i64, usize>>>,
+}
+
+impl HistoryStore {
+    pub(crate) fn new(dir: PathBuf, max_age: Option<i64>, max_entries: usize) -> Self {
+        Self {
+            dir,
+            max_age,
+            max_entries,
+            appended: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub(crate) fn from_env() -> Self {
+        let retention_days = env_number("PLANABOT_HISTORY_RETENTION_DAYS").unwrap_or(90);
+        let max_entries = env_number("PLANABOT_HISTORY_MAX_ENTRIES").unwrap_or(5000);
+        Self::new(
+            resolve_history_dir(),
+            (retention_days > 0).then(|| retention_days as i64 * 24 * 60 * 60),
+            max_entries,
+        )
+    }
+
+    pub(crate) fn retention_days(&self) -> Option<i64> {
+        self.max_age.map(|secs| secs / (24 * 60 * 60))
+    }
+
+    pub(crate) async fn append(&self, chat_id: i64, record: &LookupRecord) -> Result<()> {
+        // 파일 쓰기와 정리가 겹치지 않도록 카운터 잠금을 쥔 채로 씁니다.
+        let mut appended = self.appended.lock().await;
+        let path = self.file_path(chat_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        // tokio 파일은 flush해야 쓰기가 끝난 뒤에 돌아옵니다. 바로 읽는 `/recent`가 놓치지 않게 합니다.
+        file.flush().await?;
+
+        // 재시작 후 처음 쓸 때와 일정 개수마다 오래된 기록을 정리합니다.
+        let count = appended.entry(chat_id).or_insert(0);
+        if *count % COMPACT_EVERY == 0 {
+            self.compact(&path).await?;
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    /// 보관 기간 안의 기록을 오래된 순서로 돌려줍니다.
+    pub(crate) async fn records(&self, chat_id: i64) -> Result<Vec<LookupRecord>> {
+        let records = read_records(&self.file_path(chat_id)).await?;
+        Ok(self.retain(records, now_secs()))
+    }
+
+    /// 최근 `limit`개를 최신순으로 돌려줍니다.
+    pub(crate) async fn recent(&self, chat_id: i64, limit: usize) -> Result<Vec<LookupRecord>> {
+        let mut records = self.records(chat_id).await?;
+        records.reverse();
+        records.truncate(limit);
+        Ok(records)
+    }
+
+    /// 최근 `days`일 동안의 통계.
+    pub(crate) async fn stats(&self, chat_id: i64, days: i64, top: usize) -> Result<LookupStats> {
+        let since = now_secs() - days * 24 * 60 * 60;
+        let records: Vec<_> = self
+            .records(chat_id)
+            .await?
+            .into_iter()
+            .filter(|record| record.at >= since)
+            .collect();
+        Ok(LookupStats::collect(&records, top))
+    }
+
+    pub(crate) async fn clear(&self, chat_id: i64) -> Result<bool> {
+        let _guard = self.appended.lock().await;
+        match fs::remove_file(self.file_path(chat_id)).await {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn compact(&self, path: &Path) -> Result<()> {
+        let records = read_records(path).await?;
+        let before = records.len();
+        let records = self.retain(records, now_secs());
+        if records.len() == before {
+            return Ok(());
+        }
+        let mut payload = String::new();
+        for record in &records {
+            payload.push_str(&serde_json::to_string(record)?);
+            payload.push('\n');
+        }
+        fs::write(path, payload).await?;
+        Ok(())
+    }
+
+    fn retain(&self, mut records: Vec<LookupRecord>, now: i64) -> Vec<LookupRecord> {
+        if let Some(max_age) = self.max_age {
+            records.retain(|record| now - record.at <= max_age);
+        }
+        if self.max_entries > 0 && records.len() > self.max_entries {
+            records.drain(..records.len() - self.max_entries);
+        }
+        records
+    }
+
+    fn file_path(&self, chat_id: i64) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", chat_id))
+    }
+}
+
+async fn read_records(path: &Path) -> Result<Vec<LookupRecord>> {
+    let raw = match fs::read_to_string(path).await {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    // 쓰다 끊긴 줄 하나 때문에 전체를 버리지 않도록 줄 단위로 읽습니다.
+    Ok(raw
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(record) => Some(record),
+            Err(err) => {
+                warn!("조회 기록 줄을 건너뜁니다 ({}): {}", path.display(), err);
+                None
+            }
+        })
+        .collect())
+}
+
+fn resolve_history_dir() -> PathBuf {
+    let raw =
+        std::env::var("PLANABOT_HISTORY_DIR").unwrap_or_else(|_| ".planabot/history".to_string());
+    let path = PathBuf::from(raw);
+    if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, tags: &[&str], at: i64) -> LookupRecord {
+        LookupRecord {
+            gallery_id: id.to_string(),
+            title: format!("title {id}"),
+            artists: vec!["artist".to_string()],
+            language: Some("korean".to_string()),
+            tags: tags.iter().map(ToString::to_string).collect(),
+            user_id: Some(1),
+            user_name: Some("선생님".to_string()),
+            source: LookupSource::Message,
+            at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_append_recent_and_clear() {
+        let dir = std::env::temp_dir().join(format!(
+            "planabot-history-test-{}-{}",
+            std::process::id(),
+            now_secs()
+        ));
+        let store = HistoryStore::new(dir.clone(), Some(24 * 60 * 60), 2);
+        let now = now_secs();
+
+        store
+            .append(-100, &record("1", &[], now - 2 * 24 * 60 * 60))
+            .await
+            .unwrap();
+        store.append(-100, &record("2", &[], now)).await.unwrap();
+        store.append(-100, &record("3", &[], now)).await.unwrap();
+        store.append(-100, &record("4", &[], now)).await.unwrap();
+
+        let ids: Vec<_> = store
+            .recent(-100, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.gallery_id)
+            .collect();
+        assert_eq!(ids, vec!["4", "3"]);
+
+        assert!(store.clear(-100).await.unwrap());
+        assert!(store.recent(-100, 10).await.unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_stats_counts_and_ranks() {
+        let records = vec![
+            record("1", &["female:glasses", "full color"], 0),
+            record("2", &["female:glasses"], 0),
+            record("1", &["female:glasses", "full color"], 0),
+        ];
+        let stats = LookupStats::collect(&records, 1);
+        assert_eq!(stats.lookups, 3);
+        assert_eq!(stats.galleries, 2);
+        assert_eq!(stats.tags, vec![("female:glasses".to_string(), 3)]);
+        assert_eq!(stats.languages, vec![("korean".to_string(), 3)]);
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::util::now_secs;
+
+use super::parser::GalleryInfo;
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub capacity: usize,
+    pub ttl: Duration,
+    pub negative_ttl: Duration,
+    pub persist_path: Option<PathBuf>,
+}
+
+impl CacheConfig {
+    pub fn from_env() -> Self {
+        let secs = |key: &str, default: u64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|raw| raw.trim().parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(Duration::from_secs(default))
+        };
+
+        Self {
+            capacity: std::env::var("PLANABOT_GALLERY_CACHE_SIZE")
+                .ok()
+                .and_then(|raw| raw.trim().parse().ok())
+                .unwrap_or(500),
+            ttl: secs("PLANABOT_GALLERY_CACHE_TTL_SECS", 60 * 60),
+            negative_ttl: secs("PLANABOT_GALLERY_CACHE_NEGATIVE_TTL_SECS", 5 * 60),
+            persist_path: std::env::var("PLANABOT_GALLERY_CACHE_PATH")
+                .ok()
+                .filter(|raw| !raw.trim().is_empty())
+                .map(PathBuf::from),
+        }
+    }
+}
+
+/// 캐시 조회 결과. `NotFound`는 최근에 "없음"으로 확인된 ID입니다.
+#[derive(Debug, Clone)]
+pub enum CacheLookup {
+    Hit(Box<GalleryInfo>),
+    NotFound,
+    Miss,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub negative_hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.negative_hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            (self.hits + self.negative_hits) as f64 / total as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    value: Option<GalleryInfo>,
+    expires_at: i64,
+    #[serde(skip)]
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheInner {
+    entries: HashMap<String, CacheEntry>,
+    clock: u64,
+}
+
+/// 갤러리 정보 LRU 캐시. 만료 시간(TTL)이 지난 항목은 조회 시 버립니다.
+#[derive(Debug)]
+pub struct GalleryCache {
+    config: CacheConfig,
+    inner: Mutex<CacheInner>,
+    hits: AtomicU64,
+    negative_hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl GalleryCache {
+    pub fn new(config: CacheConfig) -> Self {
+        let entries = config
+            .persist_path
+            .as_deref()
+            .map(load_entries)
+            .unwrap_or_default();
+
+        Self {
+            config,
+            inner: Mutex::new(CacheInner { entries, clock: 0 }),
+            hits: AtomicU64::new(0),
+            negative_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, gallery_id: &str) -> CacheLookup {
+        let now = now_secs();
+        let Ok(mut inner) = self.inner.lock() else {
+            return CacheLookup::Miss;
+        };
+        inner.clock += 1;
+        let clock = inner.clock;
+
+        let lookup = match inner.entries.get_mut(gallery_id) {
+            Some(entry) if entry.expires_at > now => {
+                entry.last_used = clock;
+                match &entry.value {
+                    Some(info) => CacheLookup::Hit(Box::new(info.clone())),
+                    None => CacheLookup::NotFound,
+                }
+            }
+            Some(_) => {
+                inner.entries.remove(gallery_id);
+                CacheLookup::Miss
+            }
+            None => CacheLookup::Miss,
+        };
+
+        let counter = match lookup {
+            CacheLookup::Hit(_) => &self.hits,
+            CacheLookup::NotFound => &self.negative_hits,
+            CacheLookup::Miss => &self.misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        lookup
+    }
+
+    pub fn insert(&self, gallery_id: &str, info: GalleryInfo) {
+        self.store(gallery_id, Some(info), self.config.ttl);
+    }
+
+    pub fn insert_not_found(&self, gallery_id: &str) {
+        self.store(gallery_id, None, self.config.negative_ttl);
+    }
+
+    fn store(&self, gallery_id: &str, value: Option<GalleryInfo>, ttl: Duration) {
+        if self.config.capacity == 0 || ttl.is_zero() {
+            return;
+        }
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        inner.clock += 1;
+        let entry = CacheEntry {
+            value,
+            expires_at: now_secs() + ttl.as_secs() as i64,
+            last_used: inner.clock,
+        };
+        inner.entries.insert(gallery_id.to_string(), entry);
+
+        while inner.entries.len() > self.config.capacity {
+            let Some(oldest) = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            inner.entries.remove(&oldest);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 모든 항목을 지우고 지운 개수를 돌려줍니다.
+    pub fn clear(&self) -> usize {
+        let Ok(mut inner) = self.inner.lock() else {
+            return 0;
+        };
+        let removed = inner.entries.len();
+        inner.entries.clear();
+        removed
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self
+            .inner
+            .lock()
+            .map(|inner| inner.entries.len())
+            .unwrap_or(0);
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            negative_hits: self.negative_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries,
+        }
+    }
+
+    /// 설정된 경로가 있으면 만료되지 않은 항목을 디스크에 저장합니다.
+    pub async fn persist(&self) {
+        let Some(path) = self.config.persist_path.as_deref() else {
+            return;
+        };
+
+        let now = now_secs();
+        let snapshot: HashMap<String, CacheEntry> = match self.inner.lock() {
+            Ok(inner) => inner
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.expires_at > now)
+                .map(|(key, entry)| (key.clone(), entry.clone()))
+                .collect(),
+            Err(_) => return,
+        };
+
+        if let Err(err) = persist_entries(path, &snapshot).await {
+            warn!("갤러리 캐시 저장 실패: {}", err);
+        }
+    }
+}
+
+fn load_entries(path: &Path) -> HashMap<String, CacheEntry> {
+    let Ok(raw) = std::fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    let Ok(mut entries) = serde_json::from_str::<HashMap<String, CacheEntry>>(&raw) else {
+        return HashMap::new();
+    };
+    let now = now_secs();
+    entries.retain(|_, entry| entry.expires_at > now);
+    entries
+}
+
+async fn persist_entries(
+    path: &Path,
+    entries: &HashMap<String, CacheEntry>,
+) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let payload = serde_json::to_string(entries).unwrap_or_else(|_| "{}".to_string());
+    tokio::fs::write(path, payload).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(capacity: usize) -> CacheConfig {
+        CacheConfig {
+            capacity,
+            ttl: Duration::from_secs(60),
+            negative_ttl: Duration::from_secs(60),
+            persist_path: None,
+        }
+    }
+
+    fn info(id: &str) -> GalleryInfo {
+        GalleryInfo {
+            id: id.to_string(),
+            title: format!("title {id}"),
+            ..GalleryInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_hit_miss_and_negative_lookups_are_counted() {
+        let cache = GalleryCache::new(config(10));
+        cache.insert("1", info("1"));
+        cache.insert_not_found("2");
+
+        assert!(matches!(cache.get("1"), CacheLookup::Hit(i) if i.id == "1"));
+        assert!(matches!(cache.get("2"), CacheLookup::NotFound));
+        assert!(matches!(cache.get("3"), CacheLookup::Miss));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.negative_hits, stats.misses), (1, 1, 1));
+        assert_eq!(stats.entries, 2);
+    }
+
+    #[test]
+    fn test_least_recently_used_entry_is_evicted() {
+        let cache = GalleryCache::new(config(2));
+        cache.insert("1", info("1"));
+        cache.insert("2", info("2"));
+        let _ = cache.get("1");
+        cache.insert("3", info("3"));
+
+        assert!(matches!(cache.get("2"), CacheLookup::Miss));
+        assert!(matches!(cache.get("1"), CacheLookup::Hit(_)));
+        assert_eq!(cache.stats().evictions, 1);
+    }
+
+    #[test]
+    fn test_zero_ttl_disables_caching() {
+        let cache = GalleryCache::new(CacheConfig {
+            negative_ttl: Duration::ZERO,
+            ..config(10)
+        });
+        cache.insert_not_found("1");
+        assert!(matches!(cache.get("1"), CacheLookup::Miss));
+    }
+
+    #[test]
+    fn test_clear_removes_entries() {
+        let cache = GalleryCache::new(config(10));
+        cache.insert("1", info("1"));
+        assert_eq!(cache.clear(), 1);
+        assert!(matches!(cache.get("1"), CacheLookup::Miss));
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+
+/// 갤러리 조회 실패 사유.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GalleryError {
+    /// 해당 ID의 갤러리가 없습니다 (404).
+    NotFound,
+    /// 업스트림이 2xx/404가 아닌 상태 코드로 응답했습니다.
+    Upstream(StatusCode),
+    /// 연결 실패 등 네트워크 오류.
+    Network(String),
+    /// 응답은 받았지만 갤러리 정보로 해석하지 못했습니다.
+    Parse(String),
+    /// 요청 시간이 초과되었습니다.
+    Timeout,
+}
+
+impl GalleryError {
+    /// 잠시 후 다시 시도하면 성공할 수 있는 오류인지 여부.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            GalleryError::Network(_) | GalleryError::Timeout => true,
+            GalleryError::Upstream(status) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            GalleryError::NotFound | GalleryError::Parse(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for GalleryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GalleryError::NotFound => write!(f, "갤러리를 찾을 수 없습니다"),
+            GalleryError::Upstream(status) => write!(f, "업스트림 응답 오류: {}", status),
+            GalleryError::Network(err) => write!(f, "네트워크 오류: {}", err),
+            GalleryError::Parse(err) => write!(f, "응답 해석 실패: {}", err),
+            GalleryError::Timeout => write!(f, "요청 시간 초과"),
+        }
+    }
+}
+
+impl std::error::Error for GalleryError {}
+
+impl From<reqwest::Error> for GalleryError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            GalleryError::Timeout
+        } else if let Some(status) = err.status() {
+            GalleryError::Upstream(status)
+        } else {
+            GalleryError::Network(err.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transient_errors() {
+        assert!(GalleryError::Timeout.is_transient());
+        assert!(GalleryError::Network("reset".to_string()).is_transient());
+        assert!(GalleryError::Upstream(StatusCode::BAD_GATEWAY).is_transient());
+        assert!(GalleryError::Upstream(StatusCode::TOO_MANY_REQUESTS).is_transient());
+        assert!(!GalleryError::Upstream(StatusCode::FORBIDDEN).is_transient());
+        assert!(!GalleryError::NotFound.is_transient());
+        assert!(!GalleryError::Parse("bad".to_string()).is_transient());
+    }
+}
@@ -1,3 +1,9 @@
+mod cache;
+mod error;
 mod parser;
+mod search;
 
-pub use parser::{GalleryClient, GalleryInfo};
+pub(crate) use cache::{CacheConfig, CacheLookup, GalleryCache};
+pub use error::GalleryError;
+pub use parser::{GalleryClient, GalleryInfo, GalleryTag, TagKind};
+pub use search::{SearchArea, SearchTerm, parse_query};
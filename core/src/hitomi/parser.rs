@@ -1,89 +1,377 @@
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result};
 use log::{error, warn};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use reqwest::{Client, StatusCode};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use super::cache::{CacheConfig, CacheLookup, CacheStats, GalleryCache};
+use super::error::GalleryError;
+use super::search::{self, SearchArea, SearchTerm};
+use crate::sources::GalleryRef;
+use crate::util::USER_AGENT;
 
 #[derive(Clone)]
 pub struct GalleryClient {
     client: Client,
+    cache: Arc<GalleryCache>,
+    data_base_url: Arc<str>,
+    language_indexes: Arc<Mutex<HashMap<String, CachedIndex>>>,
 }
 
+/// 언어 인덱스는 수십 MB라서 검색마다 받지 않고 잠시 재사용합니다.
+type CachedIndex = (Instant, Arc<[u32]>);
+
+const DEFAULT_DATA_BASE_URL: &str = "https://ltn.gold-usergeneratedcontent.net";
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+const LANGUAGE_INDEX_TTL: Duration = Duration::from_secs(30 * 60);
+
 impl GalleryClient {
     pub fn new() -> Self {
+        let client = Self::with_cache(CacheConfig::from_env());
+        match std::env::var("PLANABOT_HITOMI_DATA_URL") {
+            Ok(url) if !url.trim().is_empty() => client.with_data_base_url(url.trim()),
+            _ => client,
+        }
+    }
+
+    pub fn with_cache(cache: CacheConfig) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(15))
-            .user_agent("eyetracker-rs/0.1")
+            .user_agent(USER_AGENT)
             .build()
             .expect("reqwest client should build");
 
-        Self { client }
+        Self {
+            client,
+            cache: Arc::new(GalleryCache::new(cache)),
+            data_base_url: Arc::from(DEFAULT_DATA_BASE_URL),
+            language_indexes: Arc::default(),
+        }
     }
 
-    pub async fn get_gallery_info(&self, gallery_id: &str) -> Result<Option<GalleryInfo>> {
-        let url = format!(
-            "https://ltn.gold-usergeneratedcontent.net/galleries/{}.js",
-            gallery_id
-        );
-        let referer = format!("https://hitomi.la/reader/{}.html", gallery_id);
+    /// 갤러리 JS·nozomi 인덱스를 받을 서버를 바꿉니다. (미러나 테스트용 서버)
+    pub fn with_data_base_url(mut self, url: &str) -> Self {
+        self.data_base_url = Arc::from(url.trim_end_matches('/'));
+        self
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    pub fn clear_cache(&self) -> usize {
+        self.cache.clear()
+    }
 
+    pub async fn persist_cache(&self) {
+        self.cache.persist().await;
+    }
+
+    /// 표지 썸네일을 내려받습니다. 썸네일 서버는 Referer를 확인하므로 Telegram에 주소를
+    /// 넘기지 않고 직접 받아서 보냅니다.
+    pub async fn fetch_cover(&self, info: &GalleryInfo) -> Option<Vec<u8>> {
+        const MAX_COVER_BYTES: usize = 5 * 1024 * 1024;
+
+        let url = info.cover_url()?;
         let response = match self
             .client
             .get(&url)
-            .header("Referer", referer)
+            .header("Referer", info.hitomi_url())
             .send()
             .await
         {
-            Ok(resp) => resp,
+            Ok(resp) if resp.status().is_success() => resp,
+            Ok(resp) => {
+                warn!("표지 요청 실패 (ID {}): {}", info.id, resp.status());
+                return None;
+            }
             Err(err) => {
-                warn!("갤러리 JS 요청 실패 (ID {}): {}", gallery_id, err);
-                return Ok(None);
+                warn!("표지 요청 실패 (ID {}): {}", info.id, err);
+                return None;
+            }
+        };
+
+        if response
+            .content_length()
+            .is_some_and(|len| len as usize > MAX_COVER_BYTES)
+        {
+            return None;
+        }
+
+        let bytes = response.bytes().await.ok()?;
+        (bytes.len() <= MAX_COVER_BYTES).then(|| bytes.to_vec())
+    }
+
+    /// 캐시를 먼저 확인하고, 없으면 Hitomi에서 가져와 캐시에 저장합니다.
+    /// 404는 짧은 시간 동안 "없음"으로 캐시하고, 그 밖의 실패는 캐시하지 않습니다.
+    /// 일시적인 오류(네트워크, 시간 초과, 5xx/429)는 지수 백오프로 재시도합니다.
+    pub async fn get_gallery_info(&self, gallery_id: &str) -> Result<GalleryInfo, GalleryError> {
+        match self.cache.get(gallery_id) {
+            CacheLookup::Hit(info) => return Ok(*info),
+            CacheLookup::NotFound => return Err(GalleryError::NotFound),
+            CacheLookup::Miss => {}
+        }
+
+        let mut attempt = 0;
+        let result = loop {
+            match self.fetch_gallery_info(gallery_id).await {
+                Err(err) if err.is_transient() && attempt + 1 < MAX_ATTEMPTS => {
+                    let delay = RETRY_BASE_DELAY * 2u32.pow(attempt);
+                    warn!(
+                        "갤러리 조회 재시도 예정 (ID {}, {}회차, {:?} 후): {}",
+                        gallery_id,
+                        attempt + 1,
+                        delay,
+                        err
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => break result,
             }
         };
 
+        match &result {
+            Ok(info) => self.cache.insert(gallery_id, info.clone()),
+            Err(GalleryError::NotFound) => self.cache.insert_not_found(gallery_id),
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// 여러 ID를 최대 `concurrency`개씩 동시에 조회합니다. 결과는 입력 순서를 따릅니다.
+    pub async fn get_many(
+        &self,
+        gallery_ids: &[String],
+        concurrency: usize,
+    ) -> Vec<(String, Result<GalleryInfo, GalleryError>)> {
+        let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+        let mut tasks = JoinSet::new();
+
+        for (index, gallery_id) in gallery_ids.iter().cloned().enumerate() {
+            let client = self.clone();
+            let permits = Arc::clone(&permits);
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let result = client.get_gallery_info(&gallery_id).await;
+                (index, gallery_id, result)
+            });
+        }
+
+        let mut results = Vec::with_capacity(gallery_ids.len());
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(result) => results.push(result),
+                Err(err) => error!("갤러리 일괄 조회 작업 실패: {}", err),
+            }
+        }
+        results.sort_by_key(|(index, _, _)| *index);
+        results
+            .into_iter()
+            .map(|(_, gallery_id, result)| (gallery_id, result))
+            .collect()
+    }
+
+    /// 모든 검색어의 nozomi 인덱스를 받아 교집합을 최신순으로 돌려줍니다.
+    /// 인덱스가 없는 검색어(404)는 결과가 없는 것으로 봅니다.
+    pub async fn search(&self, terms: &[SearchTerm]) -> Result<Vec<u32>, GalleryError> {
+        let mut lists = Vec::with_capacity(terms.len());
+        for term in terms {
+            let ids = match term.area {
+                SearchArea::Language => self.language_index(term).await,
+                _ => self.fetch_nozomi(term, None).await.map(Arc::from),
+            };
+            match ids {
+                Ok(ids) => lists.push(ids),
+                Err(GalleryError::NotFound) => return Ok(Vec::new()),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(search::intersect(&lists))
+    }
+
+    async fn language_index(&self, term: &SearchTerm) -> Result<Arc<[u32]>, GalleryError> {
+        if let Some((fetched_at, ids)) = self
+            .language_indexes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&term.value)
+            && fetched_at.elapsed() < LANGUAGE_INDEX_TTL
+        {
+            return Ok(Arc::clone(ids));
+        }
+
+        let ids: Arc<[u32]> = Arc::from(self.fetch_nozomi(term, None).await?);
+        let mut indexes = self
+            .language_indexes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        indexes.retain(|_, (fetched_at, _)| fetched_at.elapsed() < LANGUAGE_INDEX_TTL);
+        indexes.insert(term.value.clone(), (Instant::now(), Arc::clone(&ids)));
+        Ok(ids)
+    }
+
+    /// 검색어 하나의 최신 갤러리 ID를 최대 `limit`개 돌려줍니다. 인덱스 앞부분만 받습니다.
+    pub async fn latest(&self, term: &SearchTerm, limit: usize) -> Result<Vec<u32>, GalleryError> {
+        let mut ids = self.fetch_nozomi(term, Some(limit * 4)).await?;
+        ids.truncate(limit);
+        Ok(ids)
+    }
+
+    async fn fetch_nozomi(
+        &self,
+        term: &SearchTerm,
+        max_bytes: Option<usize>,
+    ) -> Result<Vec<u32>, GalleryError> {
+        const MAX_NOZOMI_BYTES: usize = 32 * 1024 * 1024;
+
+        let url = format!("{}/{}", self.data_base_url, term.nozomi_path());
+        let mut request = self
+            .client
+            .get(&url)
+            .header("Referer", "https://hitomi.la/search.html");
+        if let Some(max_bytes) = max_bytes.filter(|bytes| *bytes > 0) {
+            request = request.header("Range", format!("bytes=0-{}", max_bytes - 1));
+        }
+        let response = request
+            .send()
+            .await
+            .inspect_err(|err| warn!("nozomi 요청 실패 ({}): {}", term, err))?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => return Err(GalleryError::NotFound),
+            // 빈 인덱스에 범위 요청을 보내면 416이 옵니다.
+            StatusCode::RANGE_NOT_SATISFIABLE if max_bytes.is_some() => return Ok(Vec::new()),
+            status if !status.is_success() => {
+                warn!("nozomi 응답 오류 ({}): {}", term, status);
+                return Err(GalleryError::Upstream(status));
+            }
+            _ => {}
+        }
+
+        let bytes = response.bytes().await?;
+        if bytes.len() > MAX_NOZOMI_BYTES {
+            return Err(GalleryError::Parse(format!(
+                "인덱스가 너무 큽니다 ({} bytes)",
+                bytes.len()
+            )));
+        }
+        Ok(search::decode_nozomi(&bytes))
+    }
+
+    async fn fetch_gallery_info(&self, gallery_id: &str) -> Result<GalleryInfo, GalleryError> {
+        let url = format!("{}/galleries/{}.js", self.data_base_url, gallery_id);
+        let referer = format!("https://hitomi.la/reader/{}.html", gallery_id);
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Referer", referer)
+            .send()
+            .await
+            .inspect_err(|err| warn!("갤러리 JS 요청 실패 (ID {}): {}", gallery_id, err))?;
+
         if response.status() == StatusCode::NOT_FOUND {
-            return Ok(None);
+            return Err(GalleryError::NotFound);
         }
 
         if !response.status().is_success() {
+            let status = response.status();
             warn!(
                 "갤러리 JS 응답 오류 (ID {}): {} {}",
                 gallery_id,
-                response.status(),
+                status,
                 response.text().await.unwrap_or_default()
             );
-            return Ok(None);
+            return Err(GalleryError::Upstream(status));
         }
 
         let raw_text = response
             .text()
             .await
-            .context("갤러리 JS 응답을 텍스트로 읽는데 실패")?;
+            .inspect_err(|err| warn!("갤러리 JS 응답 읽기 실패 (ID {}): {}", gallery_id, err))?;
 
-        let normalized = normalize_js_payload(raw_text);
+        parse_gallery_payload(gallery_id, raw_text).inspect_err(|err| {
+            error!("갤러리 JSON 파싱 실패 (ID {}): {}", gallery_id, err);
+        })
+    }
+}
 
-        let raw: GalleryRaw = match serde_json::from_str(&normalized) {
-            Ok(data) => data,
-            Err(err) => {
-                error!("갤러리 JSON 파싱 실패 (ID {}): {}", gallery_id, err);
-                return Ok(None);
-            }
+fn parse_gallery_payload(gallery_id: &str, raw_text: String) -> Result<GalleryInfo, GalleryError> {
+    let normalized = normalize_js_payload(raw_text);
+    let raw: GalleryRaw =
+        serde_json::from_str(&normalized).map_err(|err| GalleryError::Parse(err.to_string()))?;
+    Ok(GalleryInfo::from_raw(gallery_id.to_string(), raw))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagKind {
+    Female,
+    Male,
+    Plain,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GalleryTag {
+    pub kind: TagKind,
+    pub name: String,
+}
+
+impl GalleryTag {
+    /// `female:big breasts`, `male:glasses`, `full color` 형식의 태그 문자열을 해석합니다.
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw.trim();
+        let (kind, name) = match raw.split_once(':') {
+            Some(("female", name)) => (TagKind::Female, name),
+            Some(("male", name)) => (TagKind::Male, name),
+            _ => (TagKind::Plain, raw),
         };
+        Self {
+            kind,
+            name: name.trim().to_string(),
+        }
+    }
+}
 
-        Ok(Some(GalleryInfo::from_raw(gallery_id.to_string(), raw)))
+impl std::fmt::Display for GalleryTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            TagKind::Female => write!(f, "female:{}", self.name),
+            TagKind::Male => write!(f, "male:{}", self.name),
+            TagKind::Plain => write!(f, "{}", self.name),
+        }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GalleryInfo {
     pub id: String,
     pub title: String,
-    pub artists: String,
-    pub language: String,
-    pub tags: Vec<String>,
+    pub japanese_title: Option<String>,
+    pub gallery_type: Option<String>,
+    pub artists: Vec<String>,
+    pub groups: Vec<String>,
+    pub series: Vec<String>,
+    pub characters: Vec<String>,
+    /// 표시용 언어 이름 (Hitomi는 `한국어`처럼 현지어 이름)
+    pub language: Option<String>,
+    /// 출처가 쓰는 영문 언어 이름 (`korean`). 검색·필터는 이 값을 봅니다.
+    #[serde(default)]
+    pub language_code: Option<String>,
+    pub tags: Vec<GalleryTag>,
+    pub page_count: usize,
+    /// 업로드 날짜 (`YYYY-MM-DD`)
+    pub date: Option<String>,
+    /// 첫 페이지 해시. 표지 썸네일 주소를 만들 때 씁니다.
+    pub cover_hash: Option<String>,
 }
 
 impl GalleryInfo {
@@ -91,8 +379,29 @@ impl GalleryInfo {
         format!("https://hitomi.la/galleries/{}.html", self.id)
     }
 
-    pub fn k_hentai_url(&self) -> String {
-        format!("https://k-hentai.org/r/{}", self.id)
+    /// 출처의 갤러리 페이지 주소. `id`의 출처 접두사를 따릅니다.
+    pub fn page_url(&self) -> String {
+        GalleryRef::parse(&self.id)
+            .map(|gallery| gallery.page_url())
+            .unwrap_or_else(|| self.hitomi_url())
+    }
+
+    /// 표지 썸네일 주소. Hitomi는 해시 끝 세 글자로 경로를 나눕니다 (`…abc` → `c/ab/…abc`).
+    pub fn cover_url(&self) -> Option<String> {
+        let hash = self.cover_hash.as_deref()?;
+        if hash.len() < 3 || !hash.is_ascii() {
+            return None;
+        }
+        let last = &hash[hash.len() - 1..];
+        let middle = &hash[hash.len() - 3..hash.len() - 1];
+        Some(format!(
+            "https://tn.gold-usergeneratedcontent.net/webpbigtn/{}/{}/{}.webp",
+            last, middle, hash
+        ))
+    }
+
+    pub fn tags_of(&self, kind: TagKind) -> impl Iterator<Item = &GalleryTag> {
+        self.tags.iter().filter(move |tag| tag.kind == kind)
     }
 
     fn from_raw(id: String, raw: GalleryRaw) -> Self {
@@ -101,25 +410,28 @@ impl GalleryInfo {
             .or(raw.n)
             .unwrap_or_else(|| "정보 없음".to_string());
 
-        let tags = merge_tags(raw.tags, raw.t);
-        let artists_vec = merge_artists(raw.artists, raw.a);
-        let artists = if artists_vec.is_empty() {
-            "정보 없음".to_string()
-        } else {
-            artists_vec.join(", ")
-        };
-
-        let language = raw
-            .language_localname
-            .or(raw.language)
-            .unwrap_or_else(|| "정보 없음".to_string());
+        let language_code = raw.language.clone();
+        let language = raw.language_localname.or(raw.language);
+        let date = raw
+            .date
+            .map(|date| date.chars().take(10).collect::<String>())
+            .filter(|date| !date.is_empty());
 
         Self {
             id,
             title,
-            artists,
+            japanese_title: raw.japanese_title.filter(|t| !t.trim().is_empty()),
+            gallery_type: raw.gallery_type.filter(|t| !t.trim().is_empty()),
+            artists: merge_names([raw.artists, raw.a], "artist"),
+            groups: merge_names([raw.groups, Vec::new()], "group"),
+            series: merge_names([raw.parodys, Vec::new()], "parody"),
+            characters: merge_names([raw.characters, Vec::new()], "character"),
             language,
-            tags,
+            language_code,
+            tags: merge_tags(raw.tags, raw.t),
+            page_count: raw.files.len(),
+            date,
+            cover_hash: raw.files.into_iter().next().and_then(|file| file.hash),
         }
     }
 }
@@ -131,62 +443,112 @@ struct GalleryRaw {
     #[serde(default)]
     n: Option<String>,
     #[serde(default)]
-    tags: Vec<Tag>,
-    #[serde(default)]
-    t: Vec<Tag>,
-    #[serde(default)]
-    artists: Vec<Artist>,
-    #[serde(default)]
-    a: Vec<Artist>,
+    japanese_title: Option<String>,
+    #[serde(default, rename = "type")]
+    gallery_type: Option<String>,
+    #[serde(default, deserialize_with = "nullable_list")]
+    tags: Vec<Named>,
+    #[serde(default, deserialize_with = "nullable_list")]
+    t: Vec<Named>,
+    #[serde(default, deserialize_with = "nullable_list")]
+    artists: Vec<Named>,
+    #[serde(default, deserialize_with = "nullable_list")]
+    a: Vec<Named>,
+    #[serde(default, deserialize_with = "nullable_list")]
+    groups: Vec<Named>,
+    #[serde(default, deserialize_with = "nullable_list")]
+    parodys: Vec<Named>,
+    #[serde(default, deserialize_with = "nullable_list")]
+    characters: Vec<Named>,
     #[serde(default)]
     language_localname: Option<String>,
     #[serde(default)]
     language: Option<String>,
+    #[serde(default)]
+    date: Option<String>,
+    #[serde(default, deserialize_with = "nullable_list")]
+    files: Vec<FileRaw>,
 }
 
 #[derive(Debug, Deserialize)]
-#[serde(untagged)]
-enum Tag {
-    Simple(String),
-    Object { tag: Option<String> },
+struct FileRaw {
+    #[serde(default)]
+    hash: Option<String>,
 }
 
+/// 목록 항목은 문자열이거나 `{ "artist": "...", "url": "..." }` 같은 객체입니다.
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
-enum Artist {
+enum Named {
     Simple(String),
-    Object { artist: Option<String> },
+    Object(serde_json::Map<String, serde_json::Value>),
 }
 
-fn merge_tags(a: Vec<Tag>, b: Vec<Tag>) -> Vec<String> {
-    let mut out = Vec::new();
-    for tag in a.into_iter().chain(b.into_iter()) {
-        let value = match tag {
-            Tag::Simple(s) => Some(s),
-            Tag::Object { tag } => tag,
+impl Named {
+    fn name(&self, key: &str) -> Option<&str> {
+        match self {
+            Named::Simple(s) => Some(s),
+            Named::Object(map) => map.get(key).and_then(|v| v.as_str()),
+        }
+    }
+
+    /// `"female": "1"`, `1`, `true` 모두 참으로 봅니다.
+    fn flag(&self, key: &str) -> bool {
+        let Named::Object(map) = self else {
+            return false;
         };
+        match map.get(key) {
+            Some(serde_json::Value::String(s)) => s == "1",
+            Some(serde_json::Value::Number(n)) => n.as_i64() == Some(1),
+            Some(serde_json::Value::Bool(b)) => *b,
+            _ => false,
+        }
+    }
+}
 
-        if let Some(v) = value {
-            let trimmed = v.trim();
-            if !trimmed.is_empty() && !out.contains(&trimmed.to_string()) {
-                out.push(trimmed.to_string());
+/// Hitomi는 빈 목록을 `null`로 보내기도 합니다.
+fn nullable_list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(Option::<Vec<T>>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+fn merge_tags(a: Vec<Named>, b: Vec<Named>) -> Vec<GalleryTag> {
+    let mut out: Vec<GalleryTag> = Vec::new();
+    for tag in a.into_iter().chain(b) {
+        let Some(name) = tag.name("tag") else {
+            continue;
+        };
+
+        let parsed = if tag.flag("female") {
+            GalleryTag {
+                kind: TagKind::Female,
+                name: name.trim().to_string(),
             }
+        } else if tag.flag("male") {
+            GalleryTag {
+                kind: TagKind::Male,
+                name: name.trim().to_string(),
+            }
+        } else {
+            GalleryTag::parse(name)
+        };
+
+        if !parsed.name.is_empty() && !out.contains(&parsed) {
+            out.push(parsed);
         }
     }
     out
 }
 
-fn merge_artists(a: Vec<Artist>, b: Vec<Artist>) -> Vec<String> {
-    let mut out = Vec::new();
-    for artist in a.into_iter().chain(b.into_iter()) {
-        let value = match artist {
-            Artist::Simple(s) => Some(s),
-            Artist::Object { artist } => artist,
-        };
-
-        if let Some(v) = value {
+fn merge_names<const N: usize>(lists: [Vec<Named>; N], key: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for item in lists.into_iter().flatten() {
+        if let Some(v) = item.name(key) {
             let trimmed = v.trim();
-            if !trimmed.is_empty() && !out.contains(&trimmed.to_string()) {
+            if !trimmed.is_empty() && !out.iter().any(|existing| existing == trimmed) {
                 out.push(trimmed.to_string());
             }
         }
@@ -201,3 +563,89 @@ fn normalize_js_payload(raw: String) -> String {
     let trimmed = without_prefix.trim();
     trimmed.trim_end_matches(';').to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = r#"var galleryinfo = {"id":"123","title":"Sample","japanese_title":"サンプル","type":"doujinshi","language":"korean","language_localname":"한국어","date":"2023-04-05 12:34:00-05","artists":[{"artist":"someone","url":"/artist/someone-all.html"}],"groups":[{"group":"circle"}],"parodys":[{"parody":"original"}],"characters":null,"tags":[{"tag":"big breasts","female":"1","male":""},{"tag":"glasses","female":"","male":1},{"tag":"full color"}],"files":[{"hash":"0123456789abc","name":"01.jpg"},{"hash":"ffff","name":"02.jpg"}]};"#;
+
+    fn parse_fixture() -> GalleryInfo {
+        parse_gallery_payload("123", FIXTURE.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_from_raw_extracts_all_fields() {
+        let info = parse_fixture();
+        assert_eq!(info.title, "Sample");
+        assert_eq!(info.japanese_title.as_deref(), Some("サンプル"));
+        assert_eq!(info.gallery_type.as_deref(), Some("doujinshi"));
+        assert_eq!(info.artists, vec!["someone"]);
+        assert_eq!(info.groups, vec!["circle"]);
+        assert_eq!(info.series, vec!["original"]);
+        assert!(info.characters.is_empty());
+        assert_eq!(info.language.as_deref(), Some("한국어"));
+        assert_eq!(info.language_code.as_deref(), Some("korean"));
+        assert_eq!(info.page_count, 2);
+        assert_eq!(info.date.as_deref(), Some("2023-04-05"));
+    }
+
+    #[test]
+    fn test_tags_are_typed() {
+        let info = parse_fixture();
+        let rendered: Vec<String> = info.tags.iter().map(|t| t.to_string()).collect();
+        assert_eq!(
+            rendered,
+            vec!["female:big breasts", "male:glasses", "full color"]
+        );
+        assert_eq!(info.tags_of(TagKind::Female).count(), 1);
+    }
+
+    #[test]
+    fn test_cover_url_uses_hash_directories() {
+        let info = parse_fixture();
+        assert_eq!(
+            info.cover_url().as_deref(),
+            Some("https://tn.gold-usergeneratedcontent.net/webpbigtn/c/ab/0123456789abc.webp")
+        );
+    }
+
+    #[test]
+    fn test_invalid_payload_is_parse_error() {
+        let err = parse_gallery_payload("1", "var galleryinfo = <html>".to_string()).unwrap_err();
+        assert!(matches!(err, GalleryError::Parse(_)));
+    }
+
+    #[test]
+    fn test_parse_tag_prefixes() {
+        assert_eq!(GalleryTag::parse("female:stockings").kind, TagKind::Female);
+        assert_eq!(GalleryTag::parse("male:shota").name, "shota");
+        assert_eq!(GalleryTag::parse("full color").kind, TagKind::Plain);
+    }
+
+    #[tokio::test]
+    async fn test_search_uses_hitomi_nozomi_layout_and_caches_language_index() {
+        use crate::test_support::{Routes, mock_server};
+
+        let nozomi =
+            |ids: &[u32]| -> Vec<u8> { ids.iter().flat_map(|id| id.to_be_bytes()).collect() };
+        let routes: Routes = Arc::default();
+        {
+            let mut routes = routes.lock().unwrap();
+            // https://ltn.gold-usergeneratedcontent.net/n/tag/full%20color-all.nozomi 와 같은 배치입니다.
+            routes.insert(
+                "/n/tag/full%20color-all.nozomi".to_string(),
+                nozomi(&[5, 4, 3, 2]),
+            );
+            routes.insert("/n/index-korean.nozomi".to_string(), nozomi(&[4, 2, 1]));
+        }
+        let client = GalleryClient::new().with_data_base_url(&mock_server(Arc::clone(&routes)));
+        let terms = search::parse_query("full_color language:korean").unwrap();
+
+        assert_eq!(client.search(&terms).await.unwrap(), vec![4, 2]);
+
+        // 언어 인덱스는 캐시에서 다시 씁니다.
+        routes.lock().unwrap().remove("/n/index-korean.nozomi");
+        assert_eq!(client.search(&terms).await.unwrap(), vec![4, 2]);
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+use std::fmt;
+
+/// nozomi 인덱스 종류. 각 종류마다 `n/<area>/<값>-all.nozomi` 파일이 있습니다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchArea {
+    Tag,
+    Artist,
+    Group,
+    Series,
+    Character,
+    Type,
+    Language,
+}
+
+impl SearchArea {
+    fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            "tag" => Some(SearchArea::Tag),
+            "artist" => Some(SearchArea::Artist),
+            "group" => Some(SearchArea::Group),
+            "series" | "parody" => Some(SearchArea::Series),
+            "character" => Some(SearchArea::Character),
+            "type" => Some(SearchArea::Type),
+            "language" | "lang" => Some(SearchArea::Language),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SearchArea::Tag => "tag",
+            SearchArea::Artist => "artist",
+            SearchArea::Group => "group",
+            SearchArea::Series => "series",
+            SearchArea::Character => "character",
+            SearchArea::Type => "type",
+            SearchArea::Language => "language",
+        }
+    }
+}
+
+/// 검색어 하나. Hitomi 관례대로 공백은 `_`로 입력받습니다. (예: `female:big_breasts`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchTerm {
+    pub area: SearchArea,
+    pub value: String,
+}
+
+impl SearchTerm {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim().to_lowercase();
+        if raw.is_empty() {
+            return None;
+        }
+
+        let (area, value) = match raw.split_once(':') {
+            // female:/male: 태그는 접두사까지가 태그 이름입니다.
+            Some(("female" | "male", _)) => (SearchArea::Tag, raw.as_str()),
+            Some((prefix, value)) => (SearchArea::from_prefix(prefix)?, value),
+            None => (SearchArea::Tag, raw.as_str()),
+        };
+
+        let value = value.replace('_', " ");
+        let value = value.trim();
+        if value.is_empty() || value.contains('/') {
+            return None;
+        }
+
+        Some(Self {
+            area,
+            value: value.to_string(),
+        })
+    }
+
+    /// 인덱스 서버 기준 상대 경로. hitomi.la의 `compressed_nozomi_prefix`(`n`) 아래에 있습니다.
+    pub fn nozomi_path(&self) -> String {
+        match self.area {
+            SearchArea::Language => format!("n/index-{}.nozomi", self.value),
+            area => format!("n/{}/{}-all.nozomi", area.label(), self.value),
+        }
+    }
+}
+
+impl fmt::Display for SearchTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.value.replace(' ', "_");
+        match self.area {
+            SearchArea::Tag => write!(f, "{}", value),
+            area => write!(f, "{}:{}", area.label(), value),
+        }
+    }
+}
+
+/// 공백으로 구분된 검색어를 해석합니다. 하나라도 잘못되면 `Err`로 그 검색어를 돌려줍니다.
+pub fn parse_query(query: &str) -> Result<Vec<SearchTerm>, String> {
+    let mut terms = Vec::new();
+    for raw in query.split_whitespace() {
+        let term = SearchTerm::parse(raw).ok_or_else(|| raw.to_string())?;
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+    Ok(terms)
+}
+
+/// nozomi 파일은 빅엔디언 int32 갤러리 ID의 연속입니다. (최신순)
+pub fn decode_nozomi(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+/// 모든 목록에 들어 있는 ID를 첫 목록의 순서대로 돌려줍니다.
+pub fn intersect<L: AsRef<[u32]>>(lists: &[L]) -> Vec<u32> {
+    let Some((first, rest)) = lists.split_first() else {
+        return Vec::new();
+    };
+    let others: Vec<HashSet<u32>> = rest
+        .iter()
+        .map(|list| list.as_ref().iter().copied().collect())
+        .collect();
+    first
+        .as_ref()
+        .iter()
+        .copied()
+        .filter(|id| others.iter().all(|set| set.contains(id)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_terms_and_paths() {
+        let terms =
+            parse_query("female:big_breasts artist:foo_bar language:korean full_color").unwrap();
+        let paths: Vec<_> = terms.iter().map(SearchTerm::nozomi_path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "n/tag/female:big breasts-all.nozomi",
+                "n/artist/foo bar-all.nozomi",
+                "n/index-korean.nozomi",
+                "n/tag/full color-all.nozomi",
+            ]
+        );
+        assert_eq!(terms[1].to_string(), "artist:foo_bar");
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_prefix_and_paths() {
+        assert_eq!(parse_query("foo:bar").unwrap_err(), "foo:bar");
+        assert!(SearchTerm::parse("tag:../x").is_none());
+        assert!(SearchTerm::parse("artist:").is_none());
+    }
+
+    #[test]
+    fn test_decode_nozomi_big_endian() {
+        let bytes = [0, 0, 0x30, 0x39, 0, 0x01, 0xe2, 0x40, 0xff];
+        assert_eq!(decode_nozomi(&bytes), vec![12345, 123456]);
+    }
+
+    #[test]
+    fn test_intersect_keeps_first_order() {
+        let lists = vec![vec![5, 4, 3, 2, 1], vec![1, 3, 5], vec![3, 5, 9]];
+        assert_eq!(intersect(&lists), vec![5, 3]);
+    }
+}
@@ -0,0 +1,81 @@
+/// 문서를 `size`글자 내외의 조각으로 나눕니다. 조각끼리는 `overlap`글자만큼 겹치며,
+/// 가능하면 문단/줄/공백 경계에서 자릅니다.
+pub(crate) fn split_text(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let size = size.max(1);
+    let overlap = overlap.min(size / 2);
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let hard_end = (start + size).min(chars.len());
+        let end = if hard_end == chars.len() {
+            hard_end
+        } else {
+            find_break(&chars, start + size / 2, hard_end).unwrap_or(hard_end)
+        };
+
+        let chunk: String = chars[start..end].iter().collect();
+        let chunk = chunk.trim();
+        if !chunk.is_empty() {
+            chunks.push(chunk.to_string());
+        }
+
+        if end == chars.len() {
+            break;
+        }
+        start = end.saturating_sub(overlap).max(start + 1);
+    }
+
+    chunks
+}
+
+/// `[min, max)` 구간에서 가장 뒤쪽의 자연스러운 경계를 찾습니다.
+fn find_break(chars: &[char], min: usize, max: usize) -> Option<usize> {
+    let window = &chars[min..max];
+    let paragraph = window
+        .windows(2)
+        .rposition(|pair| pair[0] == '\n' && pair[1] == '\n')
+        .map(|pos| min + pos + 2);
+    let line = || {
+        window
+            .iter()
+            .rposition(|ch| *ch == '\n')
+            .map(|pos| min + pos + 1)
+    };
+    let space = || {
+        window
+            .iter()
+            .rposition(|ch| ch.is_whitespace())
+            .map(|pos| min + pos + 1)
+    };
+
+    paragraph.or_else(line).or_else(space)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_text_is_single_chunk() {
+        assert_eq!(split_text("  짧은 문서  ", 100, 10), vec!["짧은 문서"]);
+    }
+
+    #[test]
+    fn test_chunks_prefer_paragraph_breaks_and_overlap() {
+        let text = format!("{}\n\n{}", "가".repeat(60), "나".repeat(60));
+        let chunks = split_text(&text, 100, 10);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], "가".repeat(60));
+        assert!(chunks[1].ends_with(&"나".repeat(60)));
+    }
+
+    #[test]
+    fn test_chunks_cover_text_without_breaks() {
+        let text = "a".repeat(250);
+        let chunks = split_text(&text, 100, 20);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 100));
+        assert_eq!(chunks.len(), 3);
+    }
+}
@@ -0,0 +1,380 @@
+mod chunk;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+use crate::llm::LlmProvider;
+use crate::util::now_secs;
+
+pub(crate) use chunk::split_text;
+
+const CHUNK_SIZE: usize = 1000;
+const CHUNK_OVERLAP: usize = 150;
+const TOP_K: usize = 4;
+const MIN_SCORE: f32 = 0.3;
+
+const ANSWER_SYSTEM_PROMPT: &str = "당신은 블루아카이브의 프라나 말투로 답하는 비서입니다. \
+사용자 호칭은 \"선생님\"이며 항상 존댓말을 씁니다. \
+주어진 자료만 근거로 짧게 답하고, 근거로 쓴 자료 번호를 [1]처럼 문장 끝에 표시하십시오. \
+자료에 답이 없으면 \"확인 필요\"라고 답하십시오.";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct KnowledgeChunk {
+    pub text: String,
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct KnowledgeEntry {
+    pub id: u32,
+    pub title: String,
+    pub added_by: i64,
+    pub added_at: i64,
+    pub chunks: Vec<KnowledgeChunk>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct KnowledgeIndex {
+    pub version: u32,
+    pub embedding_model: String,
+    pub next_id: u32,
+    pub entries: Vec<KnowledgeEntry>,
+}
+
+impl KnowledgeIndex {
+    fn empty(embedding_model: &str) -> Self {
+        Self {
+            version: 1,
+            embedding_model: embedding_model.to_string(),
+            next_id: 1,
+            entries: Vec::new(),
+        }
+    }
+
+    /// 코사인 유사도가 높은 순으로 상위 `k`개 조각을 돌려줍니다.
+    pub(crate) fn search(&self, query: &[f32], k: usize) -> Vec<SearchHit<'_>> {
+        let mut hits: Vec<SearchHit<'_>> = self
+            .entries
+            .iter()
+            .flat_map(|entry| {
+                entry.chunks.iter().map(move |chunk| SearchHit {
+                    entry,
+                    text: &chunk.text,
+                    score: cosine_similarity(query, &chunk.embedding),
+                })
+            })
+            .filter(|hit| hit.score >= MIN_SCORE)
+            .collect();
+
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(k);
+        hits
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SearchHit<'a> {
+    pub entry: &'a KnowledgeEntry,
+    pub text: &'a str,
+    pub score: f32,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Citation {
+    pub number: usize,
+    pub entry_id: u32,
+    pub title: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct KnowledgeAnswer {
+    pub text: String,
+    pub citations: Vec<Citation>,
+}
+
+/// 채팅별 지식 베이스. 채팅마다 JSON 인덱스 파일 하나를 씁니다.
+#[derive(Clone)]
+pub(crate) struct KnowledgeBase {
+    dir: PathBuf,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl KnowledgeBase {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub(crate) fn from_env() -> Self {
+        Self::new(resolve_knowledge_dir())
+    }
+
+    pub(crate) async fn load(&self, chat_id: i64) -> Result<Option<KnowledgeIndex>> {
+        load_index(&self.index_path(chat_id)).await
+    }
+
+    /// 문서를 잘라 임베딩한 뒤 채팅 인덱스에 추가하고, 새 항목을 돌려줍니다.
+    pub(crate) async fn add(
+        &self,
+        chat_id: i64,
+        provider: &dyn LlmProvider,
+        title: &str,
+        text: &str,
+        added_by: i64,
+    ) -> Result<KnowledgeEntry> {
+        let pieces = split_text(text, CHUNK_SIZE, CHUNK_OVERLAP);
+        if pieces.is_empty() {
+            return Err(anyhow!("추가할 내용이 비어 있습니다"));
+        }
+
+        let embeddings = provider.embed(&pieces).await?;
+        if embeddings.len() != pieces.len() {
+            return Err(anyhow!("임베딩 개수가 조각 개수와 다릅니다"));
+        }
+
+        let _guard = self.write_lock.lock().await;
+        let path = self.index_path(chat_id);
+        let mut index = load_index(&path)
+            .await?
+            .unwrap_or_else(|| KnowledgeIndex::empty(provider.embedding_model()));
+        ensure_model(&index, provider)?;
+        if index.entries.is_empty() {
+            index.embedding_model = provider.embedding_model().to_string();
+        }
+
+        let entry = KnowledgeEntry {
+            id: index.next_id,
+            title: title.to_string(),
+            added_by,
+            added_at: now_secs(),
+            chunks: pieces
+                .into_iter()
+                .zip(embeddings)
+                .map(|(text, embedding)| KnowledgeChunk { text, embedding })
+                .collect(),
+        };
+        index.next_id += 1;
+        index.entries.push(entry.clone());
+
+        persist_index(&path, &index).await?;
+        Ok(entry)
+    }
+
+    pub(crate) async fn remove(
+        &self,
+        chat_id: i64,
+        entry_id: u32,
+    ) -> Result<Option<KnowledgeEntry>> {
+        let _guard = self.write_lock.lock().await;
+        let path = self.index_path(chat_id);
+        let Some(mut index) = load_index(&path).await? else {
+            return Ok(None);
+        };
+
+        let Some(pos) = index.entries.iter().position(|e| e.id == entry_id) else {
+            return Ok(None);
+        };
+        let removed = index.entries.remove(pos);
+        persist_index(&path, &index).await?;
+        Ok(Some(removed))
+    }
+
+    /// 채팅 인덱스에서 질문과 가까운 조각을 찾아 출처 번호가 붙은 답변을 만듭니다.
+    /// 관련 자료가 없으면 `None`을 돌려줍니다.
+    pub(crate) async fn ask(
+        &self,
+        chat_id: i64,
+        provider: &dyn LlmProvider,
+        question: &str,
+    ) -> Result<Option<KnowledgeAnswer>> {
+        let Some(index) = self.load(chat_id).await? else {
+            return Ok(None);
+        };
+        if index.entries.is_empty() {
+            return Ok(None);
+        }
+        ensure_model(&index, provider)?;
+
+        let query = provider
+            .embed(&[question.to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .context("질문 임베딩이 비어 있습니다")?;
+
+        let hits = index.search(&query, TOP_K);
+        if hits.is_empty() {
+            return Ok(None);
+        }
+
+        let mut citations: Vec<Citation> = Vec::new();
+        let mut context = String::new();
+        for hit in &hits {
+            let number = match citations.iter().find(|c| c.entry_id == hit.entry.id) {
+                Some(citation) => citation.number,
+                None => {
+                    let number = citations.len() + 1;
+                    citations.push(Citation {
+                        number,
+                        entry_id: hit.entry.id,
+                        title: hit.entry.title.clone(),
+                    });
+                    number
+                }
+            };
+            context.push_str(&format!("[{}] {}\n\n", number, hit.text));
+        }
+
+        let prompt = format!("질문:\n{}\n\n자료:\n{}", question, context.trim_end());
+        let text = provider.generate(ANSWER_SYSTEM_PROMPT, &prompt).await?;
+
+        Ok(Some(KnowledgeAnswer {
+            text: text.trim().to_string(),
+            citations,
+        }))
+    }
+
+    fn index_path(&self, chat_id: i64) -> PathBuf {
+        self.dir.join(format!("{}.json", chat_id))
+    }
+}
+
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+
+    let (mut dot, mut a2, mut b2) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        a2 += x * x;
+        b2 += y * y;
+    }
+
+    let denom = a2.sqrt() * b2.sqrt();
+    if denom == 0.0 { 0.0 } else { dot / denom }
+}
+
+fn ensure_model(index: &KnowledgeIndex, provider: &dyn LlmProvider) -> Result<()> {
+    if !index.entries.is_empty() && index.embedding_model != provider.embedding_model() {
+        return Err(anyhow!(
+            "임베딩 모델이 다릅니다 (인덱스: {}, 현재: {}). 항목을 삭제 후 다시 추가해 주십시오.",
+            index.embedding_model,
+            provider.embedding_model()
+        ));
+    }
+    Ok(())
+}
+
+async fn load_index(path: &Path) -> Result<Option<KnowledgeIndex>> {
+    let raw = match fs::read_to_string(path).await {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let index: KnowledgeIndex =
+        serde_json::from_str(&raw).context("지식 베이스 인덱스 형식이 올바르지 않습니다")?;
+    if index.version != 1 {
+        return Err(anyhow!(
+            "지원하지 않는 인덱스 버전입니다: {}",
+            index.version
+        ));
+    }
+    Ok(Some(index))
+}
+
+async fn persist_index(path: &Path, index: &KnowledgeIndex) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(path, serde_json::to_string(index)?).await?;
+    Ok(())
+}
+
+fn resolve_knowledge_dir() -> PathBuf {
+    let raw = std::env::var("PLANABOT_KB_DIR").unwrap_or_else(|_| ".planabot/kb".to_string());
+    let path = PathBuf::from(raw);
+    if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::BoxFuture;
+
+    /// 글자마다 고정 축에 값을 더하는 장난감 임베딩.
+    struct FakeProvider;
+
+    impl LlmProvider for FakeProvider {
+        fn embedding_model(&self) -> &str {
+            "fake"
+        }
+
+        fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+            Box::pin(async move {
+                Ok(texts
+                    .iter()
+                    .map(|text| {
+                        let mut v = vec![0.0; 3];
+                        for ch in text.chars() {
+                            match ch {
+                                '사' => v[0] += 1.0,
+                                '과' => v[1] += 1.0,
+                                _ => v[2] += 0.1,
+                            }
+                        }
+                        v
+                    })
+                    .collect())
+            })
+        }
+
+        fn generate<'a>(
+            &'a self,
+            _system: &'a str,
+            prompt: &'a str,
+        ) -> BoxFuture<'a, Result<String>> {
+            Box::pin(async move { Ok(prompt.to_string()) })
+        }
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 2.0]), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_add_ask_and_remove() {
+        let dir = std::env::temp_dir().join(format!("planabot-kb-test-{}", now_secs()));
+        let kb = KnowledgeBase::new(dir.clone());
+
+        let apples = kb.add(1, &FakeProvider, "과일", "사사사", 7).await.unwrap();
+        let other = kb.add(1, &FakeProvider, "기타", "과과과", 7).await.unwrap();
+        assert_eq!((apples.id, other.id), (1, 2));
+
+        let answer = kb.ask(1, &FakeProvider, "사").await.unwrap().unwrap();
+        assert_eq!(answer.citations[0].entry_id, 1);
+        assert!(answer.text.contains("[1] 사사사"));
+
+        assert!(kb.ask(2, &FakeProvider, "사").await.unwrap().is_none());
+        assert!(kb.remove(1, 1).await.unwrap().is_some());
+        assert!(kb.remove(1, 1).await.unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
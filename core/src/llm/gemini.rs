@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::util::BoxFuture;
+
+use super::LlmProvider;
+
+const API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+const EMBED_BATCH: usize = 100;
+
+#[derive(Clone)]
+pub(crate) struct GeminiProvider {
+    client: Client,
+    api_key: String,
+    chat_model: String,
+    embedding_model: String,
+}
+
+impl GeminiProvider {
+    pub(crate) fn new(api_key: String, chat_model: String, embedding_model: String) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()
+            .expect("reqwest client should build");
+
+        Self {
+            client,
+            api_key,
+            chat_model,
+            embedding_model,
+        }
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let model = format!("models/{}", self.embedding_model);
+        let requests = texts
+            .iter()
+            .map(|text| json!({ "model": model, "content": { "parts": [{ "text": text }] } }))
+            .collect::<Vec<_>>();
+
+        let response: EmbedResponse = self
+            .client
+            .post(format!("{API_BASE}/{model}:batchEmbedContents"))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&json!({ "requests": requests }))
+            .send()
+            .await
+            .context("Gemini 임베딩 요청 실패")?
+            .error_for_status()
+            .context("Gemini 임베딩 응답 오류")?
+            .json()
+            .await
+            .context("Gemini 임베딩 응답 파싱 실패")?;
+
+        if response.embeddings.len() != texts.len() {
+            return Err(anyhow!(
+                "임베딩 개수가 맞지 않습니다: texts={} embeddings={}",
+                texts.len(),
+                response.embeddings.len()
+            ));
+        }
+
+        Ok(response.embeddings.into_iter().map(|e| e.values).collect())
+    }
+
+    async fn generate_text(&self, system: &str, prompt: &str) -> Result<String> {
+        let body = json!({
+            "systemInstruction": { "parts": [{ "text": system }] },
+            "contents": [{ "role": "user", "parts": [{ "text": prompt }] }],
+        });
+
+        let response: GenerateResponse = self
+            .client
+            .post(format!(
+                "{API_BASE}/models/{}:generateContent",
+                self.chat_model
+            ))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .context("Gemini 생성 요청 실패")?
+            .error_for_status()
+            .context("Gemini 생성 응답 오류")?
+            .json()
+            .await
+            .context("Gemini 생성 응답 파싱 실패")?;
+
+        let text = response
+            .candidates
+            .into_iter()
+            .next()
+            .map(|candidate| {
+                candidate
+                    .content
+                    .parts
+                    .into_iter()
+                    .filter_map(|part| part.text)
+                    .collect::<String>()
+            })
+            .unwrap_or_default();
+
+        if text.trim().is_empty() {
+            return Err(anyhow!("Gemini 응답이 비어 있습니다"));
+        }
+        Ok(text)
+    }
+}
+
+impl LlmProvider for GeminiProvider {
+    fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        Box::pin(async move {
+            let mut out = Vec::with_capacity(texts.len());
+            for batch in texts.chunks(EMBED_BATCH) {
+                out.extend(self.embed_batch(batch).await?);
+            }
+            Ok(out)
+        })
+    }
+
+    fn generate<'a>(&'a self, system: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<String>> {
+        Box::pin(self.generate_text(system, prompt))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbedResponse {
+    #[serde(default)]
+    embeddings: Vec<Embedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Embedding {
+    #[serde(default)]
+    values: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GenerateResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Candidate {
+    content: Content,
+}
+
+#[derive(Debug, Deserialize)]
+struct Content {
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Part {
+    #[serde(default)]
+    text: Option<String>,
+}
@@ -0,0 +1,33 @@
+mod gemini;
+
+use anyhow::Result;
+
+use crate::util::BoxFuture;
+
+pub(crate) use gemini::GeminiProvider;
+
+/// 봇이 직접 호출하는 LLM 백엔드(임베딩/텍스트 생성).
+pub(crate) trait LlmProvider: Send + Sync {
+    /// 임베딩 모델 이름. 인덱스에 함께 저장해 모델이 바뀌었는지 확인하는 데 씁니다.
+    fn embedding_model(&self) -> &str;
+
+    /// 입력 순서대로 임베딩 벡터를 돌려줍니다.
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>>;
+
+    fn generate<'a>(&'a self, system: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<String>>;
+}
+
+/// `GOOGLE_API_KEY`(또는 `GEMINI_API_KEY`)가 있으면 Gemini 제공자를 만듭니다.
+pub(crate) fn provider_from_env() -> Option<GeminiProvider> {
+    let api_key = std::env::var("GOOGLE_API_KEY")
+        .or_else(|_| std::env::var("GEMINI_API_KEY"))
+        .ok()
+        .filter(|key| !key.trim().is_empty())?;
+
+    let chat_model = std::env::var("PLANABRAIN_GEMINI_MODEL")
+        .unwrap_or_else(|_| "gemini-3-flash-preview".to_string());
+    let embedding_model = std::env::var("PLANABRAIN_GEMINI_EMBEDDING_MODEL")
+        .unwrap_or_else(|_| "gemini-embedding-001".to_string());
+
+    Some(GeminiProvider::new(api_key, chat_model, embedding_model))
+}
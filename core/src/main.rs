@@ -1,8 +1,19 @@
 mod bot;
 mod config;
+mod favorites;
+mod follow;
+mod history;
 mod hitomi;
+mod knowledge;
+mod llm;
+mod mirrors;
 mod planabrain;
+mod settings;
+mod sources;
+#[cfg(test)]
+mod test_support;
 mod urlchanger;
+mod util;
 
 use anyhow::Result;
 use bot::AppState;
@@ -23,8 +34,20 @@ async fn main() -> Result<()> {
     let bot_username = me.user.username.clone().unwrap_or_default();
     info!("봇 초기화 완료: @{}", bot_username);
 
-    let state = AppState::new(bot_username, GalleryClient::new());
+    let gallery_client = GalleryClient::new();
+    let state = AppState::new(
+        bot_username,
+        me.user.id,
+        gallery_client.clone(),
+        config.owner_ids.clone(),
+        config.mirrors.clone(),
+        config.link_rules.clone(),
+    );
+    state.mirrors.spawn_health_checks();
 
     bot::announce_startup(&bot, &state).await;
-    bot::run(bot, state).await
+    bot::spawn_follow_poller(bot.clone(), state.clone());
+    let result = bot::run(bot, state).await;
+    gallery_client.persist_cache().await;
+    result
 }
@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+use log::{info, warn};
+use reqwest::Client;
+use url::Url;
+
+use crate::util::USER_AGENT;
+
+/// 기본 미러 목록. `PLANABOT_MIRRORS`가 없을 때 씁니다.
+const DEFAULT_MIRRORS: &str =
+    "Hitomi.la=https://hitomi.la/galleries/{id}.html;K-Hentai=https://k-hentai.org/r/{id}";
+
+/// `이름=https://example.com/{id}` 형식의 링크 템플릿.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MirrorTemplate {
+    pub name: String,
+    template: String,
+}
+
+impl MirrorTemplate {
+    /// 시작할 때 한 번 검사하므로, 통과한 템플릿은 어떤 숫자 ID로도 올바른 주소가 됩니다.
+    pub(crate) fn parse(raw: &str) -> Result<Self> {
+        let (name, template) = raw
+            .split_once('=')
+            .ok_or_else(|| anyhow!("`이름=주소` 형식이 아닙니다: {}", raw))?;
+        let name = name.trim();
+        let template = template.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            bail!(
+                "미러 이름은 비어 있거나 공백을 포함할 수 없습니다: {:?}",
+                name
+            );
+        }
+        if !template.contains("{id}") {
+            bail!("{} 템플릿에 {{id}}가 없습니다: {}", name, template);
+        }
+        let url = Url::parse(&template.replace("{id}", "1")).map_err(|err| {
+            anyhow!(
+                "{} 템플릿이 올바른 주소가 아닙니다 ({}): {}",
+                name,
+                err,
+                template
+            )
+        })?;
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            bail!("{} 템플릿은 http(s) 주소여야 합니다: {}", name, template);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            template: template.to_string(),
+        })
+    }
+
+    pub(crate) fn url(&self, gallery_id: &str) -> Option<Url> {
+        Url::parse(&self.template.replace("{id}", gallery_id)).ok()
+    }
+
+    /// 상태 확인에 쓰는 주소. 템플릿의 호스트 루트입니다.
+    fn origin(&self) -> Option<Url> {
+        let mut url = self.url("1")?;
+        url.set_path("/");
+        url.set_query(None);
+        url.set_fragment(None);
+        Some(url)
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+}
+
+/// `;` 또는 줄바꿈으로 구분된 템플릿 목록을 읽습니다. 이름이 겹치면 오류입니다.
+pub(crate) fn parse_templates(raw: &str) -> Result<Vec<MirrorTemplate>> {
+    let mut templates: Vec<MirrorTemplate> = Vec::new();
+    for item in raw
+        .split([';', '\n'])
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+    {
+        let template = MirrorTemplate::parse(item)?;
+        if templates
+            .iter()
+            .any(|existing| existing.matches(&template.name))
+        {
+            bail!("미러 이름이 중복되었습니다: {}", template.name);
+        }
+        templates.push(template);
+    }
+    Ok(templates)
+}
+
+/// `PLANABOT_MIRRORS`를 읽어 검사합니다. 잘못된 설정이면 시작하지 않습니다.
+pub(crate) fn templates_from_env() -> Result<Vec<MirrorTemplate>> {
+    let raw = std::env::var("PLANABOT_MIRRORS").unwrap_or_else(|_| DEFAULT_MIRRORS.to_string());
+    parse_templates(&raw).map_err(|err| anyhow!("PLANABOT_MIRRORS 설정 오류: {}", err))
+}
+
+/// 키보드에 넣을 링크 하나.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MirrorLink {
+    pub name: String,
+    pub url: Url,
+}
+
+/// 설정된 미러와 상태 확인 결과.
+#[derive(Clone)]
+pub(crate) struct Mirrors {
+    templates: Arc<Vec<MirrorTemplate>>,
+    down: Arc<RwLock<HashSet<String>>>,
+}
+
+impl Mirrors {
+    pub(crate) fn new(templates: Vec<MirrorTemplate>) -> Self {
+        Self {
+            templates: Arc::new(templates),
+            down: Arc::new(RwLock::new(HashSet::new())),
+        }
+    }
+
+    pub(crate) fn templates(&self) -> &[MirrorTemplate] {
+        &self.templates
+    }
+
+    pub(crate) fn is_up(&self, name: &str) -> bool {
+        self.down
+            .read()
+            .map(|down| !down.contains(name))
+            .unwrap_or(true)
+    }
+
+    /// 이름을 설정된 표기로 바꿉니다. 없는 이름이 있으면 그 이름을 `Err`로 돌려줍니다.
+    pub(crate) fn resolve_names<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Vec<String>, String> {
+        let mut resolved = Vec::new();
+        for name in names {
+            let template = self
+                .templates
+                .iter()
+                .find(|template| template.matches(name))
+                .ok_or_else(|| name.to_string())?;
+            if !resolved.contains(&template.name) {
+                resolved.push(template.name.clone());
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// 살아 있는 미러 링크. `selection`이 있으면 그 이름만 그 순서대로 씁니다.
+    pub(crate) fn links(&self, selection: Option<&[String]>, gallery_id: &str) -> Vec<MirrorLink> {
+        let chosen: Vec<&MirrorTemplate> = match selection {
+            Some(names) => names
+                .iter()
+                .filter_map(|name| self.templates.iter().find(|t| t.matches(name)))
+                .collect(),
+            None => self.templates.iter().collect(),
+        };
+        chosen
+            .into_iter()
+            .filter(|template| self.is_up(&template.name))
+            .filter_map(|template| {
+                Some(MirrorLink {
+                    name: template.name.clone(),
+                    url: template.url(gallery_id)?,
+                })
+            })
+            .collect()
+    }
+
+    /// 각 미러의 호스트에 요청해 연결 실패나 5xx면 내려간 것으로 표시합니다.
+    pub(crate) async fn check_health(&self, client: &Client) {
+        for template in self.templates.iter() {
+            let Some(origin) = template.origin() else {
+                continue;
+            };
+            let up = match client.get(origin).send().await {
+                Ok(response) => !response.status().is_server_error(),
+                Err(err) => {
+                    warn!("미러 상태 확인 실패 ({}): {}", template.name, err);
+                    false
+                }
+            };
+            let changed = match self.down.write() {
+                Ok(mut down) if up => down.remove(&template.name),
+                Ok(mut down) => down.insert(template.name.clone()),
+                Err(_) => false,
+            };
+            if changed {
+                info!(
+                    "미러 상태 변경: {} → {}",
+                    template.name,
+                    if up { "정상" } else { "중단" }
+                );
+            }
+        }
+    }
+
+    /// `PLANABOT_MIRROR_HEALTH_INTERVAL_SECS`(기본 600초, `0`이면 끔)마다 상태를 확인합니다.
+    pub(crate) fn spawn_health_checks(&self) {
+        let interval = std::env::var("PLANABOT_MIRROR_HEALTH_INTERVAL_SECS")
+            .ok()
+            .and_then(|raw| raw.trim().parse::<u64>().ok())
+            .unwrap_or(600);
+        if interval == 0 {
+            return;
+        }
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .user_agent(USER_AGENT)
+            .build()
+            .expect("reqwest client should build");
+        let mirrors = self.clone();
+        tokio::spawn(async move {
+            loop {
+                mirrors.check_health(&client).await;
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Routes, Statuses, mock_server_with_statuses};
+
+    #[test]
+    fn test_parse_templates_validates() {
+        let templates = parse_templates(DEFAULT_MIRRORS).unwrap();
+        assert_eq!(templates.len(), 2);
+        assert_eq!(
+            templates[1].url("123").unwrap().as_str(),
+            "https://k-hentai.org/r/123"
+        );
+
+        assert!(parse_templates("A=https://a.example/").is_err());
+        assert!(parse_templates("A=ftp://a.example/{id}").is_err());
+        assert!(parse_templates("https://a.example/{id}").is_err());
+        assert!(parse_templates("A=https://a.example/{id};a=https://b.example/{id}").is_err());
+    }
+
+    #[test]
+    fn test_links_follow_selection_and_skip_down_mirrors() {
+        let mirrors = Mirrors::new(parse_templates(DEFAULT_MIRRORS).unwrap());
+        let names = |links: Vec<MirrorLink>| links.into_iter().map(|l| l.name).collect::<Vec<_>>();
+
+        assert_eq!(
+            names(mirrors.links(None, "1")),
+            vec!["Hitomi.la", "K-Hentai"]
+        );
+        let selection = mirrors.resolve_names(["k-hentai"]).unwrap();
+        assert_eq!(
+            names(mirrors.links(Some(&selection), "1")),
+            vec!["K-Hentai"]
+        );
+        assert_eq!(mirrors.resolve_names(["nope"]), Err("nope".to_string()));
+
+        mirrors
+            .down
+            .write()
+            .unwrap()
+            .insert("Hitomi.la".to_string());
+        assert_eq!(names(mirrors.links(None, "1")), vec!["K-Hentai"]);
+    }
+
+    #[tokio::test]
+    async fn test_health_check_marks_server_errors_down() {
+        let statuses: Statuses = Arc::default();
+        statuses.lock().unwrap().insert("/".to_string(), 503);
+        let base = mock_server_with_statuses(Routes::default(), Arc::clone(&statuses));
+
+        let mirrors = Mirrors::new(parse_templates(&format!("Local={}/g/{{id}}", base)).unwrap());
+        mirrors.check_health(&Client::new()).await;
+        assert!(!mirrors.is_up("Local"));
+        assert!(mirrors.links(None, "1").is_empty());
+
+        statuses.lock().unwrap().clear();
+        mirrors.check_health(&Client::new()).await;
+        assert!(mirrors.is_up("Local"));
+    }
+}
@@ -0,0 +1,548 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+use crate::util::{BoxFuture, env_number};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MemoryRole {
+    Human,
+    Ai,
+}
+
+/// 대화 한 턴. planabrain이 쓰던 `{ role, content, at }` 형식(밀리초)을 그대로 사용합니다.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct MemoryTurn {
+    pub role: MemoryRole,
+    pub content: String,
+    pub at: i64,
+}
+
+impl MemoryTurn {
+    pub(crate) fn human(content: impl Into<String>) -> Self {
+        Self {
+            role: MemoryRole::Human,
+            content: content.into(),
+            at: now_millis(),
+        }
+    }
+
+    pub(crate) fn ai(content: impl Into<String>) -> Self {
+        Self {
+            role: MemoryRole::Ai,
+            content: content.into(),
+            at: now_millis(),
+        }
+    }
+
+    /// 질문 앞에 붙는 "현재 시각: ..." 줄을 뺀 본문.
+    pub(crate) fn body(&self) -> &str {
+        self.content
+            .strip_prefix("현재 시각:")
+            .and_then(|rest| rest.split_once("\n\n"))
+            .map_or(&self.content, |(_, body)| body)
+    }
+}
+
+/// 마지막 `question` 질문 턴의 위치. 그 뒤로 다른 질문이 없을 때만 찾습니다.
+pub(crate) fn last_exchange_start(turns: &[MemoryTurn], question: &str) -> Option<usize> {
+    let start = turns
+        .iter()
+        .rposition(|turn| turn.role == MemoryRole::Human)?;
+    (turns[start].body() == question).then_some(start)
+}
+
+/// 대화 기록에 적용할 변경.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MemoryEdit {
+    Append(Vec<MemoryTurn>),
+    /// 다시 생성: 같은 질문의 마지막 문답을 새 문답으로 바꿉니다. 없으면 덧붙입니다.
+    ReplaceExchange {
+        question: String,
+        turns: Vec<MemoryTurn>,
+    },
+    /// 이어서 작성: 같은 질문의 마지막 답변 뒤에 이어 붙입니다. 없으면 기록하지 않습니다.
+    ExtendAnswer {
+        question: String,
+        text: String,
+    },
+}
+
+impl MemoryEdit {
+    fn apply(self, messages: &mut Vec<MemoryTurn>) {
+        match self {
+            MemoryEdit::Append(turns) => messages.extend(turns),
+            MemoryEdit::ReplaceExchange { question, turns } => {
+                if let Some(start) = last_exchange_start(messages, &question) {
+                    messages.truncate(start);
+                }
+                messages.extend(turns);
+            }
+            MemoryEdit::ExtendAnswer { question, text } => {
+                if last_exchange_start(messages, &question).is_some()
+                    && let Some(last) = messages.last_mut()
+                    && last.role == MemoryRole::Ai
+                {
+                    last.content.push_str("\n\n");
+                    last.content.push_str(&text);
+                    last.at = now_millis();
+                }
+            }
+        }
+    }
+}
+
+/// 사용자별 대화 메모리 저장소.
+pub(crate) trait MemoryStore: Send + Sync {
+    /// 보존 정책을 적용한 최근 대화 기록을 오래된 순으로 돌려줍니다.
+    fn history<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, Result<Vec<MemoryTurn>>>;
+    fn update<'a>(&'a self, user_id: &'a str, edit: MemoryEdit) -> BoxFuture<'a, Result<()>>;
+    /// 기록이 있어 삭제했으면 `true`를 돌려줍니다.
+    fn reset<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, Result<bool>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RetentionPolicy {
+    pub max_messages: usize,
+    pub max_age: Option<Duration>,
+    pub max_tokens: usize,
+}
+
+impl RetentionPolicy {
+    pub(crate) fn from_env() -> Self {
+        let enabled = std::env::var("PLANABRAIN_MEMORY_ENABLED")
+            .map(|raw| !(raw == "0" || raw.eq_ignore_ascii_case("false")))
+            .unwrap_or(true);
+        let max_messages = env_number("PLANABRAIN_MEMORY_MAX_MESSAGES").unwrap_or(20);
+        let retention_days = env_number("PLANABOT_MEMORY_RETENTION_DAYS").unwrap_or(30);
+        let max_tokens = env_number("PLANABOT_MEMORY_MAX_TOKENS").unwrap_or(8000);
+
+        Self {
+            max_messages: if enabled { max_messages } else { 0 },
+            max_age: (retention_days > 0)
+                .then(|| Duration::from_secs(retention_days as u64 * 24 * 60 * 60)),
+            max_tokens,
+        }
+    }
+
+    /// 나이 → 개수 → 토큰 순으로 잘라내고, 남은 기록을 오래된 순으로 돌려줍니다.
+    pub(crate) fn apply(&self, turns: Vec<MemoryTurn>, now_ms: i64) -> Vec<MemoryTurn> {
+        if self.max_messages == 0 {
+            return Vec::new();
+        }
+
+        let mut kept: Vec<MemoryTurn> = turns
+            .into_iter()
+            .filter(|turn| !turn.content.trim().is_empty())
+            .filter(|turn| match self.max_age {
+                Some(max_age) => now_ms.saturating_sub(turn.at) <= max_age.as_millis() as i64,
+                None => true,
+            })
+            .collect();
+
+        if kept.len() > self.max_messages {
+            kept.drain(..kept.len() - self.max_messages);
+        }
+
+        if self.max_tokens > 0 {
+            let mut total = 0usize;
+            let mut start = kept.len();
+            for turn in kept.iter().rev() {
+                let tokens = estimate_tokens(&turn.content);
+                if total + tokens > self.max_tokens {
+                    break;
+                }
+                total += tokens;
+                start -= 1;
+            }
+            kept.drain(..start);
+        }
+
+        kept
+    }
+}
+
+/// 토크나이저 없이 쓰는 대략적인 토큰 수 추정치입니다.
+/// ASCII는 4글자당 1토큰, 그 밖의 문자(한글 등)는 글자당 1토큰으로 셉니다.
+pub(crate) fn estimate_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(ascii, other), ch| {
+        if ch.is_ascii() {
+            (ascii + 1, other)
+        } else {
+            (ascii, other + 1)
+        }
+    });
+    ascii.div_ceil(4) + other
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredMemoryFile {
+    version: u32,
+    messages: Vec<MemoryTurn>,
+}
+
+/// 사용자마다 JSON 파일 하나에 대화를 저장합니다.
+///
+/// 예전에 planabrain이 쓰던 메모리 디렉터리(`planabrain/.planabrain/memory`, 같은 형식)가 있으면
+/// 새 파일이 없는 사용자는 그 파일을 읽고, 다음에 기록할 때 새 경로로 옮깁니다.
+#[derive(Debug, Clone)]
+pub(crate) struct FileMemoryStore {
+    dir: PathBuf,
+    legacy_dir: Option<PathBuf>,
+    policy: RetentionPolicy,
+    /// 읽고-고치고-쓰는 동안 다른 요청이 끼어들지 않게 합니다.
+    lock: Arc<Mutex<()>>,
+}
+
+impl FileMemoryStore {
+    pub(crate) fn new(dir: PathBuf, policy: RetentionPolicy) -> Self {
+        Self {
+            dir,
+            legacy_dir: None,
+            policy,
+            lock: Arc::default(),
+        }
+    }
+
+    pub(crate) fn from_env() -> Self {
+        Self::new(resolve_memory_dir(), RetentionPolicy::from_env())
+            .with_legacy_dir(resolve_legacy_memory_dir())
+    }
+
+    pub(crate) fn with_legacy_dir(mut self, legacy_dir: Option<PathBuf>) -> Self {
+        self.legacy_dir = legacy_dir.filter(|dir| *dir != self.dir);
+        self
+    }
+
+    fn file_path(&self, user_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", safe_user_id(user_id)))
+    }
+
+    fn legacy_path(&self, user_id: &str) -> Option<PathBuf> {
+        let dir = self.legacy_dir.as_ref()?;
+        Some(dir.join(format!("{}.json", safe_user_id(user_id))))
+    }
+
+    async fn read_user(&self, user_id: &str) -> Result<Vec<MemoryTurn>> {
+        if let Some(messages) = read_file(&self.file_path(user_id)).await? {
+            return Ok(messages);
+        }
+        match self.legacy_path(user_id) {
+            Some(path) => Ok(read_file(&path).await?.unwrap_or_default()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn remove_legacy(&self, user_id: &str) -> Result<bool> {
+        let Some(path) = self.legacy_path(user_id) else {
+            return Ok(false);
+        };
+        remove_file(&path).await
+    }
+}
+
+async fn read_file(path: &Path) -> Result<Option<Vec<MemoryTurn>>> {
+    let raw = match fs::read_to_string(path).await {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let file: StoredMemoryFile =
+        serde_json::from_str(&raw).context("메모리 파일 형식이 올바르지 않습니다")?;
+    Ok(Some(file.messages))
+}
+
+async fn remove_file(path: &Path) -> Result<bool> {
+    match fs::remove_file(path).await {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+impl MemoryStore for FileMemoryStore {
+    fn history<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, Result<Vec<MemoryTurn>>> {
+        Box::pin(async move {
+            let _guard = self.lock.lock().await;
+            let turns = self.read_user(user_id).await?;
+            Ok(self.policy.apply(turns, now_millis()))
+        })
+    }
+
+    fn update<'a>(&'a self, user_id: &'a str, edit: MemoryEdit) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if self.policy.max_messages == 0 {
+                return Ok(());
+            }
+
+            let _guard = self.lock.lock().await;
+            let mut messages = self.read_user(user_id).await?;
+            edit.apply(&mut messages);
+            let messages = self.policy.apply(messages, now_millis());
+
+            fs::create_dir_all(&self.dir).await?;
+            let payload = serde_json::to_string(&StoredMemoryFile {
+                version: 1,
+                messages,
+            })?;
+            fs::write(self.file_path(user_id), payload).await?;
+            self.remove_legacy(user_id).await?;
+            Ok(())
+        })
+    }
+
+    fn reset<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(async move {
+            let _guard = self.lock.lock().await;
+            let removed = remove_file(&self.file_path(user_id)).await?;
+            let removed_legacy = self.remove_legacy(user_id).await?;
+            Ok(removed || removed_legacy)
+        })
+    }
+}
+
+/// planabrain이 메모리를 직접 저장하던 때의 경로. (`PLANABRAIN_MEMORY_DIR`, 없으면 인덱스 옆 `memory`)
+fn resolve_legacy_memory_dir() -> Option<PathBuf> {
+    let root = super::find_planabrain_root()?;
+    let resolve = |raw: String| {
+        let path = PathBuf::from(raw);
+        if path.is_absolute() {
+            path
+        } else {
+            root.join(path)
+        }
+    };
+
+    if let Ok(raw) = std::env::var("PLANABRAIN_MEMORY_DIR") {
+        return Some(resolve(raw));
+    }
+    let index_path = resolve(
+        std::env::var("PLANABRAIN_INDEX_PATH")
+            .unwrap_or_else(|_| ".planabrain/index.json".to_string()),
+    );
+    Some(index_path.parent().unwrap_or(&root).join("memory"))
+}
+
+fn resolve_memory_dir() -> PathBuf {
+    let raw =
+        std::env::var("PLANABOT_MEMORY_DIR").unwrap_or_else(|_| ".planabot/memory".to_string());
+    let path = PathBuf::from(raw);
+    if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(path)
+    }
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+fn safe_user_id(raw: &str) -> String {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return "default".to_string();
+    }
+
+    let mut out = String::new();
+    for ch in trimmed.chars() {
+        if ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' {
+            out.push(ch);
+        } else {
+            out.push('_');
+        }
+        if out.len() >= 200 {
+            break;
+        }
+    }
+
+    if out.is_empty() {
+        "default".to_string()
+    } else {
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(role: MemoryRole, content: &str, at: i64) -> MemoryTurn {
+        MemoryTurn {
+            role,
+            content: content.to_string(),
+            at,
+        }
+    }
+
+    fn policy(max_messages: usize, max_age_ms: Option<u64>, max_tokens: usize) -> RetentionPolicy {
+        RetentionPolicy {
+            max_messages,
+            max_age: max_age_ms.map(Duration::from_millis),
+            max_tokens,
+        }
+    }
+
+    #[test]
+    fn test_retention_drops_old_turns() {
+        let turns = vec![
+            turn(MemoryRole::Human, "old", 0),
+            turn(MemoryRole::Human, "new", 9_000),
+        ];
+        let kept = policy(10, Some(5_000), 0).apply(turns, 10_000);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].content, "new");
+    }
+
+    #[test]
+    fn test_retention_keeps_newest_within_token_budget() {
+        let turns = vec![
+            turn(MemoryRole::Human, "가나다라", 1),
+            turn(MemoryRole::Ai, "마바", 2),
+            turn(MemoryRole::Human, "사아", 3),
+        ];
+        let kept = policy(10, None, 4).apply(turns, 10);
+        let contents: Vec<_> = kept.iter().map(|t| t.content.as_str()).collect();
+        assert_eq!(contents, vec!["마바", "사아"]);
+    }
+
+    #[test]
+    fn test_retention_caps_message_count() {
+        let turns = (0..5)
+            .map(|i| turn(MemoryRole::Human, &i.to_string(), i))
+            .collect();
+        let kept = policy(2, None, 0).apply(turns, 10);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].content, "3");
+    }
+
+    #[test]
+    fn test_estimate_tokens_counts_ascii_and_hangul() {
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("선생님"), 3);
+    }
+
+    #[tokio::test]
+    async fn test_file_store_append_history_and_reset() {
+        let dir = std::env::temp_dir().join(format!("planabot-memory-test-{}", now_millis()));
+        let store = FileMemoryStore::new(dir.clone(), policy(10, None, 0));
+
+        store
+            .update(
+                "user/1",
+                MemoryEdit::Append(vec![MemoryTurn::human("q"), MemoryTurn::ai("a")]),
+            )
+            .await
+            .unwrap();
+        let history = store.history("user/1").await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].role, MemoryRole::Ai);
+        assert!(dir.join("user_1.json").exists());
+
+        assert!(store.reset("user/1").await.unwrap());
+        assert!(!store.reset("user/1").await.unwrap());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_updates_keep_every_turn() {
+        let dir = std::env::temp_dir().join(format!("planabot-memory-race-{}", now_millis()));
+        let store = FileMemoryStore::new(dir.clone(), policy(100, None, 0));
+
+        let handles: Vec<_> = (0..10)
+            .map(|i| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    store
+                        .update(
+                            "1",
+                            MemoryEdit::Append(vec![MemoryTurn::human(i.to_string())]),
+                        )
+                        .await
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+
+        assert_eq!(store.history("1").await.unwrap().len(), 10);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_regenerate_and_continue_do_not_duplicate_turns() {
+        let question = "오늘 날씨";
+        let mut messages = vec![
+            turn(MemoryRole::Human, "이전 질문", 1),
+            turn(MemoryRole::Ai, "이전 답변", 2),
+            turn(
+                MemoryRole::Human,
+                &format!("현재 시각: 2026-01-01 (목) 09:00:00\n\n{question}"),
+                3,
+            ),
+            turn(MemoryRole::Ai, "맑음", 4),
+        ];
+
+        MemoryEdit::ReplaceExchange {
+            question: question.to_string(),
+            turns: vec![MemoryTurn::human(question), MemoryTurn::ai("흐림")],
+        }
+        .apply(&mut messages);
+        let contents: Vec<_> = messages.iter().map(|t| t.content.as_str()).collect();
+        assert_eq!(contents, vec!["이전 질문", "이전 답변", question, "흐림"]);
+
+        MemoryEdit::ExtendAnswer {
+            question: question.to_string(),
+            text: "오후에는 비".to_string(),
+        }
+        .apply(&mut messages);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[3].content, "흐림\n\n오후에는 비");
+
+        // 그 사이에 다른 질문을 했으면 덮어쓰지 않고 덧붙입니다.
+        MemoryEdit::ReplaceExchange {
+            question: "이전 질문".to_string(),
+            turns: vec![MemoryTurn::human("이전 질문"), MemoryTurn::ai("새 답변")],
+        }
+        .apply(&mut messages);
+        assert_eq!(messages.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_reads_and_migrates_legacy_planabrain_memory() {
+        let base = std::env::temp_dir().join(format!("planabot-memory-legacy-{}", now_millis()));
+        let legacy = base.join("planabrain/.planabrain/memory");
+        std::fs::create_dir_all(&legacy).unwrap();
+        std::fs::write(
+            legacy.join("7.json"),
+            r#"{"version":1,"messages":[{"role":"human","content":"q","at":1},{"role":"ai","content":"a","at":2}]}"#,
+        )
+        .unwrap();
+        let store = FileMemoryStore::new(base.join("new"), policy(10, None, 0))
+            .with_legacy_dir(Some(legacy.clone()));
+
+        assert_eq!(store.history("7").await.unwrap().len(), 2);
+
+        store
+            .update("7", MemoryEdit::Append(vec![MemoryTurn::human("q2")]))
+            .await
+            .unwrap();
+        assert!(!legacy.join("7.json").exists());
+        assert!(base.join("new/7.json").exists());
+        assert_eq!(store.history("7").await.unwrap().len(), 3);
+        let _ = std::fs::remove_dir_all(base);
+    }
+}
@@ -1,11 +1,18 @@
+mod memory;
+
 use std::collections::HashSet;
-use std::path::{Path, PathBuf};
-use std::process::Command as ProcessCommand;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command as ProcessCommand, Stdio};
 
 use anyhow::{Context, Result, anyhow};
 use once_cell::sync::Lazy;
 use tokio::task;
 
+pub(crate) use memory::{
+    FileMemoryStore, MemoryEdit, MemoryRole, MemoryStore, MemoryTurn, last_exchange_start,
+};
+
 pub(crate) fn extract_plana_question(text: &str) -> Option<String> {
     let trimmed = text.trim_start();
     let prefixes = ["프라나야"];
@@ -22,27 +29,24 @@ pub(crate) fn extract_plana_question(text: &str) -> Option<String> {
     None
 }
 
-pub(crate) async fn run_planabrain_ask(question: &str, user_id: &str) -> Result<String> {
+/// planabrain CLI에 질문합니다. 대화 기록은 표준 입력(JSON)으로 넘기며,
+/// planabrain은 자체 메모리 파일을 읽거나 쓰지 않습니다.
+pub(crate) async fn run_planabrain_ask(
+    question: &str,
+    user_id: &str,
+    history: &[MemoryTurn],
+) -> Result<String> {
     let question = question.to_string();
     let user_id = user_id.to_string();
+    let history = serde_json::to_vec(&serde_json::json!({ "messages": history }))?;
 
-    let handle = task::spawn_blocking(move || run_planabrain_ask_blocking(&question, &user_id));
+    let handle =
+        task::spawn_blocking(move || run_planabrain_ask_blocking(&question, &user_id, &history));
     handle
         .await
         .context("planabrain 실행 작업이 중단되었습니다")?
 }
 
-pub(crate) async fn reset_user_memory(user_id: &str) -> Result<bool> {
-    let root = find_planabrain_root().context("planabrain 디렉터리를 찾지 못했습니다")?;
-    let memory_file = planabrain_memory_file(&root, user_id)?;
-
-    match tokio::fs::remove_file(&memory_file).await {
-        Ok(()) => Ok(true),
-        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
-        Err(err) => Err(err.into()),
-    }
-}
-
 pub(crate) fn is_planabrain_allowed(chat_id: i64, user_id: Option<i64>, is_private: bool) -> bool {
     if ALLOWED_CHAT_IDS.contains(&chat_id) {
         return true;
@@ -76,68 +80,9 @@ fn find_planabrain_root() -> Option<PathBuf> {
     let cwd = std::env::current_dir().ok()?;
     let candidates = [cwd.join("planabrain"), cwd.join("..").join("planabrain")];
 
-    for candidate in candidates {
-        if candidate.join("package.json").exists() {
-            return Some(candidate);
-        }
-    }
-
-    None
-}
-
-fn planabrain_memory_file(planabrain_root: &Path, user_id: &str) -> Result<PathBuf> {
-    let memory_dir = resolve_planabrain_memory_dir(planabrain_root)?;
-    let safe_id = safe_user_id(user_id);
-    Ok(memory_dir.join(format!("{safe_id}.json")))
-}
-
-fn resolve_planabrain_memory_dir(planabrain_root: &Path) -> Result<PathBuf> {
-    if let Ok(raw) = std::env::var("PLANABRAIN_MEMORY_DIR") {
-        return Ok(resolve_relative(planabrain_root, &raw));
-    }
-
-    let index_path = std::env::var("PLANABRAIN_INDEX_PATH")
-        .unwrap_or_else(|_| ".planabrain/index.json".to_string());
-    let index_path = resolve_relative(planabrain_root, &index_path);
-    let base = index_path
-        .parent()
-        .map(|p| p.to_path_buf())
-        .unwrap_or_else(|| planabrain_root.to_path_buf());
-    Ok(base.join("memory"))
-}
-
-fn resolve_relative(base: &Path, raw: &str) -> PathBuf {
-    let path = PathBuf::from(raw);
-    if path.is_absolute() {
-        path
-    } else {
-        base.join(path)
-    }
-}
-
-fn safe_user_id(raw: &str) -> String {
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return "default".to_string();
-    }
-
-    let mut out = String::new();
-    for ch in trimmed.chars() {
-        if ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' {
-            out.push(ch);
-        } else {
-            out.push('_');
-        }
-        if out.len() >= 200 {
-            break;
-        }
-    }
-
-    if out.is_empty() {
-        "default".to_string()
-    } else {
-        out
-    }
+    candidates
+        .into_iter()
+        .find(|candidate| candidate.join("package.json").exists())
 }
 
 static ALLOWED_CHAT_IDS: Lazy<HashSet<i64>> = Lazy::new(|| {
@@ -167,7 +112,7 @@ static ALLOWED_USER_IDS: Lazy<HashSet<i64>> = Lazy::new(|| {
         })
         .collect()
 });
-fn run_planabrain_ask_blocking(question: &str, user_id: &str) -> Result<String> {
+fn run_planabrain_ask_blocking(question: &str, user_id: &str, history: &[u8]) -> Result<String> {
     let root = find_planabrain_root().context("planabrain 디렉터리를 찾지 못했습니다")?;
 
     let dist_entry = root.join("dist/cli/index.js");
@@ -191,22 +136,92 @@ fn run_planabrain_ask_blocking(question: &str, user_id: &str) -> Result<String>
     let repo_root = root.parent().unwrap_or(&root);
     let dotenv_path = repo_root.join(".env");
 
-    let command = command.current_dir(&root).env("PLANABRAIN_USER_ID", user_id);
+    let command = command
+        .current_dir(&root)
+        .env("PLANABRAIN_USER_ID", user_id)
+        .env("PLANABRAIN_HISTORY_SOURCE", "stdin");
     if dotenv_path.exists() {
         command.env("DOTENV_CONFIG_PATH", dotenv_path);
     }
 
-    let output = command
+    let child = command
         .arg("ask")
         .arg(question)
-        .output()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
         .context("planabrain 실행 실패")?;
 
+    finish_with_stdin(child, history)
+}
+
+/// `history`를 자식의 stdin에 쓰고 출력을 모읍니다.
+///
+/// 자식이 stdin을 다 읽기 전에 stdout/stderr를 많이 써도 막히지 않도록 stdin은 별도 스레드에서 씁니다.
+/// 자식이 먼저 끝나서 생긴 BrokenPipe는 무시하고, 실패하면 항상 자식의 stderr를 함께 알립니다.
+fn finish_with_stdin(mut child: Child, history: &[u8]) -> Result<String> {
+    let writer = child.stdin.take().map(|mut stdin| {
+        let history = history.to_vec();
+        std::thread::spawn(move || match stdin.write_all(&history) {
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            result => result,
+        })
+    });
+
+    let output = child.wait_with_output().context("planabrain 실행 실패")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(anyhow!("planabrain 오류: {}", stderr.trim()));
     }
 
+    let written = writer.map_or(Ok(()), |writer| {
+        writer
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("stdin 쓰기 스레드가 중단되었습니다")))
+    });
+    if let Err(err) = written {
+        return Err(anyhow!(
+            "planabrain에 대화 기록을 전달하지 못했습니다: {} ({})",
+            err,
+            stderr.trim()
+        ));
+    }
+
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     Ok(stdout)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_sh(script: &str) -> Child {
+        ProcessCommand::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_early_exit_reports_stderr_instead_of_broken_pipe() {
+        let history = vec![b'x'; 1 << 20];
+        let err = finish_with_stdin(spawn_sh("echo boom >&2; exit 3"), &history).unwrap_err();
+        assert_eq!(err.to_string(), "planabrain 오류: boom");
+    }
+
+    #[test]
+    fn test_large_output_before_reading_stdin_does_not_block() {
+        let history = vec![b'x'; 1 << 20];
+        let script = "head -c 1048576 /dev/zero >&2; wc -c; echo done";
+        let stdout = finish_with_stdin(spawn_sh(script), &history).unwrap();
+        assert_eq!(
+            stdout.split_whitespace().collect::<Vec<_>>(),
+            ["1048576", "done"]
+        );
+    }
+}
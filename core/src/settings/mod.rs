@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use anyhow::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+use crate::hitomi::GalleryInfo;
+
+/// 차단 태그가 걸린 갤러리를 어떻게 보여줄지.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum BlacklistMode {
+    /// 아예 표시하지 않습니다.
+    Refuse,
+    /// 해당 태그만 숨기고 스포일러 경고를 붙입니다.
+    #[default]
+    Hide,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ChatSettings {
+    /// `None`이면 기본값(개인 채팅 허용, 그룹은 `PLANABOT_ADULT_DEFAULT`)을 따릅니다.
+    #[serde(default)]
+    pub adult: Option<bool>,
+    #[serde(default)]
+    pub blacklist: Vec<String>,
+    #[serde(default)]
+    pub blacklist_mode: BlacklistMode,
+    /// `true`면 이 채팅의 갤러리 조회 기록을 남기지 않습니다.
+    #[serde(default)]
+    pub history_disabled: bool,
+    /// 갤러리 키보드에 보일 미러 이름. `None`이면 설정된 미러를 모두 보입니다.
+    #[serde(default)]
+    pub mirrors: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct UserSettings {
+    #[serde(default)]
+    pub blacklist: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SettingsFile {
+    #[serde(default)]
+    chats: HashMap<i64, ChatSettings>,
+    #[serde(default)]
+    users: HashMap<u64, UserSettings>,
+}
+
+/// 채팅·사용자 설정 저장소. 메모리에 들고 있다가 바뀔 때마다 JSON 파일로 저장합니다.
+#[derive(Clone)]
+pub(crate) struct SettingsStore {
+    path: PathBuf,
+    adult_default: bool,
+    inner: Arc<RwLock<SettingsFile>>,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl SettingsStore {
+    pub(crate) fn new(path: PathBuf, adult_default: bool) -> Self {
+        let file = load_file(&path);
+        Self {
+            path,
+            adult_default,
+            inner: Arc::new(RwLock::new(file)),
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub(crate) fn from_env() -> Self {
+        let adult_default = std::env::var("PLANABOT_ADULT_DEFAULT")
+            .map(|raw| raw == "1" || raw.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        Self::new(resolve_settings_path(), adult_default)
+    }
+
+    pub(crate) fn chat(&self, chat_id: i64) -> ChatSettings {
+        self.inner
+            .read()
+            .ok()
+            .and_then(|file| file.chats.get(&chat_id).cloned())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn user(&self, user_id: u64) -> UserSettings {
+        self.inner
+            .read()
+            .ok()
+            .and_then(|file| file.users.get(&user_id).cloned())
+            .unwrap_or_default()
+    }
+
+    /// 개인 채팅은 항상 허용하고, 그룹은 설정이 없으면 기본값을 따릅니다.
+    pub(crate) fn adult_allowed(&self, chat_id: i64, is_private: bool) -> bool {
+        is_private || self.chat(chat_id).adult.unwrap_or(self.adult_default)
+    }
+
+    /// 채팅별 설정이 없을 때 그룹에서 갤러리 조회를 허용하는지.
+    pub(crate) fn adult_default(&self) -> bool {
+        self.adult_default
+    }
+
+    pub(crate) fn history_enabled(&self, chat_id: i64) -> bool {
+        !self.chat(chat_id).history_disabled
+    }
+
+    pub(crate) fn content_policy(
+        &self,
+        chat_id: Option<i64>,
+        user_id: Option<u64>,
+    ) -> ContentPolicy {
+        let chat = chat_id.map(|id| self.chat(id)).unwrap_or_default();
+        let user = user_id.map(|id| self.user(id)).unwrap_or_default();
+        let mut blacklist = chat.blacklist;
+        for tag in user.blacklist {
+            if !blacklist.contains(&tag) {
+                blacklist.push(tag);
+            }
+        }
+        ContentPolicy {
+            blacklist,
+            mode: chat.blacklist_mode,
+        }
+    }
+
+    pub(crate) async fn update_chat(
+        &self,
+        chat_id: i64,
+        update: impl FnOnce(&mut ChatSettings),
+    ) -> Result<ChatSettings> {
+        let _guard = self.write_lock.lock().await;
+        let (settings, payload) = {
+            let mut file = self
+                .inner
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let settings = file.chats.entry(chat_id).or_default();
+            update(settings);
+            let settings = settings.clone();
+            if settings == ChatSettings::default() {
+                file.chats.remove(&chat_id);
+            }
+            (settings, serde_json::to_string_pretty(&*file)?)
+        };
+        persist(&self.path, payload).await?;
+        Ok(settings)
+    }
+
+    pub(crate) async fn update_user(
+        &self,
+        user_id: u64,
+        update: impl FnOnce(&mut UserSettings),
+    ) -> Result<UserSettings> {
+        let _guard = self.write_lock.lock().await;
+        let (settings, payload) = {
+            let mut file = self
+                .inner
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let settings = file.users.entry(user_id).or_default();
+            update(settings);
+            let settings = settings.clone();
+            if settings == UserSettings::default() {
+                file.users.remove(&user_id);
+            }
+            (settings, serde_json::to_string_pretty(&*file)?)
+        };
+        persist(&self.path, payload).await?;
+        Ok(settings)
+    }
+}
+
+/// 차단 태그 입력을 저장 형식으로 맞춥니다. (`Female:Big_Breasts` → `female:big breasts`)
+pub(crate) fn normalize_tag(raw: &str) -> Option<String> {
+    let tag = raw.trim().to_lowercase().replace('_', " ");
+    let tag = tag.trim();
+    (!tag.is_empty()).then(|| tag.to_string())
+}
+
+/// 채팅과 사용자 차단 목록을 합친 결과.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ContentPolicy {
+    pub blacklist: Vec<String>,
+    pub mode: BlacklistMode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Verdict {
+    Allowed,
+    /// 걸린 태그를 숨기고 보여줍니다.
+    Hidden(Vec<String>),
+    /// 걸린 태그 때문에 보여주지 않습니다.
+    Refused(Vec<String>),
+}
+
+impl ContentPolicy {
+    /// 차단 항목 `glasses`는 `female:glasses`에도 맞고, `female:glasses`는 정확히 그 태그에만 맞습니다.
+    pub(crate) fn matched_tags(&self, info: &GalleryInfo) -> Vec<String> {
+        info.tags
+            .iter()
+            .map(ToString::to_string)
+            .filter(|tag| {
+                let tag = tag.to_lowercase();
+                self.blacklist.iter().any(|blocked| {
+                    tag == *blocked || tag.split_once(':').is_some_and(|(_, name)| name == blocked)
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn check(&self, info: &GalleryInfo) -> Verdict {
+        let matched = self.matched_tags(info);
+        if matched.is_empty() {
+            Verdict::Allowed
+        } else if self.mode == BlacklistMode::Refuse {
+            Verdict::Refused(matched)
+        } else {
+            Verdict::Hidden(matched)
+        }
+    }
+
+    /// 걸린 태그를 뺀 사본을 돌려줍니다.
+    pub(crate) fn redact(&self, info: &GalleryInfo) -> GalleryInfo {
+        let matched = self.matched_tags(info);
+        let mut redacted = info.clone();
+        redacted
+            .tags
+            .retain(|tag| !matched.contains(&tag.to_string()));
+        redacted
+    }
+}
+
+fn load_file(path: &Path) -> SettingsFile {
+    let Ok(raw) = std::fs::read_to_string(path) else {
+        return SettingsFile::default();
+    };
+    serde_json::from_str(&raw).unwrap_or_else(|err| {
+        warn!(
+            "설정 파일 형식이 올바르지 않습니다 ({}): {}",
+            path.display(),
+            err
+        );
+        SettingsFile::default()
+    })
+}
+
+async fn persist(path: &Path, payload: String) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(path, payload).await?;
+    Ok(())
+}
+
+fn resolve_settings_path() -> PathBuf {
+    let raw = std::env::var("PLANABOT_SETTINGS_PATH")
+        .unwrap_or_else(|_| ".planabot/settings.json".to_string());
+    let path = PathBuf::from(raw);
+    if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitomi::GalleryTag;
+
+    fn info(tags: &[&str]) -> GalleryInfo {
+        GalleryInfo {
+            id: "1".to_string(),
+            tags: tags.iter().map(|tag| GalleryTag::parse(tag)).collect(),
+            ..GalleryInfo::default()
+        }
+    }
+
+    fn temp_store() -> (SettingsStore, PathBuf) {
+        let path = std::env::temp_dir().join(format!(
+            "planabot-settings-test-{}-{}.json",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+        ));
+        (SettingsStore::new(path.clone(), false), path)
+    }
+
+    #[test]
+    fn test_policy_matches_prefixed_and_bare_tags() {
+        let policy = ContentPolicy {
+            blacklist: vec!["guro".to_string(), "male:yaoi".to_string()],
+            mode: BlacklistMode::Hide,
+        };
+        let info = info(&["female:guro", "female:yaoi", "full color"]);
+        assert_eq!(policy.matched_tags(&info), vec!["female:guro"]);
+        assert_eq!(
+            policy.check(&info),
+            Verdict::Hidden(vec!["female:guro".to_string()])
+        );
+        assert_eq!(policy.redact(&info).tags.len(), 2);
+    }
+
+    #[test]
+    fn test_refuse_mode_and_clean_gallery() {
+        let policy = ContentPolicy {
+            blacklist: vec!["guro".to_string()],
+            mode: BlacklistMode::Refuse,
+        };
+        assert!(matches!(
+            policy.check(&info(&["guro"])),
+            Verdict::Refused(_)
+        ));
+        assert_eq!(policy.check(&info(&["full color"])), Verdict::Allowed);
+    }
+
+    #[tokio::test]
+    async fn test_store_merges_chat_and_user_blacklists_and_persists() {
+        let (store, path) = temp_store();
+        assert!(!store.adult_allowed(-100, false));
+        assert!(store.adult_allowed(5, true));
+
+        store
+            .update_chat(-100, |chat| {
+                chat.adult = Some(true);
+                chat.blacklist.push("guro".to_string());
+            })
+            .await
+            .unwrap();
+        store
+            .update_user(7, |user| user.blacklist.push("yaoi".to_string()))
+            .await
+            .unwrap();
+
+        let reloaded = SettingsStore::new(path.clone(), false);
+        assert!(reloaded.adult_allowed(-100, false));
+        let policy = reloaded.content_policy(Some(-100), Some(7));
+        assert_eq!(policy.blacklist, vec!["guro", "yaoi"]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(
+            normalize_tag(" Female:Big_Breasts ").as_deref(),
+            Some("female:big breasts")
+        );
+        assert_eq!(normalize_tag("__"), None);
+    }
+}
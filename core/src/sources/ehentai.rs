@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use log::warn;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::hitomi::{GalleryError, GalleryInfo, GalleryTag};
+use crate::util::{BoxFuture, USER_AGENT};
+
+use super::{GalleryRef, GallerySource, SourceKind, env_url};
+
+const DEFAULT_API_URL: &str = "https://api.e-hentai.org";
+
+/// E-Hentai 갤러리 메타데이터 API (`gdata`). ID는 `<gid>/<token>`입니다.
+pub(crate) struct EHentaiSource {
+    client: Client,
+    api_url: String,
+}
+
+impl EHentaiSource {
+    pub(crate) fn new(api_url: &str) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(15))
+            .user_agent(USER_AGENT)
+            .build()
+            .expect("reqwest client should build");
+        Self {
+            client,
+            api_url: api_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub(crate) fn from_env() -> Self {
+        Self::new(&env_url("PLANABOT_EHENTAI_API_URL", DEFAULT_API_URL))
+    }
+
+    async fn fetch_gallery(&self, id: &str) -> Result<GalleryInfo, GalleryError> {
+        let Some((gid, token)) = id
+            .split_once('/')
+            .and_then(|(gid, token)| Some((gid.parse::<u64>().ok()?, token)))
+        else {
+            return Err(GalleryError::NotFound);
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/api.php", self.api_url))
+            .json(&json!({
+                "method": "gdata",
+                "gidlist": [[gid, token]],
+                "namespace": 1,
+            }))
+            .send()
+            .await
+            .inspect_err(|err| warn!("E-Hentai 요청 실패 (ID {}): {}", id, err))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            warn!("E-Hentai 응답 오류 (ID {}): {}", id, status);
+            return Err(GalleryError::Upstream(status));
+        }
+
+        let raw = response.text().await?;
+        parse_gdata(id, &raw)
+    }
+}
+
+impl GallerySource for EHentaiSource {
+    fn kind(&self) -> SourceKind {
+        SourceKind::EHentai
+    }
+
+    fn fetch<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<GalleryInfo, GalleryError>> {
+        Box::pin(self.fetch_gallery(id))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GdataResponse {
+    #[serde(default)]
+    gmetadata: Vec<GalleryRaw>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GalleryRaw {
+    /// 없는 갤러리나 틀린 토큰이면 다른 필드 없이 이것만 옵니다.
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    title_jpn: Option<String>,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    posted: Option<String>,
+    #[serde(default)]
+    filecount: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn parse_gdata(id: &str, raw: &str) -> Result<GalleryInfo, GalleryError> {
+    let response: GdataResponse =
+        serde_json::from_str(raw).map_err(|err| GalleryError::Parse(err.to_string()))?;
+    let raw = response
+        .gmetadata
+        .into_iter()
+        .next()
+        .ok_or_else(|| GalleryError::Parse("gmetadata가 비어 있습니다".to_string()))?;
+    if raw.error.is_some() {
+        return Err(GalleryError::NotFound);
+    }
+
+    let mut info = GalleryInfo {
+        id: GalleryRef {
+            kind: SourceKind::EHentai,
+            id: id.to_string(),
+        }
+        .to_string(),
+        title: raw
+            .title
+            .filter(|title| !title.trim().is_empty())
+            .unwrap_or_else(|| "정보 없음".to_string()),
+        japanese_title: raw.title_jpn.filter(|title| !title.trim().is_empty()),
+        gallery_type: raw.category.map(|category| category.to_lowercase()),
+        page_count: raw
+            .filecount
+            .and_then(|count| count.parse().ok())
+            .unwrap_or_default(),
+        date: raw
+            .posted
+            .and_then(|secs| secs.parse().ok())
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+            .map(|date| date.format("%Y-%m-%d").to_string()),
+        ..GalleryInfo::default()
+    };
+
+    for tag in raw.tags {
+        let (namespace, name) = tag.split_once(':').unwrap_or(("", tag.as_str()));
+        let name = name.to_string();
+        match namespace {
+            "artist" => info.artists.push(name),
+            "group" => info.groups.push(name),
+            "parody" if name != "original" => info.series.push(name),
+            "parody" => {}
+            "character" => info.characters.push(name),
+            "language" if name == "translated" || name == "rewrite" => {}
+            "language" => {
+                info.language_code.get_or_insert_with(|| name.clone());
+                info.language.get_or_insert(name);
+            }
+            "female" | "male" => info.tags.push(GalleryTag::parse(&tag)),
+            _ => info.tags.push(GalleryTag::parse(&name)),
+        }
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitomi::TagKind;
+    use crate::test_support::{Routes, mock_server};
+
+    #[tokio::test]
+    async fn test_fetch_maps_recorded_gdata() {
+        let routes: Routes = Default::default();
+        routes.lock().unwrap().insert(
+            "/api.php".to_string(),
+            include_bytes!("fixtures/ehentai_gdata.json").to_vec(),
+        );
+        let source = EHentaiSource::new(&mock_server(routes));
+
+        let info = source.fetch("618395/0439fa3666").await.unwrap();
+        assert_eq!(info.id, "e:618395/0439fa3666");
+        assert!(info.title.starts_with("(Kouroumu 8)"));
+        assert_eq!(info.gallery_type.as_deref(), Some("non-h"));
+        assert_eq!(info.artists, vec!["nanahara fuyuki"]);
+        assert_eq!(info.series, vec!["touhou project"]);
+        assert_eq!(info.language.as_deref(), Some("english"));
+        assert_eq!(info.tags[0].kind, TagKind::Female);
+        assert_eq!(info.tags[1].to_string(), "full color");
+        assert_eq!(info.page_count, 20);
+        assert_eq!(info.date.as_deref(), Some("2013-08-10"));
+    }
+
+    #[test]
+    fn test_error_entry_is_not_found() {
+        let raw = include_str!("fixtures/ehentai_error.json");
+        assert!(matches!(
+            parse_gdata("618395/0000000000", raw),
+            Err(GalleryError::NotFound)
+        ));
+    }
+}
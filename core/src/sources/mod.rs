@@ -0,0 +1,296 @@
+mod ehentai;
+mod nhentai;
+
+use std::fmt;
+use std::sync::Arc;
+
+use log::error;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::hitomi::{
+    CacheConfig, CacheLookup, GalleryCache, GalleryClient, GalleryError, GalleryInfo,
+};
+use crate::util::BoxFuture;
+
+pub(crate) use ehentai::EHentaiSource;
+pub(crate) use nhentai::NHentaiSource;
+
+/// 갤러리 출처. ID 앞의 접두사로 구분하며, 접두사가 없으면 Hitomi입니다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum SourceKind {
+    Hitomi,
+    NHentai,
+    EHentai,
+}
+
+impl SourceKind {
+    fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix.to_ascii_lowercase().as_str() {
+            "nh" => Some(SourceKind::NHentai),
+            "e" => Some(SourceKind::EHentai),
+            _ => None,
+        }
+    }
+
+    fn prefix(self) -> Option<&'static str> {
+        match self {
+            SourceKind::Hitomi => None,
+            SourceKind::NHentai => Some("nh"),
+            SourceKind::EHentai => Some("e"),
+        }
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            SourceKind::Hitomi => "Hitomi.la",
+            SourceKind::NHentai => "nhentai",
+            SourceKind::EHentai => "E-Hentai",
+        }
+    }
+}
+
+/// 출처와 출처 안에서의 ID. 문자열로는 `123`, `nh:177013`, `e:618395/0439fa3666` 형식이며
+/// 즐겨찾기·콜백·기록에는 이 문자열을 그대로 저장합니다.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GalleryRef {
+    pub kind: SourceKind,
+    pub id: String,
+}
+
+impl GalleryRef {
+    pub(crate) fn new(kind: SourceKind, id: &str) -> Option<Self> {
+        static EHENTAI_ID_RE: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^\d+/[0-9a-f]{10}$").unwrap());
+
+        let valid = match kind {
+            SourceKind::Hitomi | SourceKind::NHentai => {
+                !id.is_empty() && id.len() <= 10 && id.bytes().all(|b| b.is_ascii_digit())
+            }
+            SourceKind::EHentai => EHENTAI_ID_RE.is_match(id),
+        };
+        valid.then(|| Self {
+            kind,
+            id: id.to_string(),
+        })
+    }
+
+    /// `GalleryRef`를 문자열로 바꾼 값을 다시 읽습니다.
+    pub(crate) fn parse(raw: &str) -> Option<Self> {
+        match raw.split_once(':') {
+            Some((prefix, id)) => Self::new(SourceKind::from_prefix(prefix)?, id),
+            None => Self::new(SourceKind::Hitomi, raw),
+        }
+    }
+
+    /// `!nh 177013`, `!e 618395/0439fa3666`처럼 접두사로 고른 출처의 ID를 읽습니다.
+    /// 접두사가 비어 있으면 Hitomi입니다.
+    pub(crate) fn from_prefixed(prefix: &str, id: &str) -> Option<Self> {
+        let kind = if prefix.is_empty() {
+            SourceKind::Hitomi
+        } else {
+            SourceKind::from_prefix(prefix)?
+        };
+        Self::new(kind, id.trim_end_matches('/'))
+    }
+
+    /// 붙여 넣은 갤러리 주소에서 출처와 ID를 찾습니다.
+    pub(crate) fn from_url(text: &str) -> Option<Self> {
+        // hitomi.la/galleries/123.html, hitomi.la/reader/123.html#1, hitomi.la/doujinshi/title-korean-123.html
+        static HITOMI_RE: Lazy<Regex> = Lazy::new(|| {
+            Regex::new(r"(?i)hitomi\.la/(?:[a-z]+/)(?:[^\s/?#]*-)?(\d+)\.html").unwrap()
+        });
+        static NHENTAI_RE: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"(?i)nhentai\.net/g/(\d+)").unwrap());
+        static EHENTAI_RE: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"(?i)(?:e-|ex)hentai\.org/g/(\d+)/([0-9a-f]{10})").unwrap());
+
+        if let Some(cap) = HITOMI_RE.captures(text) {
+            Self::new(SourceKind::Hitomi, &cap[1])
+        } else if let Some(cap) = NHENTAI_RE.captures(text) {
+            Self::new(SourceKind::NHentai, &cap[1])
+        } else if let Some(cap) = EHENTAI_RE.captures(text) {
+            Self::new(SourceKind::EHentai, &format!("{}/{}", &cap[1], &cap[2]))
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn page_url(&self) -> String {
+        match self.kind {
+            SourceKind::Hitomi => format!("https://hitomi.la/galleries/{}.html", self.id),
+            SourceKind::NHentai => format!("https://nhentai.net/g/{}/", self.id),
+            SourceKind::EHentai => format!("https://e-hentai.org/g/{}/", self.id),
+        }
+    }
+}
+
+impl fmt::Display for GalleryRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind.prefix() {
+            Some(prefix) => write!(f, "{}:{}", prefix, self.id),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+/// 갤러리 정보 제공자. 돌려주는 `GalleryInfo::id`는 `GalleryRef` 문자열 형식입니다.
+pub(crate) trait GallerySource: Send + Sync {
+    fn kind(&self) -> SourceKind;
+
+    /// `id`는 출처 안에서의 ID입니다. (`GalleryRef::id`)
+    fn fetch<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<GalleryInfo, GalleryError>>;
+}
+
+impl GallerySource for GalleryClient {
+    fn kind(&self) -> SourceKind {
+        SourceKind::Hitomi
+    }
+
+    fn fetch<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<GalleryInfo, GalleryError>> {
+        Box::pin(self.get_gallery_info(id))
+    }
+}
+
+/// 출처별 제공자 모음. ID 문자열을 보고 알맞은 제공자에게 넘깁니다.
+#[derive(Clone)]
+pub(crate) struct GallerySources {
+    sources: Arc<Vec<Arc<dyn GallerySource>>>,
+    /// Hitomi 이외 출처의 캐시. Hitomi는 `GalleryClient`가 따로 캐시합니다.
+    cache: Arc<GalleryCache>,
+}
+
+impl GallerySources {
+    pub(crate) fn new(sources: Vec<Arc<dyn GallerySource>>) -> Self {
+        let cache = CacheConfig {
+            persist_path: None,
+            ..CacheConfig::from_env()
+        };
+        Self {
+            sources: Arc::new(sources),
+            cache: Arc::new(GalleryCache::new(cache)),
+        }
+    }
+
+    pub(crate) fn from_env(hitomi: GalleryClient) -> Self {
+        Self::new(vec![
+            Arc::new(hitomi),
+            Arc::new(NHentaiSource::from_env()),
+            Arc::new(EHentaiSource::from_env()),
+        ])
+    }
+
+    pub(crate) async fn get(&self, gallery_id: &str) -> Result<GalleryInfo, GalleryError> {
+        let Some(gallery) = GalleryRef::parse(gallery_id) else {
+            return Err(GalleryError::NotFound);
+        };
+        let Some(source) = self
+            .sources
+            .iter()
+            .find(|source| source.kind() == gallery.kind)
+        else {
+            return Err(GalleryError::NotFound);
+        };
+        if gallery.kind == SourceKind::Hitomi {
+            return source.fetch(&gallery.id).await;
+        }
+
+        let key = gallery.to_string();
+        match self.cache.get(&key) {
+            CacheLookup::Hit(info) => return Ok(*info),
+            CacheLookup::NotFound => return Err(GalleryError::NotFound),
+            CacheLookup::Miss => {}
+        }
+        let result = source.fetch(&gallery.id).await;
+        match &result {
+            Ok(info) => self.cache.insert(&key, info.clone()),
+            Err(GalleryError::NotFound) => self.cache.insert_not_found(&key),
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// 여러 ID를 최대 `concurrency`개씩 동시에 조회합니다. 결과는 입력 순서를 따릅니다.
+    pub(crate) async fn get_many(
+        &self,
+        gallery_ids: &[String],
+        concurrency: usize,
+    ) -> Vec<(String, Result<GalleryInfo, GalleryError>)> {
+        let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+        let mut tasks = JoinSet::new();
+
+        for (index, gallery_id) in gallery_ids.iter().cloned().enumerate() {
+            let sources = self.clone();
+            let permits = Arc::clone(&permits);
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let result = sources.get(&gallery_id).await;
+                (index, gallery_id, result)
+            });
+        }
+
+        let mut results = Vec::with_capacity(gallery_ids.len());
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(result) => results.push(result),
+                Err(err) => error!("갤러리 일괄 조회 작업 실패: {}", err),
+            }
+        }
+        results.sort_by_key(|(index, _, _)| *index);
+        results
+            .into_iter()
+            .map(|(_, gallery_id, result)| (gallery_id, result))
+            .collect()
+    }
+}
+
+fn env_url(key: &str, default: &str) -> String {
+    std::env::var(key)
+        .ok()
+        .map(|raw| raw.trim().trim_end_matches('/').to_string())
+        .filter(|raw| !raw.is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gallery_ref_round_trips_and_validates() {
+        for raw in ["123", "nh:177013", "e:618395/0439fa3666"] {
+            assert_eq!(GalleryRef::parse(raw).unwrap().to_string(), raw);
+        }
+        assert!(GalleryRef::parse("nh:abc").is_none());
+        assert!(GalleryRef::parse("e:618395").is_none());
+        assert!(GalleryRef::parse("x:1").is_none());
+        assert_eq!(
+            GalleryRef::from_prefixed("NH", "177013")
+                .unwrap()
+                .to_string(),
+            "nh:177013"
+        );
+    }
+
+    #[test]
+    fn test_gallery_ref_from_urls() {
+        let cases = [
+            ("https://hitomi.la/reader/123.html#2", "123"),
+            ("https://nhentai.net/g/177013/", "nh:177013"),
+            (
+                "https://exhentai.org/g/618395/0439fa3666/?p=1",
+                "e:618395/0439fa3666",
+            ),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(GalleryRef::from_url(url).unwrap().to_string(), expected);
+        }
+        assert_eq!(
+            GalleryRef::parse("e:618395/0439fa3666").unwrap().page_url(),
+            "https://e-hentai.org/g/618395/0439fa3666/"
+        );
+        assert!(GalleryRef::from_url("https://example.com/g/1").is_none());
+    }
+}
@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use log::warn;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+
+use crate::hitomi::{GalleryError, GalleryInfo, GalleryTag};
+use crate::util::{BoxFuture, USER_AGENT};
+
+use super::{GalleryRef, GallerySource, SourceKind, env_url};
+
+const DEFAULT_BASE_URL: &str = "https://nhentai.net";
+
+/// nhentai 갤러리 API (`/api/gallery/<id>`).
+pub(crate) struct NHentaiSource {
+    client: Client,
+    base_url: String,
+}
+
+impl NHentaiSource {
+    pub(crate) fn new(base_url: &str) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(15))
+            .user_agent(USER_AGENT)
+            .build()
+            .expect("reqwest client should build");
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub(crate) fn from_env() -> Self {
+        Self::new(&env_url("PLANABOT_NHENTAI_URL", DEFAULT_BASE_URL))
+    }
+
+    async fn fetch_gallery(&self, id: &str) -> Result<GalleryInfo, GalleryError> {
+        let url = format!("{}/api/gallery/{}", self.base_url, id);
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .inspect_err(|err| warn!("nhentai 요청 실패 (ID {}): {}", id, err))?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => return Err(GalleryError::NotFound),
+            status if !status.is_success() => {
+                warn!("nhentai 응답 오류 (ID {}): {}", id, status);
+                return Err(GalleryError::Upstream(status));
+            }
+            _ => {}
+        }
+
+        let raw = response.text().await?;
+        parse_gallery(id, &raw)
+    }
+}
+
+impl GallerySource for NHentaiSource {
+    fn kind(&self) -> SourceKind {
+        SourceKind::NHentai
+    }
+
+    fn fetch<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<GalleryInfo, GalleryError>> {
+        Box::pin(self.fetch_gallery(id))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GalleryRaw {
+    title: TitleRaw,
+    #[serde(default)]
+    upload_date: Option<i64>,
+    #[serde(default)]
+    tags: Vec<TagRaw>,
+    #[serde(default)]
+    num_pages: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct TitleRaw {
+    #[serde(default)]
+    english: Option<String>,
+    #[serde(default)]
+    japanese: Option<String>,
+    #[serde(default)]
+    pretty: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagRaw {
+    #[serde(rename = "type")]
+    kind: String,
+    name: String,
+}
+
+fn parse_gallery(id: &str, raw: &str) -> Result<GalleryInfo, GalleryError> {
+    let raw: GalleryRaw =
+        serde_json::from_str(raw).map_err(|err| GalleryError::Parse(err.to_string()))?;
+    let names = |kind: &str| -> Vec<String> {
+        raw.tags
+            .iter()
+            .filter(|tag| tag.kind == kind)
+            .map(|tag| tag.name.clone())
+            .collect()
+    };
+    let non_empty = |value: &Option<String>| value.clone().filter(|v| !v.trim().is_empty());
+    // "translated"/"rewrite"는 언어가 아니라 번역 여부 표시입니다.
+    let language = names("language")
+        .into_iter()
+        .find(|name| name != "translated" && name != "rewrite");
+
+    Ok(GalleryInfo {
+        id: GalleryRef {
+            kind: SourceKind::NHentai,
+            id: id.to_string(),
+        }
+        .to_string(),
+        title: non_empty(&raw.title.english)
+            .or_else(|| non_empty(&raw.title.pretty))
+            .unwrap_or_else(|| "정보 없음".to_string()),
+        japanese_title: non_empty(&raw.title.japanese),
+        gallery_type: names("category").into_iter().next(),
+        artists: names("artist"),
+        groups: names("group"),
+        series: names("parody")
+            .into_iter()
+            .filter(|name| name != "original")
+            .collect(),
+        characters: names("character"),
+        language_code: language.clone(),
+        language,
+        tags: names("tag")
+            .iter()
+            .map(|name| GalleryTag::parse(name))
+            .collect(),
+        page_count: raw.num_pages,
+        date: raw
+            .upload_date
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+            .map(|date| date.format("%Y-%m-%d").to_string()),
+        cover_hash: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Routes, mock_server};
+
+    const FIXTURE: &str = include_str!("fixtures/nhentai_gallery.json");
+
+    #[tokio::test]
+    async fn test_fetch_maps_recorded_gallery() {
+        let routes: Routes = Default::default();
+        routes.lock().unwrap().insert(
+            "/api/gallery/177013".to_string(),
+            FIXTURE.as_bytes().to_vec(),
+        );
+        let source = NHentaiSource::new(&mock_server(routes));
+
+        let info = source.fetch("177013").await.unwrap();
+        assert_eq!(info.id, "nh:177013");
+        assert_eq!(info.title, "[ShindoLA] METAMORPHOSIS (Complete) [English]");
+        assert_eq!(info.japanese_title, None);
+        assert_eq!(info.gallery_type.as_deref(), Some("manga"));
+        assert_eq!(info.artists, vec!["shindol"]);
+        assert!(info.series.is_empty());
+        assert_eq!(info.language.as_deref(), Some("english"));
+        assert_eq!(info.tags.len(), 2);
+        assert_eq!(info.page_count, 225);
+        assert_eq!(info.date.as_deref(), Some("2016-10-18"));
+
+        assert!(matches!(
+            source.fetch("1").await,
+            Err(GalleryError::NotFound)
+        ));
+    }
+}
@@ -0,0 +1,97 @@
+//! 테스트에서 쓰는 최소한의 HTTP 목 서버.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+/// 경로(쿼리 제외) → 응답 본문. 없는 경로는 404입니다.
+pub(crate) type Routes = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+/// 경로(쿼리 제외) → `Location`. `routes`보다 먼저 보고 302로 응답합니다.
+pub(crate) type Redirects = Arc<Mutex<HashMap<String, String>>>;
+
+/// 경로(쿼리 제외) → 상태 코드. 다른 표보다 먼저 보고 본문 없이 그 코드로 응답합니다.
+pub(crate) type Statuses = Arc<Mutex<HashMap<String, u16>>>;
+
+/// 요청마다 `routes`를 보고 응답하는 서버를 띄우고 `http://127.0.0.1:<port>`를 돌려줍니다.
+/// 메서드는 구분하지 않고, 요청 본문은 읽고 버립니다.
+pub(crate) fn mock_server(routes: Routes) -> String {
+    mock_server_with_redirects(routes, Redirects::default())
+}
+
+/// `mock_server`에 리다이렉트 응답을 더한 서버.
+pub(crate) fn mock_server_with_redirects(routes: Routes, redirects: Redirects) -> String {
+    serve(routes, redirects, Statuses::default())
+}
+
+/// `mock_server`에 고정 상태 코드(5xx 등) 응답을 더한 서버.
+pub(crate) fn mock_server_with_statuses(routes: Routes, statuses: Statuses) -> String {
+    serve(routes, Redirects::default(), statuses)
+}
+
+fn serve(routes: Routes, redirects: Redirects, statuses: Statuses) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let Some((path, _body)) = read_request(&mut stream) else {
+                continue;
+            };
+            let path = path.split('?').next().unwrap_or("/").to_string();
+            let status = statuses.lock().unwrap().get(&path).copied();
+            let location = redirects.lock().unwrap().get(&path).cloned();
+            let body = routes.lock().unwrap().get(&path).cloned();
+            let (status, extra, body) = match (status, location, body) {
+                (Some(code), _, _) => (format!("{} Mock", code), String::new(), Vec::new()),
+                (None, Some(location), _) => (
+                    "302 Found".to_string(),
+                    format!("Location: {}\r\n", location),
+                    Vec::new(),
+                ),
+                (None, None, Some(body)) => ("200 OK".to_string(), String::new(), body),
+                (None, None, None) => ("404 Not Found".to_string(), String::new(), Vec::new()),
+            };
+            let head = format!(
+                "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                extra,
+                body.len()
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(&body);
+        }
+    });
+    format!("http://{}", addr)
+}
+
+fn read_request(stream: &mut impl Read) -> Option<(String, Vec<u8>)> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    let header_end = loop {
+        if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+    };
+    let head = String::from_utf8_lossy(&request[..header_end]).to_string();
+    let content_length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = request[header_end..].to_vec();
+    while body.len() < content_length {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => body.extend_from_slice(&buf[..n]),
+        }
+    }
+    let path = head.split_whitespace().nth(1)?.to_string();
+    Some((path, body))
+}
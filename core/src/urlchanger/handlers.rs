@@ -1,13 +1,19 @@
-use crate::bot::{AppState, HandlerResult, SendOptions, send_reply_with_fallback, send_in_thread};
-use crate::urlchanger::link_utils::{
-    LinkConversion, contains_instagram_link, contains_music_link, contains_x_link,
-    convert_instagram_links, convert_x_links, extract_music_links,
+use crate::bot::{
+    AppState, HandlerResult, SendOptions, extract_gallery_ids, send_in_thread,
+    send_reply_with_fallback,
 };
+use crate::planabrain::truncate_message;
+use crate::urlchanger::link_utils::{Delivery, LinkConversion, apply_conversions, message_preview};
+use crate::urlchanger::metadata::LinkMetadata;
+use crate::urlchanger::repost::{build_repost, message_text, reply_target};
+use crate::urlchanger::undo::{UNDO_CALLBACK, UndoStore};
 use log::{error, warn};
 use teloxide::dispatching::DpHandlerDescription;
 use teloxide::prelude::*;
-use teloxide::sugar::request::RequestLinkPreviewExt;
-use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use teloxide::types::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, ReplyParameters};
+
+/// 버튼 이름으로 쓸 제목의 최대 글자 수.
+const BUTTON_TITLE_CHARS: usize = 40;
 
 pub fn url_handlers<B>() -> Handler<'static, HandlerResult, DpHandlerDescription>
 where
@@ -15,214 +21,253 @@ where
     B::Err: std::error::Error + Send + Sync + 'static,
     <B as Requester>::GetUpdates: Send,
     <B as Requester>::GetChatMember: Send,
+    <B as Requester>::CopyMessage: Send,
+    <B as Requester>::SendMessage: Send,
 {
-    Update::filter_message()
-        .branch(
-            dptree::filter(|msg: Message, state: AppState| state.is_after_boot(&msg))
-                .branch(
-                    dptree::filter(|msg: Message| {
-                        msg.text().is_some() && contains_music_link(msg.text().unwrap())
-                    })
-                    .endpoint(handle_music_links::<B>),
-                )
-                .branch(
-                    dptree::filter(|msg: Message| {
-                        msg.text().is_some() && contains_x_link(msg.text().unwrap())
-                    })
-                    .endpoint(handle_x_links::<B>),
-                )
-                .branch(
-                    dptree::filter(|msg: Message| {
-                        msg.text().is_some() && contains_instagram_link(msg.text().unwrap())
-                    })
-                    .endpoint(handle_instagram_links::<B>),
-                ),
-        )
+    Update::filter_message().branch(
+        dptree::filter(|msg: Message, state: AppState| {
+            state.is_after_boot(&msg) && !has_gallery_request(&msg, &state.bot_username)
+        })
+        .filter_map_async(|msg: Message, state: AppState| async move {
+            let links = state.links.rewrite_expanded(message_text(&msg)?).await;
+            (!links.is_empty()).then_some(links)
+        })
+        .endpoint(handle_links::<B>),
+    )
 }
 
-pub async fn handle_music_links<B>(bot: B, msg: Message, state: AppState) -> HandlerResult
+/// 갤러리 요청(`!123`, 갤러리 URL 등)이 든 메시지는 링크를 정리하지 않고 갤러리 조회로 넘깁니다.
+/// 관리자 모드에서 원본을 지우고 다시 올리면 조회가 묻히기 때문입니다.
+fn has_gallery_request(msg: &Message, bot_username: &str) -> bool {
+    msg.text()
+        .is_some_and(|text| !extract_gallery_ids(text.trim(), msg, bot_username).is_empty())
+}
+
+pub async fn handle_links<B>(
+    bot: B,
+    msg: Message,
+    state: AppState,
+    links: Vec<LinkConversion>,
+) -> HandlerResult
 where
     B: Requester + Send + Sync + 'static,
     B::Err: std::error::Error + Send + Sync + 'static,
     <B as Requester>::GetChatMember: Send,
+    <B as Requester>::CopyMessage: Send,
+    <B as Requester>::SendMessage: Send,
 {
     state.record_group_chat(&msg).await;
 
-    let text = msg.text().unwrap_or("");
-    let links = extract_music_links(text);
-
-    if links.is_empty() {
-        return Ok(());
-    }
-
-    let chat_member = match bot
-        .get_chat_member(msg.chat.id, bot.get_me().await?.id)
+    if state
+        .admin_cache
+        .can_delete(&bot, msg.chat.id, state.bot_id)
         .await
     {
-        Ok(member) => member,
-        Err(e) => {
-            error!("관리자 권한 확인 중 오류 발생: {:?}", e);
-            return handle_without_admin_rights(&bot, &msg, &links).await;
-        }
-    };
-
-    if chat_member.kind.is_privileged() {
-        handle_with_admin_rights(&bot, &msg, &links).await
+        handle_with_admin_rights(&bot, &msg, &links, &state).await
     } else {
-        handle_without_admin_rights(&bot, &msg, &links).await
+        handle_without_admin_rights(&bot, &msg, &links, &state).await
     }
 }
 
 async fn handle_with_admin_rights<B>(
     bot: &B,
     msg: &Message,
-    links: &[(String, String)],
-) -> HandlerResult
-where
-    B: Requester + ?Sized,
-    B::Err: Send + Sync + 'static,
-{
-    if let Err(e) = bot.delete_message(msg.chat.id, msg.id).await {
-        warn!("메시지 삭제 실패: {:?}", e);
-        return handle_without_admin_rights(bot, msg, links).await;
-    }
-
-    let username = display_name(msg);
-    let mut cleaned_text = msg.text().unwrap_or("").to_string();
-
-    for (original, cleaned) in links {
-        cleaned_text = cleaned_text.replace(original, cleaned);
-    }
-
-    send_in_thread(bot, msg, format!("{}: {}", username, cleaned_text)).await?;
-
-    Ok(())
-}
-
-async fn handle_without_admin_rights<B>(
-    bot: &B,
-    msg: &Message,
-    links: &[(String, String)],
+    links: &[LinkConversion],
+    state: &AppState,
 ) -> HandlerResult
 where
     B: Requester + ?Sized,
     B::Err: Send + Sync + 'static,
+    B::SendMessage: Send,
+    B::CopyMessage: Send,
 {
-    let mut keyboard = Vec::new();
-
-    for (i, (_, cleaned)) in links.iter().enumerate() {
-        match reqwest::Url::parse(cleaned) {
-            Ok(url) => {
-                let row = vec![InlineKeyboardButton::url(
-                    format!("정리된 링크 #{}", i + 1),
-                    url,
-                )];
-                keyboard.push(row);
+    let undo = &state.link_undo;
+    let repost = build_repost(msg, links);
+    let reply_to =
+        reply_target(msg).map(|id| ReplyParameters::new(id).allow_sending_without_reply());
+
+    // 다시 올리기에 실패해도 원본이 남도록 텍스트와 미디어 모두 먼저 보내고, 성공한 뒤에 원본을 지웁니다.
+    if msg.text().is_some() {
+        let mut req = send_in_thread(bot, msg, repost.text)
+            .entities(repost.entities)
+            .link_preview_options(message_preview(links));
+        if let Some(reply_to) = reply_to {
+            req = req.reply_parameters(reply_to);
+        }
+        if undo.enabled() {
+            req = req.reply_markup(UndoStore::keyboard());
+        }
+        let sent = match req.await {
+            Ok(sent) => sent,
+            Err(e) => {
+                warn!("변환한 메시지 전송 실패: {:?}", e);
+                return handle_without_admin_rights(bot, msg, links, state).await;
+            }
+        };
+        if let Err(e) = bot.delete_message(msg.chat.id, msg.id).await {
+            warn!("메시지 삭제 실패: {:?}", e);
+            if let Err(e) = bot.delete_message(msg.chat.id, sent.id).await {
+                warn!("변환한 메시지 삭제 실패: {:?}", e);
             }
-            Err(e) => warn!("URL 파싱 오류: {}, URL: {}", e, cleaned),
+            return handle_without_admin_rights(bot, msg, links, state).await;
         }
-    }
-
-    if keyboard.is_empty() {
+        undo.remember(msg.chat.id, sent.id, msg);
         return Ok(());
     }
 
-    let markup = InlineKeyboardMarkup::new(keyboard);
-
-    send_reply_with_fallback(
-        bot,
-        msg,
-        "추적 파라미터가 제거된 링크:",
-        SendOptions {
-            reply_markup: Some(markup),
-            ..SendOptions::default()
-        },
-    )
-    .await?;
+    let mut req = bot
+        .copy_message(msg.chat.id, msg.chat.id, msg.id)
+        .caption(repost.text)
+        .caption_entities(repost.entities);
+    if let Some(thread_id) = msg.thread_id {
+        req = req.message_thread_id(thread_id);
+    }
+    if let Some(reply_to) = reply_to {
+        req = req.reply_parameters(reply_to);
+    }
+    if undo.enabled() {
+        req = req.reply_markup(UndoStore::keyboard());
+    }
+    let copied = match req.await {
+        Ok(copied) => copied,
+        Err(e) => {
+            warn!("캡션 메시지 복사 실패: {:?}", e);
+            return handle_without_admin_rights(bot, msg, links, state).await;
+        }
+    };
+    if let Err(e) = bot.delete_message(msg.chat.id, msg.id).await {
+        warn!("메시지 삭제 실패: {:?}", e);
+        if let Err(e) = bot.delete_message(msg.chat.id, copied).await {
+            warn!("복사한 메시지 삭제 실패: {:?}", e);
+        }
+        return handle_without_admin_rights(bot, msg, links, state).await;
+    }
+    undo.remember(msg.chat.id, copied, msg);
 
     Ok(())
 }
 
-pub async fn handle_x_links<B>(bot: B, msg: Message, state: AppState) -> HandlerResult
+pub fn is_undo_callback(data: &str) -> bool {
+    data == UNDO_CALLBACK
+}
+
+/// "↩ 원본 복원" 버튼: 작성자나 관리자가 누르면 원본을 다시 올리고 변환한 메시지를 지웁니다.
+pub async fn handle_undo_callback<B>(bot: B, query: CallbackQuery, state: AppState) -> HandlerResult
 where
     B: Requester + Send + Sync + 'static,
     B::Err: std::error::Error + Send + Sync + 'static,
-    <B as Requester>::GetChatMember: Send,
+    B::SendMessage: Send,
+    B::CopyMessage: Send,
 {
-    state.record_group_chat(&msg).await;
+    let Some(message) = query.regular_message().cloned() else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+    let chat_id = message.chat.id;
 
-    let text = msg.text().unwrap_or("");
-    let links = convert_x_links(text);
+    let Some(entry) = state.link_undo.get(chat_id, message.id) else {
+        let _ = bot.edit_message_reply_markup(chat_id, message.id).await;
+        bot.answer_callback_query(query.id)
+            .text("선생님, 원본을 복원할 수 있는 시간이 지났습니다.")
+            .await?;
+        return Ok(());
+    };
 
-    if links.is_empty() {
+    let allowed = entry.author == Some(query.from.id)
+        || bot
+            .get_chat_member(chat_id, query.from.id)
+            .await
+            .map(|member| member.kind.is_privileged())
+            .unwrap_or(false);
+    if !allowed {
+        bot.answer_callback_query(query.id)
+            .text("선생님, 원본 복원은 작성자나 관리자만 할 수 있습니다.")
+            .show_alert(true)
+            .await?;
         return Ok(());
     }
-
-    let chat_member = match bot
-        .get_chat_member(msg.chat.id, bot.get_me().await?.id)
-        .await
-    {
-        Ok(member) => member,
-        Err(e) => {
-            error!("관리자 권한 확인 중 오류 발생(X): {:?}", e);
-            return handle_x_without_admin(&bot, &msg, &links).await;
-        }
+    // 두 번 눌러도 한 번만 복원합니다.
+    let Some(entry) = state.link_undo.take(chat_id, message.id) else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
     };
 
-    if chat_member.kind.is_privileged() {
-        handle_x_with_admin(&bot, &msg, &links).await
+    let reply_to = entry
+        .reply_to
+        .map(|id| ReplyParameters::new(id).allow_sending_without_reply());
+    let restored = if entry.media {
+        let mut req = bot
+            .copy_message(chat_id, chat_id, message.id)
+            .caption(entry.original.text)
+            .caption_entities(entry.original.entities);
+        if let Some(thread_id) = entry.thread_id {
+            req = req.message_thread_id(thread_id);
+        }
+        if let Some(reply_to) = reply_to {
+            req = req.reply_parameters(reply_to);
+        }
+        req.await.map(|_| ())
     } else {
-        handle_x_without_admin(&bot, &msg, &links).await
-    }
-}
-
-async fn handle_x_with_admin<B>(bot: &B, msg: &Message, links: &[LinkConversion]) -> HandlerResult
-where
-    B: Requester + ?Sized,
-    B::Err: Send + Sync + 'static,
-{
-    if let Err(e) = bot.delete_message(msg.chat.id, msg.id).await {
-        warn!("X 메시지 삭제 실패: {:?}", e);
-        return handle_x_without_admin(bot, msg, links).await;
+        let mut req = bot
+            .send_message(chat_id, entry.original.text)
+            .entities(entry.original.entities);
+        if let Some(thread_id) = entry.thread_id {
+            req = req.message_thread_id(thread_id);
+        }
+        if let Some(reply_to) = reply_to {
+            req = req.reply_parameters(reply_to);
+        }
+        req.await.map(|_| ())
+    };
+    if let Err(e) = restored {
+        error!("원본 복원 실패: {:?}", e);
+        bot.answer_callback_query(query.id)
+            .text("선생님, 원본을 복원하지 못했습니다.")
+            .await?;
+        return Ok(());
     }
 
-    let username = display_name(msg);
-    let mut converted_text = msg.text().unwrap_or("").to_string();
-    for link in links {
-        converted_text = converted_text.replace(&link.original, &link.converted);
+    if let Err(e) = bot.delete_message(chat_id, message.id).await {
+        warn!("변환한 메시지 삭제 실패: {:?}", e);
+        let _ = bot.edit_message_reply_markup(chat_id, message.id).await;
     }
-
-    let disable_preview = links.iter().any(|l| l.disable_preview);
-
-    send_in_thread(bot, msg, format!("{}: {}", username, converted_text))
-        .disable_link_preview(disable_preview)
-        .await?;
-
+    bot.answer_callback_query(query.id).await?;
     Ok(())
 }
 
-async fn handle_x_without_admin<B>(
+async fn handle_without_admin_rights<B>(
     bot: &B,
     msg: &Message,
     links: &[LinkConversion],
+    state: &AppState,
 ) -> HandlerResult
 where
     B: Requester + ?Sized,
     B::Err: Send + Sync + 'static,
 {
-    let mut converted_text = msg.text().unwrap_or("").to_string();
-    for link in links {
-        converted_text = converted_text.replace(&link.original, &link.converted);
+    let cards = match &state.link_cards {
+        Some(fetcher) => {
+            let urls: Vec<String> = links
+                .iter()
+                .filter(|link| link.delivery == Delivery::Buttons)
+                .map(|link| link.converted.clone())
+                .collect();
+            fetcher.fetch_all(&urls).await
+        }
+        None => Vec::new(),
+    };
+    let (text, markup) = build_reply(message_text(msg).unwrap_or_default(), links, &cards);
+    if text.is_empty() {
+        return Ok(());
     }
 
-    let disable_preview = links.iter().any(|l| l.disable_preview);
-
     send_reply_with_fallback(
         bot,
         msg,
-        format!("임베드용 링크:\n{}", converted_text),
+        text,
         SendOptions {
-            disable_preview: Some(disable_preview),
+            link_preview: Some(message_preview(links)),
+            reply_markup: markup,
             ..SendOptions::default()
         },
     )
@@ -231,97 +276,124 @@ where
     Ok(())
 }
 
-pub async fn handle_instagram_links<B>(bot: B, msg: Message, state: AppState) -> HandlerResult
-where
-    B: Requester + Send + Sync + 'static,
-    B::Err: std::error::Error + Send + Sync + 'static,
-    <B as Requester>::GetChatMember: Send,
-{
-    state.record_group_chat(&msg).await;
-
-    let text = msg.text().unwrap_or("");
-    let links = convert_instagram_links(text);
-
-    if links.is_empty() {
-        return Ok(());
+/// 모든 변환을 답장 하나로 묶습니다. 임베드용 링크가 있으면 변환된 본문을 보내고,
+/// 추적 파라미터만 정리한 링크는 버튼으로 붙입니다. `cards`는 버튼으로 붙일 링크의
+/// 정보이며 있으면 버튼 이름과 본문에 제목·채널·길이를 보여줍니다.
+fn build_reply(
+    text: &str,
+    links: &[LinkConversion],
+    cards: &[Option<LinkMetadata>],
+) -> (String, Option<InlineKeyboardMarkup>) {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = Vec::new();
+    let mut card_lines = Vec::new();
+    let cleaned = links
+        .iter()
+        .filter(|link| link.delivery == Delivery::Buttons);
+    for (index, link) in cleaned.enumerate() {
+        let url = match reqwest::Url::parse(&link.converted) {
+            Ok(url) => url,
+            Err(e) => {
+                warn!("URL 파싱 오류: {}, URL: {}", e, link.converted);
+                continue;
+            }
+        };
+        let number = keyboard.len() + 1;
+        let label = match cards.get(index).and_then(Option::as_ref) {
+            Some(card) => {
+                card_lines.push(format!("{}. {}", number, card.summary()));
+                truncate_message(&card.title, BUTTON_TITLE_CHARS)
+            }
+            None => format!("정리된 링크 #{}", number),
+        };
+        keyboard.push(vec![InlineKeyboardButton::url(label, url)]);
     }
+    let markup = (!keyboard.is_empty()).then(|| InlineKeyboardMarkup::new(keyboard));
 
-    let chat_member = match bot
-        .get_chat_member(msg.chat.id, bot.get_me().await?.id)
-        .await
-    {
-        Ok(member) => member,
-        Err(e) => {
-            error!("관리자 권한 확인 중 오류 발생(Instagram): {:?}", e);
-            return handle_instagram_without_admin(&bot, &msg, &links).await;
-        }
-    };
-
-    if chat_member.kind.is_privileged() {
-        handle_instagram_with_admin(&bot, &msg, &links).await
+    let mut reply = if links.iter().any(|link| link.delivery == Delivery::Text) {
+        format!("임베드용 링크:\n{}", apply_conversions(text, links))
+    } else if markup.is_some() {
+        "추적 파라미터가 제거된 링크:".to_string()
     } else {
-        handle_instagram_without_admin(&bot, &msg, &links).await
+        String::new()
+    };
+    if !card_lines.is_empty() {
+        reply.push_str(if reply.starts_with("임베드용") {
+            "\n\n"
+        } else {
+            "\n"
+        });
+        reply.push_str(&card_lines.join("\n"));
     }
+    (reply, markup)
 }
 
-async fn handle_instagram_with_admin<B>(
-    bot: &B,
-    msg: &Message,
-    links: &[(String, String)],
-) -> HandlerResult
-where
-    B: Requester + ?Sized,
-    B::Err: Send + Sync + 'static,
-{
-    if let Err(e) = bot.delete_message(msg.chat.id, msg.id).await {
-        warn!("Instagram 메시지 삭제 실패: {:?}", e);
-        return handle_instagram_without_admin(bot, msg, links).await;
-    }
-
-    let username = display_name(msg);
-    let mut converted_text = msg.text().unwrap_or("").to_string();
-    for (original, converted) in links {
-        converted_text = converted_text.replace(original, converted);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::urlchanger::LinkRewriter;
+
+    fn group_message(text: &str) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "message_id": 10,
+            "date": 0,
+            "chat": {"id": -100, "type": "supergroup", "title": "t"},
+            "from": {"id": 7, "is_bot": false, "first_name": "A"},
+            "text": text
+        }))
+        .unwrap()
     }
 
-    send_in_thread(bot, msg, format!("{}: {}", username, converted_text)).await?;
+    #[test]
+    fn test_gallery_requests_are_left_to_the_gallery_lookup() {
+        let text = "!123 https://example.com/?utm_source=x";
+        assert!(!LinkRewriter::default().rewrite(text).is_empty());
+        assert!(has_gallery_request(&group_message(text), "planabot"));
 
-    Ok(())
-}
-
-async fn handle_instagram_without_admin<B>(
-    bot: &B,
-    msg: &Message,
-    links: &[(String, String)],
-) -> HandlerResult
-where
-    B: Requester + ?Sized,
-    B::Err: Send + Sync + 'static,
-{
-    let mut converted_text = msg.text().unwrap_or("").to_string();
-    for (original, converted) in links {
-        converted_text = converted_text.replace(original, converted);
+        let text = "https://example.com/?utm_source=x";
+        assert!(!LinkRewriter::default().rewrite(text).is_empty());
+        assert!(!has_gallery_request(&group_message(text), "planabot"));
     }
 
-    send_reply_with_fallback(
-        bot,
-        msg,
-        format!("임베드용 링크:\n{}", converted_text),
-        SendOptions::default(),
-    )
-    .await?;
+    #[test]
+    fn test_build_reply_covers_every_platform() {
+        let text = "https://x.com/a/status/1?s=20 https://youtu.be/b?si=c";
+        let links = LinkRewriter::default().rewrite(text);
+        let (reply, markup) = build_reply(text, &links, &[]);
+        assert_eq!(
+            reply,
+            "임베드용 링크:\nhttps://fxtwitter.com/a/status/1 https://youtu.be/b"
+        );
+        assert_eq!(markup.unwrap().inline_keyboard.len(), 1);
+    }
 
-    Ok(())
-}
+    #[test]
+    fn test_build_reply_uses_buttons_when_only_cleaning() {
+        let text = "https://youtu.be/b?si=c https://open.spotify.com/track/x?si=y";
+        let links = LinkRewriter::default().rewrite(text);
+        let (reply, markup) = build_reply(text, &links, &[]);
+        assert_eq!(reply, "추적 파라미터가 제거된 링크:");
+        assert_eq!(markup.unwrap().inline_keyboard.len(), 2);
+    }
 
-fn display_name(msg: &Message) -> String {
-    if let Some(user) = msg.from.as_ref() {
-        if let Some(username) = &user.username {
-            username.to_string()
-        } else {
-            user.first_name.clone()
-        }
-    } else {
-        "Unknown".to_string()
+    #[test]
+    fn test_build_reply_shows_cards_and_titles_buttons() {
+        let text = "https://youtu.be/b?si=c https://open.spotify.com/track/x?si=y";
+        let links = LinkRewriter::default().rewrite(text);
+        let cards = [
+            None,
+            Some(LinkMetadata {
+                title: "Track".to_string(),
+                author: Some("Artist".to_string()),
+                duration_secs: Some(215),
+            }),
+        ];
+        let (reply, markup) = build_reply(text, &links, &cards);
+        assert_eq!(
+            reply,
+            "추적 파라미터가 제거된 링크:\n2. Track — Artist (3:35)"
+        );
+        let keyboard = markup.unwrap().inline_keyboard;
+        assert_eq!(keyboard[0][0].text, "정리된 링크 #1");
+        assert_eq!(keyboard[1][0].text, "Track");
     }
 }
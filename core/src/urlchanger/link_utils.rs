@@ -1,232 +1,676 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
 use log::warn;
 use regex::Regex;
+use serde::Deserialize;
+use teloxide::types::LinkPreviewOptions;
 use url::Url;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct LinkConversion {
-    pub original: String,
-    pub converted: String,
-    pub disable_preview: bool,
+use super::resolver::{ResolverConfig, ShortLinkResolver};
+use super::syntax::{LinkMarker, parse_links};
+use super::tracking::TrackingRules;
+
+/// 기본 규칙. `PLANABOT_LINK_RULES_PATH`를 지정하면 그 파일의 규칙으로 바꿉니다.
+const DEFAULT_RULES: &str = include_str!("default_rules.json");
+
+/// 변환 규칙 없이 추적 파라미터만 지운 링크의 규칙 이름.
+const TRACKING_RULE: &str = "tracking";
+
+/// 변환된 링크를 어떻게 알려줄지 (관리자 권한이 없어 원본을 지울 수 없을 때).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Delivery {
+    /// 링크마다 인라인 버튼을 붙입니다. (추적 파라미터 정리)
+    #[default]
+    Buttons,
+    /// 변환된 링크로 바꾼 본문을 답장합니다. (임베드용 변환)
+    Text,
 }
 
-pub fn contains_music_link(text: &str) -> bool {
-    let patterns = [
-        r"https?://(?:www\.)?youtu(?:\.be|be\.com)/\S+",
-        r"https?://(?:www\.)?music\.youtube\.com/\S+",
-        r"https?://(?:www\.)?open\.spotify\.com/\S+",
-    ];
+/// 변환한 링크의 기본 미리보기 정책. 메시지의 링크 표시(`.`/`^`)가 우선합니다.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreviewPolicy {
+    #[default]
+    Show,
+    Hide,
+}
 
-    patterns.iter().any(|pattern| {
-        Regex::new(pattern)
-            .map(|re| re.is_match(text))
-            .unwrap_or(false)
-    })
+#[derive(Debug, Clone, Deserialize)]
+struct PathRewriteConfig {
+    pattern: String,
+    replace: String,
 }
 
-pub fn contains_x_link(text: &str) -> bool {
-    let pattern = r"\.?https?://(?:www\.)?(?:x|twitter)\.com/\S+";
-    Regex::new(pattern)
-        .map(|re| re.is_match(text))
-        .unwrap_or(false)
+/// 설정 파일의 규칙 한 개.
+#[derive(Debug, Clone, Deserialize)]
+struct RuleConfig {
+    name: String,
+    /// 이 호스트와 그 하위 도메인에 적용합니다. (`x.com`은 `www.x.com`도 포함)
+    hosts: Vec<String>,
+    #[serde(default)]
+    replace_host: Option<String>,
+    /// 지울 쿼리 파라미터. `*`이면 모두 지웁니다.
+    #[serde(default)]
+    strip_params: Vec<String>,
+    /// 있으면 이 파라미터만 남깁니다.
+    #[serde(default)]
+    keep_params: Option<Vec<String>>,
+    #[serde(default)]
+    strip_fragment: bool,
+    #[serde(default)]
+    path_rewrites: Vec<PathRewriteConfig>,
+    #[serde(default)]
+    preview: PreviewPolicy,
+    #[serde(default)]
+    delivery: Delivery,
 }
 
-pub fn contains_instagram_link(text: &str) -> bool {
-    let pattern = r"https?://(?:www\.)?instagram\.com/\S+";
-    Regex::new(pattern)
-        .map(|re| re.is_match(text))
-        .unwrap_or(false)
+#[derive(Debug, Clone)]
+struct RewriteRule {
+    name: String,
+    hosts: Vec<String>,
+    replace_host: Option<String>,
+    strip_params: Vec<String>,
+    keep_params: Option<Vec<String>>,
+    strip_fragment: bool,
+    path_rewrites: Vec<(Regex, String)>,
+    preview: PreviewPolicy,
+    delivery: Delivery,
 }
 
-pub fn remove_si_parameter(url_str: &str) -> String {
-    if let Ok(mut url) = Url::parse(url_str) {
-        if url.query().is_some() {
-            let query_pairs: Vec<(String, String)> = url
-                .query_pairs()
-                .filter(|(k, _)| k != "si")
-                .map(|(k, v)| (k.to_string(), v.to_string()))
-                .collect();
+impl RewriteRule {
+    fn compile(config: RuleConfig) -> Result<Self> {
+        if config.hosts.is_empty() {
+            return Err(anyhow!("{} 규칙에 hosts가 없습니다", config.name));
+        }
+        if let Some(host) = &config.replace_host {
+            validate_host(&config.name, host)?;
+        }
+        let path_rewrites = config
+            .path_rewrites
+            .into_iter()
+            .map(|rewrite| {
+                Regex::new(&rewrite.pattern)
+                    .map(|re| (re, rewrite.replace))
+                    .map_err(|err| {
+                        anyhow!(
+                            "{} 규칙의 경로 패턴이 올바르지 않습니다: {}",
+                            config.name,
+                            err
+                        )
+                    })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            name: config.name,
+            hosts: config
+                .hosts
+                .into_iter()
+                .map(|host| host.to_lowercase())
+                .collect(),
+            replace_host: config.replace_host,
+            strip_params: config.strip_params,
+            keep_params: config.keep_params,
+            strip_fragment: config.strip_fragment,
+            path_rewrites,
+            preview: config.preview,
+            delivery: config.delivery,
+        })
+    }
 
-            url.set_query(None);
+    fn matches(&self, url: &Url) -> bool {
+        host_matches(url, &self.hosts)
+    }
 
-            if !query_pairs.is_empty() {
-                let query = query_pairs
-                    .iter()
-                    .map(|(k, v)| format!("{}={}", k, v))
-                    .collect::<Vec<String>>()
-                    .join("&");
+    fn apply(&self, mut url: Url) -> Url {
+        if let Some(host) = &self.replace_host {
+            url.set_host(Some(host)).ok();
+        }
 
-                if !query.is_empty() {
-                    url.set_query(Some(&query));
-                }
-            }
+        if self.strip_params.iter().any(|param| param == "*") {
+            url.set_query(None);
+        } else {
+            retain_query(&mut url, |key| {
+                let keep = self
+                    .keep_params
+                    .as_ref()
+                    .is_none_or(|keep| keep.iter().any(|k| k == key));
+                keep && !self.strip_params.iter().any(|p| p == key)
+            });
         }
 
-        if url.host_str() == Some("youtu.be") {
-            let path = url.path().to_string();
-            if path.contains("si=") {
-                let new_path = path.split("si=").next().unwrap_or("").trim_end_matches('?');
-                url.set_path(new_path);
+        if self.strip_fragment {
+            url.set_fragment(None);
+        }
+
+        if !self.path_rewrites.is_empty() {
+            let mut path = url.path().to_string();
+            for (pattern, replace) in &self.path_rewrites {
+                path = pattern.replace_all(&path, replace.as_str()).into_owned();
             }
+            url.set_path(&path);
         }
+        url
+    }
+}
 
-        return url.to_string();
+fn validate_host(rule: &str, host: &str) -> Result<()> {
+    match Url::parse(&format!("https://{}/", host)) {
+        Ok(url) if url.host_str() == Some(host) => Ok(()),
+        Ok(_) => Err(anyhow!(
+            "{} 규칙의 대상 호스트가 올바르지 않습니다: {}",
+            rule,
+            host
+        )),
+        Err(err) => Err(anyhow!(
+            "{} 규칙의 대상 호스트가 올바르지 않습니다: {}",
+            rule,
+            err
+        )),
     }
+}
 
-    url_str.to_string()
+/// 호스트가 목록의 도메인이거나 그 하위 도메인인지 확인합니다. (목록은 소문자)
+pub(super) fn host_matches(url: &Url, hosts: &[String]) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let host = host.to_lowercase();
+    hosts.iter().any(|rule_host| {
+        host == *rule_host
+            || host
+                .strip_suffix(rule_host.as_str())
+                .is_some_and(|rest| rest.ends_with('.'))
+    })
 }
 
-pub fn extract_music_links(text: &str) -> Vec<(String, String)> {
-    let patterns = [
-        r"(https?://(?:www\.)?youtu(?:\.be|be\.com)/\S+)",
-        r"(https?://(?:www\.)?music\.youtube\.com/\S+)",
-        r"(https?://(?:www\.)?open\.spotify\.com/\S+)",
-    ];
+/// `keep`이 거절한 쿼리 파라미터를 지우고, 지운 것이 있으면 `true`를 돌려줍니다.
+/// 남는 파라미터는 원래 인코딩 그대로 둡니다.
+pub(super) fn retain_query(url: &mut Url, keep: impl Fn(&str) -> bool) -> bool {
+    let Some(query) = url.query() else {
+        return false;
+    };
+    let parts: Vec<&str> = query.split('&').filter(|part| !part.is_empty()).collect();
+    let kept: Vec<&str> = parts
+        .iter()
+        .copied()
+        .filter(|part| {
+            let key = part.split('=').next().unwrap_or_default();
+            let key = url::form_urlencoded::parse(key.as_bytes())
+                .next()
+                .map(|(key, _)| key.into_owned())
+                .unwrap_or_default();
+            keep(&key)
+        })
+        .collect();
+    // 지울 파라미터가 없으면(빈 `?` 포함) 주소를 건드리지 않습니다.
+    if kept.len() == parts.len() {
+        return false;
+    }
+    let rebuilt = kept.join("&");
+    url.set_query((!rebuilt.is_empty()).then_some(rebuilt.as_str()));
+    true
+}
 
-    let mut links = Vec::new();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkConversion {
+    /// 메시지에 적힌 그대로의 원본 (링크 표시 `.`/`^` 포함).
+    pub original: String,
+    /// 본문에서 `original`이 차지하는 바이트 범위.
+    pub span: Range<usize>,
+    pub converted: String,
+    pub preview: LinkPreviewOptions,
+    /// 적용한 규칙 이름.
+    pub rule: String,
+    pub delivery: Delivery,
+}
 
-    for pattern in patterns {
-        if let Ok(re) = Regex::new(pattern) {
-            for cap in re.captures_iter(text) {
-                if let Some(m) = cap.get(1) {
-                    let original_url = m.as_str();
-                    let cleaned_url = remove_si_parameter(original_url);
+/// 규칙 표를 메시지에 적용하는 변환기. 규칙은 앞에 있는 것이 먼저 적용됩니다.
+/// 규칙에 맞지 않는 링크도 추적 파라미터는 지웁니다.
+#[derive(Debug, Clone)]
+pub struct LinkRewriter {
+    rules: Vec<RewriteRule>,
+    tracking: TrackingRules,
+    resolver: Option<ShortLinkResolver>,
+}
 
-                    if original_url != cleaned_url {
-                        links.push((original_url.to_string(), cleaned_url));
-                    }
-                }
-            }
-        }
+impl Default for LinkRewriter {
+    fn default() -> Self {
+        Self::from_json(DEFAULT_RULES).expect("bundled link rules should be valid")
     }
+}
 
-    links
-}
-
-pub fn convert_x_links(text: &str) -> Vec<LinkConversion> {
-    // capture optional dot prefix to allow opt-out of previews (e.g., ".https://x.com/...")
-    let pattern = r"(\.?)(https?://(?:www\.)?(?:x|twitter)\.com/\S+)";
-    let mut links = Vec::new();
-
-    if let Ok(re) = Regex::new(pattern) {
-        for cap in re.captures_iter(text) {
-            let dot_prefix = cap.get(1).map(|m| m.as_str() == ".").unwrap_or(false);
-            if let Some(url_match) = cap.get(2) {
-                let original_url = url_match.as_str();
-                match Url::parse(original_url) {
-                    Ok(mut parsed) => {
-                        parsed.set_host(Some("fxtwitter.com")).ok();
-                        parsed.set_query(None);
-                        parsed.set_fragment(None);
-                        let original_in_text = if dot_prefix {
-                            format!(".{}", original_url)
-                        } else {
-                            original_url.to_string()
-                        };
-                        links.push(LinkConversion {
-                            original: original_in_text,
-                            converted: parsed.to_string(),
-                            disable_preview: dot_prefix,
-                        });
-                    }
-                    Err(e) => warn!("X 링크 파싱 실패: {}", e),
-                }
+impl LinkRewriter {
+    pub fn from_json(raw: &str) -> Result<Self> {
+        let configs: Vec<RuleConfig> =
+            serde_json::from_str(raw).context("링크 규칙 형식이 올바르지 않습니다")?;
+        let rules = configs
+            .into_iter()
+            .map(RewriteRule::compile)
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            rules,
+            tracking: TrackingRules::default(),
+            resolver: None,
+        })
+    }
+
+    pub fn with_tracking(mut self, tracking: TrackingRules) -> Self {
+        self.tracking = tracking;
+        self
+    }
+
+    pub fn with_resolver(mut self, resolver: Option<ShortLinkResolver>) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// 규칙의 대상 호스트를 바꿉니다. (`PLANABOT_LINK_HOST_<규칙 이름>`)
+    pub fn with_target_host(mut self, rule: &str, host: &str) -> Result<Self> {
+        let host = host.trim().to_lowercase();
+        validate_host(rule, &host)?;
+        let target = self
+            .rules
+            .iter_mut()
+            .find(|candidate| candidate.name.eq_ignore_ascii_case(rule))
+            .ok_or_else(|| anyhow!("{} 규칙이 없습니다", rule))?;
+        target.replace_host = Some(host);
+        Ok(self)
+    }
+
+    /// `PLANABOT_LINK_RULES_PATH`가 있으면 그 파일을, 없으면 기본 규칙을 씁니다.
+    /// 규칙마다 `PLANABOT_LINK_HOST_<규칙 이름>`으로 대상 호스트만 바꿀 수도 있습니다.
+    /// 추적 파라미터 목록은 `PLANABOT_TRACKING_RULES_PATH`에서 읽고,
+    /// `PLANABOT_SHORTLINK_RESOLVE`가 켜져 있으면 단축 링크를 따라갑니다.
+    /// 규칙이 잘못되었으면 시작하지 않도록 오류를 돌려줍니다.
+    pub fn from_env() -> Result<Self> {
+        let mut rewriter = match std::env::var("PLANABOT_LINK_RULES_PATH") {
+            Ok(path) if !path.trim().is_empty() => Self::from_file(Path::new(path.trim()))?,
+            _ => Self::default(),
+        };
+        let names: Vec<String> = rewriter
+            .rules
+            .iter()
+            .map(|rule| rule.name.clone())
+            .collect();
+        for name in names {
+            let key = format!("PLANABOT_LINK_HOST_{}", name.to_uppercase());
+            if let Ok(host) = std::env::var(&key)
+                && !host.trim().is_empty()
+            {
+                rewriter = rewriter.with_target_host(&name, &host)?;
             }
         }
+        Ok(rewriter
+            .with_tracking(TrackingRules::from_env()?)
+            .with_resolver(ResolverConfig::from_env().map(ShortLinkResolver::new)))
     }
 
-    links
-}
+    fn from_file(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("링크 규칙 파일을 읽지 못했습니다: {}", path.display()))?;
+        Self::from_json(&raw).with_context(|| format!("링크 규칙 파일 오류: {}", path.display()))
+    }
+
+    /// 메시지의 모든 링크에 규칙을 적용합니다. 바뀐 링크만 본문 순서대로 돌려줍니다.
+    pub fn rewrite(&self, text: &str) -> Vec<LinkConversion> {
+        self.rewrite_with(text, &HashMap::new())
+    }
 
-pub fn convert_instagram_links(text: &str) -> Vec<(String, String)> {
-    let pattern = r"(https?://(?:www\.)?instagram\.com/\S+)";
-    let mut links = Vec::new();
+    /// 단축 링크를 먼저 실제 주소로 바꾼 뒤 `rewrite`와 같은 규칙을 적용합니다.
+    /// 단축 링크 확인이 꺼져 있으면 `rewrite`와 같습니다.
+    pub async fn rewrite_expanded(&self, text: &str) -> Vec<LinkConversion> {
+        let Some(resolver) = &self.resolver else {
+            return self.rewrite(text);
+        };
 
-    if let Ok(re) = Regex::new(pattern) {
-        for cap in re.captures_iter(text) {
-            if let Some(m) = cap.get(1) {
-                let original_url = m.as_str();
-                match Url::parse(original_url) {
-                    Ok(mut parsed) => {
-                        parsed.set_host(Some("www.kkinstagram.com")).ok();
-                        parsed.set_query(None);
-                        parsed.set_fragment(None);
-                        links.push((original_url.to_string(), parsed.to_string()));
+        let mut pending = Vec::new();
+        for link in parse_links(text) {
+            if link.marker == LinkMarker::Keep {
+                continue;
+            }
+            let link = link.url;
+            let Ok(url) = Url::parse(link) else { continue };
+            if !resolver.is_short_link(&url) || pending.iter().any(|(seen, _)| seen == link) {
+                continue;
+            }
+            let resolver = resolver.clone();
+            let task = tokio::spawn(async move { resolver.resolve(&url).await });
+            pending.push((link.to_string(), task));
+        }
+
+        let mut expanded = HashMap::new();
+        for (link, task) in pending {
+            if let Ok(Some(target)) = task.await {
+                expanded.insert(link, target);
+            }
+        }
+        self.rewrite_with(text, &expanded)
+    }
+
+    fn rewrite_with(&self, text: &str, expanded: &HashMap<String, Url>) -> Vec<LinkConversion> {
+        let mut conversions = Vec::new();
+        for link in parse_links(text) {
+            if link.marker == LinkMarker::Keep {
+                continue;
+            }
+            let original = link.url;
+            let resolved = expanded.get(original);
+            let mut url = match resolved {
+                Some(target) => target.clone(),
+                None => match Url::parse(original) {
+                    Ok(url) => url,
+                    Err(err) => {
+                        warn!("링크 파싱 실패 ({}): {}", original, err);
+                        continue;
                     }
-                    Err(e) => warn!("Instagram 링크 파싱 실패: {}", e),
-                }
+                },
+            };
+            let cleaned = self.tracking.clean(&mut url);
+            let rule = self.rules.iter().find(|rule| rule.matches(&url));
+            if rule.is_none() && !cleaned && resolved.is_none() {
+                continue;
             }
+
+            let converted = match rule {
+                Some(rule) => rule.apply(url).to_string(),
+                None => url.to_string(),
+            };
+            if converted == original {
+                continue;
+            }
+            let hidden = rule.is_some_and(|rule| rule.preview == PreviewPolicy::Hide);
+            let above = link.marker == LinkMarker::PreviewAbove;
+            let preview = LinkPreviewOptions {
+                is_disabled: link.marker == LinkMarker::NoPreview || (hidden && !above),
+                url: above.then(|| converted.clone()),
+                prefer_small_media: false,
+                prefer_large_media: above,
+                show_above_text: above,
+            };
+            conversions.push(LinkConversion {
+                original: link.raw.to_string(),
+                span: link.start..link.start + link.raw.len(),
+                converted,
+                preview,
+                rule: rule.map_or_else(|| TRACKING_RULE.to_string(), |rule| rule.name.clone()),
+                delivery: rule.map_or(Delivery::Buttons, |rule| rule.delivery),
+            });
         }
+        conversions
+    }
+}
+
+/// 변환된 메시지 전체의 미리보기 설정. `^` 표시한 링크가 있으면 그 링크를 위에 보여주고,
+/// 일부 링크만 미리보기를 끈 경우에는 끄지 않은 첫 링크를 미리보기로 고릅니다.
+pub fn message_preview(links: &[LinkConversion]) -> LinkPreviewOptions {
+    if let Some(link) = links.iter().find(|link| link.preview.show_above_text) {
+        return link.preview.clone();
+    }
+    let shown: Vec<&LinkConversion> = links
+        .iter()
+        .filter(|link| !link.preview.is_disabled)
+        .collect();
+    LinkPreviewOptions {
+        is_disabled: shown.is_empty(),
+        url: match shown.first() {
+            Some(first) if shown.len() < links.len() => Some(first.converted.clone()),
+            _ => None,
+        },
+        prefer_small_media: false,
+        prefer_large_media: false,
+        show_above_text: false,
     }
+}
 
-    links
+/// 원본 링크를 변환된 링크로 바꾼 본문. `conversions`는 `text`에서 만든 것이어야 합니다.
+pub fn apply_conversions(text: &str, conversions: &[LinkConversion]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for link in conversions {
+        result.push_str(&text[cursor..link.span.start]);
+        result.push_str(&link.converted);
+        cursor = link.span.end;
+    }
+    result.push_str(&text[cursor..]);
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn convert(text: &str) -> Vec<LinkConversion> {
+        LinkRewriter::default().rewrite(text)
+    }
+
     #[test]
-    fn test_remove_si_parameter_youtube() {
-        let original = "https://youtu.be/Vc-ByDGOuQE?si=qIy-ihfrRKmDAPZP";
-        let expected = "https://youtu.be/Vc-ByDGOuQE";
-        assert_eq!(remove_si_parameter(original), expected);
+    fn test_strips_si_from_music_links() {
+        let cases = [
+            (
+                "https://youtu.be/Vc-ByDGOuQE?si=qIy-ihfrRKmDAPZP",
+                "https://youtu.be/Vc-ByDGOuQE",
+            ),
+            (
+                "https://music.youtube.com/watch?v=nmYDYalgb5w&si=GGi18ac_fxnx4F1b",
+                "https://music.youtube.com/watch?v=nmYDYalgb5w",
+            ),
+            (
+                "https://open.spotify.com/track/1FYWnRofuIgJf62AnX8i5S?si=bf00147df50f4141",
+                "https://open.spotify.com/track/1FYWnRofuIgJf62AnX8i5S",
+            ),
+            (
+                "https://music.youtube.com/watch?v=nmYDYalgb5w&si=GGi18ac_fxnx4F1b&list=RDAMVMnmYDYalgb5w",
+                "https://music.youtube.com/watch?v=nmYDYalgb5w&list=RDAMVMnmYDYalgb5w",
+            ),
+        ];
+        for (original, expected) in cases {
+            let links = convert(original);
+            assert_eq!(links.len(), 1, "{original}");
+            assert_eq!(links[0].converted, expected);
+            assert_eq!(links[0].delivery, Delivery::Buttons);
+        }
+        assert!(convert("https://youtu.be/abc").is_empty());
     }
 
     #[test]
-    fn test_remove_si_parameter_youtube_music() {
-        let original = "https://music.youtube.com/watch?v=nmYDYalgb5w&si=GGi18ac_fxnx4F1b";
-        let expected = "https://music.youtube.com/watch?v=nmYDYalgb5w";
-        assert_eq!(remove_si_parameter(original), expected);
+    fn test_cleans_tracking_params_on_unruled_sites() {
+        let links = convert(
+            "https://shop.example/item/3?utm_source=newsletter&color=red https://example.com/a",
+        );
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].converted, "https://shop.example/item/3?color=red");
+        assert_eq!(links[0].rule, "tracking");
+        assert_eq!(links[0].delivery, Delivery::Buttons);
     }
 
     #[test]
-    fn test_remove_si_parameter_spotify() {
-        let original = "https://open.spotify.com/track/1FYWnRofuIgJf62AnX8i5S?si=bf00147df50f4141";
-        let expected = "https://open.spotify.com/track/1FYWnRofuIgJf62AnX8i5S";
-        assert_eq!(remove_si_parameter(original), expected);
+    fn test_rewrites_x_host_and_strips_query() {
+        let links = convert("https://x.com/lettuce9094/status/1997610286262718819?s=20");
+        assert_eq!(links.len(), 1);
+        assert_eq!(
+            links[0].converted,
+            "https://fxtwitter.com/lettuce9094/status/1997610286262718819"
+        );
+        assert!(!links[0].preview.is_disabled);
+        assert_eq!(links[0].rule, "x");
+        assert_eq!(links[0].delivery, Delivery::Text);
     }
 
     #[test]
-    fn test_remove_si_parameter_with_multiple_params() {
-        let original = "https://music.youtube.com/watch?v=nmYDYalgb5w&si=GGi18ac_fxnx4F1b&list=RDAMVMnmYDYalgb5w";
-        let expected = "https://music.youtube.com/watch?v=nmYDYalgb5w&list=RDAMVMnmYDYalgb5w";
-        assert_eq!(remove_si_parameter(original), expected);
+    fn test_link_markers_apply_to_every_rule() {
+        let links = convert(
+            ".https://x.com/user/status/12345?s=99 .https://youtu.be/a?si=b !https://youtu.be/c?si=d",
+        );
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].original, ".https://x.com/user/status/12345?s=99");
+        assert_eq!(
+            links[0].converted,
+            "https://fxtwitter.com/user/status/12345"
+        );
+        assert!(links[0].preview.is_disabled);
+        assert_eq!(links[1].original, ".https://youtu.be/a?si=b");
+        assert!(links[1].preview.is_disabled);
+        assert!(message_preview(&links).is_disabled);
     }
 
     #[test]
-    fn test_convert_x_links_rewrites_host_and_strips_query() {
-        let text = "https://x.com/lettuce9094/status/1997610286262718819?s=20";
-        let pairs = convert_x_links(text);
-        assert_eq!(pairs.len(), 1);
+    fn test_message_preview_prefers_marked_or_shown_links() {
+        let links = convert("https://youtu.be/a?si=b ^https://x.com/u/status/1?s=2");
+        assert_eq!(links[1].original, "^https://x.com/u/status/1?s=2");
+        let preview = message_preview(&links);
         assert_eq!(
-            pairs[0].converted,
-            "https://fxtwitter.com/lettuce9094/status/1997610286262718819"
+            preview.url.as_deref(),
+            Some("https://fxtwitter.com/u/status/1")
         );
-        assert!(!pairs[0].disable_preview);
+        assert!(preview.show_above_text && preview.prefer_large_media);
+
+        let links = convert(".https://youtu.be/a?si=b https://x.com/u/status/1?s=2");
+        let preview = message_preview(&links);
+        assert!(!preview.is_disabled);
         assert_eq!(
-            pairs[0].original,
-            "https://x.com/lettuce9094/status/1997610286262718819?s=20"
+            preview.url.as_deref(),
+            Some("https://fxtwitter.com/u/status/1")
         );
+
+        let links = convert("https://youtu.be/a?si=b https://x.com/u/status/1?s=2");
+        assert_eq!(message_preview(&links).url, None);
     }
 
     #[test]
-    fn test_convert_x_links_with_dot_prefix_disables_preview_and_strips_dot() {
-        let text = ".https://x.com/user/status/12345?s=99";
-        let pairs = convert_x_links(text);
-        assert_eq!(pairs.len(), 1);
-        assert!(pairs[0].disable_preview);
+    fn test_rewrites_instagram_host_and_strips_query() {
+        let links = convert(
+            "https://www.instagram.com/p/DR_uVJVklbf/?utm_source=ig_web_copy_link&igsh=Nm9hazRuaXNrdGo1",
+        );
         assert_eq!(
-            pairs[0].converted,
-            "https://fxtwitter.com/user/status/12345"
+            links[0].converted,
+            "https://www.kkinstagram.com/p/DR_uVJVklbf/"
         );
-        assert_eq!(pairs[0].original, ".https://x.com/user/status/12345?s=99");
     }
 
     #[test]
-    fn test_convert_instagram_links_rewrites_host_and_strips_query() {
-        let text = "https://www.instagram.com/p/DR_uVJVklbf/?utm_source=ig_web_copy_link&igsh=Nm9hazRuaXNrdGo1";
-        let pairs = convert_instagram_links(text);
-        assert_eq!(pairs.len(), 1);
-        assert_eq!(pairs[0].1, "https://www.kkinstagram.com/p/DR_uVJVklbf/");
+    fn test_rewrites_embed_hosts_for_more_platforms() {
+        let cases = [
+            (
+                "https://www.tiktok.com/@user/video/7301234567890123456?is_from_webapp=1&sender_device=pc",
+                "https://www.vxtiktok.com/@user/video/7301234567890123456",
+            ),
+            (
+                "https://vm.tiktok.com/ZMabc123/",
+                "https://vm.vxtiktok.com/ZMabc123/",
+            ),
+            (
+                "https://www.reddit.com/r/rust/comments/abc123/title/?share_id=x",
+                "https://rxddit.com/r/rust/comments/abc123/title/",
+            ),
+            (
+                "https://bsky.app/profile/user.bsky.social/post/3kabc",
+                "https://bskx.app/profile/user.bsky.social/post/3kabc",
+            ),
+            (
+                "https://www.pixiv.net/en/artworks/12345678",
+                "https://www.phixiv.net/en/artworks/12345678",
+            ),
+            (
+                "https://www.threads.net/@user/post/C1abc?xmt=AQ",
+                "https://www.fixthreads.net/@user/post/C1abc",
+            ),
+        ];
+        for (original, expected) in cases {
+            let links = convert(original);
+            assert_eq!(links.len(), 1, "{original}");
+            assert_eq!(links[0].converted, expected);
+            assert_eq!(links[0].delivery, Delivery::Text);
+        }
+    }
+
+    #[test]
+    fn test_target_host_can_be_overridden() {
+        let rewriter = LinkRewriter::default()
+            .with_target_host("TikTok", "tnktok.com")
+            .unwrap();
+        assert_eq!(
+            rewriter.rewrite("https://www.tiktok.com/@a/video/1")[0].converted,
+            "https://tnktok.com/@a/video/1"
+        );
+        assert!(
+            LinkRewriter::default()
+                .with_target_host("tiktok", "bad host/")
+                .is_err()
+        );
+        assert!(
+            LinkRewriter::default()
+                .with_target_host("nope", "a.com")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_custom_rules_keep_params_and_rewrite_paths() {
+        let rewriter = LinkRewriter::from_json(
+            r#"[{"name":"example","hosts":["example.com"],"replace_host":"mirror.example",
+                "keep_params":["id"],"path_rewrites":[{"pattern":"^/old/","replace":"/new/"}]}]"#,
+        )
+        .unwrap();
+        let links = rewriter.rewrite("보세요 https://www.example.com/old/page?id=1&ref=x");
+        assert_eq!(links[0].converted, "https://mirror.example/new/page?id=1");
+        assert!(rewriter.rewrite("https://notexample.com/old/").is_empty());
+
+        assert!(LinkRewriter::from_json(r#"[{"name":"bad","hosts":[]}]"#).is_err());
+        assert!(
+            LinkRewriter::from_json(
+                r#"[{"name":"bad","hosts":["a.com"],"path_rewrites":[{"pattern":"(","replace":""}]}]"#
+            )
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_expanded_cleans_the_final_url() {
+        use crate::test_support::{Redirects, mock_server_with_redirects};
+        use std::sync::{Arc, Mutex};
+
+        let redirects: Redirects = Arc::new(Mutex::new(HashMap::from([(
+            "/s".to_string(),
+            "https://x.com/a/status/1?s=20&utm_source=share".to_string(),
+        )])));
+        let base = mock_server_with_redirects(Default::default(), redirects);
+        let host = Url::parse(&base).unwrap().host_str().unwrap().to_string();
+        let rewriter =
+            LinkRewriter::default().with_resolver(Some(ShortLinkResolver::new(ResolverConfig {
+                hosts: vec![host],
+                block_private: false,
+                ..ResolverConfig::default()
+            })));
+
+        let text = format!("{}/s 보세요", base);
+        let links = rewriter.rewrite_expanded(&text).await;
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].original, format!("{}/s", base));
+        assert_eq!(links[0].converted, "https://fxtwitter.com/a/status/1");
+        assert_eq!(links[0].delivery, Delivery::Text);
+        assert!(rewriter.rewrite(&text).is_empty());
+    }
+
+    #[test]
+    fn test_apply_conversions_leaves_kept_duplicates_alone() {
+        let text = "!https://youtu.be/a?si=b https://youtu.be/a?si=b";
+        assert_eq!(
+            apply_conversions(text, &convert(text)),
+            "!https://youtu.be/a?si=b https://youtu.be/a"
+        );
+    }
+
+    #[test]
+    fn test_apply_conversions_replaces_every_link() {
+        let text = "https://youtu.be/abc?si=xyz 그리고 https://x.com/a/status/1?s=20";
+        assert_eq!(
+            apply_conversions(text, &convert(text)),
+            "https://youtu.be/abc 그리고 https://fxtwitter.com/a/status/1"
+        );
     }
 }
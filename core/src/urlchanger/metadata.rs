@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::debug;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use url::Url;
+
+use super::link_utils::host_matches;
+use super::resolver::{ensure_public_target, http_client, redirect_target};
+
+const CACHE_CAPACITY: usize = 500;
+const MAX_REDIRECTS: usize = 3;
+
+/// 링크 카드에 보여줄 정보.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkMetadata {
+    pub title: String,
+    /// 채널, 아티스트 또는 사이트 이름.
+    pub author: Option<String>,
+    pub duration_secs: Option<u64>,
+}
+
+impl LinkMetadata {
+    /// `제목 — 채널 (3:21)`
+    pub fn summary(&self) -> String {
+        let mut text = self.title.clone();
+        if let Some(author) = &self.author {
+            text.push_str(&format!(" — {}", author));
+        }
+        if let Some(secs) = self.duration_secs {
+            text.push_str(&format!(" ({})", format_duration(secs)));
+        }
+        text
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MetadataConfig {
+    pub timeout: Duration,
+    pub max_body_bytes: usize,
+    pub cache_ttl: Duration,
+    /// 정보를 얻지 못한 링크를 다시 조회하지 않는 시간. 일시적인 시간 초과가 오래 남지 않도록 짧게 둡니다.
+    pub failure_ttl: Duration,
+    pub youtube_oembed_url: String,
+    pub spotify_oembed_url: String,
+    /// 사설·루프백 주소의 페이지는 읽지 않습니다. 테스트에서만 끕니다.
+    pub block_private: bool,
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(4),
+            max_body_bytes: 512 * 1024,
+            cache_ttl: Duration::from_secs(6 * 60 * 60),
+            failure_ttl: Duration::from_secs(10 * 60),
+            youtube_oembed_url: "https://www.youtube.com/oembed".to_string(),
+            spotify_oembed_url: "https://open.spotify.com/oembed".to_string(),
+            block_private: true,
+        }
+    }
+}
+
+impl MetadataConfig {
+    /// `PLANABOT_LINK_CARDS`가 켜져 있을 때만 설정을 돌려줍니다.
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("PLANABOT_LINK_CARDS")
+            .map(|raw| raw == "1" || raw.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+        let mut config = Self::default();
+        if let Some(secs) = std::env::var("PLANABOT_LINK_CARDS_TIMEOUT_SECS")
+            .ok()
+            .and_then(|raw| raw.trim().parse::<u64>().ok())
+        {
+            config.timeout = Duration::from_secs(secs.max(1));
+        }
+        Some(config)
+    }
+}
+
+/// 조회 시각과 결과. 실패(`None`)는 `failure_ttl` 동안만 기억합니다.
+type CachedMetadata = (Instant, Option<LinkMetadata>);
+
+#[derive(Debug, Deserialize)]
+struct OEmbed {
+    title: Option<String>,
+    author_name: Option<String>,
+}
+
+/// oEmbed(YouTube, Spotify)와 OpenGraph로 링크 정보를 가져옵니다. 실패한 결과는 잠깐만 캐시합니다.
+#[derive(Debug, Clone)]
+pub struct MetadataFetcher {
+    client: Client,
+    config: MetadataConfig,
+    cache: Arc<Mutex<HashMap<String, CachedMetadata>>>,
+}
+
+impl MetadataFetcher {
+    pub fn new(config: MetadataConfig) -> Self {
+        let client = http_client(config.timeout, config.block_private);
+        Self {
+            client,
+            config,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub async fn fetch(&self, url: &str) -> Option<LinkMetadata> {
+        if let Some(cached) = self.cached(url) {
+            return cached;
+        }
+        let parsed = Url::parse(url).ok()?;
+        let metadata = tokio::time::timeout(self.config.timeout, self.lookup(&parsed))
+            .await
+            .unwrap_or(None);
+        self.store(url, metadata.clone());
+        metadata
+    }
+
+    /// 여러 링크를 함께 조회합니다. 결과는 `urls`와 같은 순서입니다.
+    pub async fn fetch_all(&self, urls: &[String]) -> Vec<Option<LinkMetadata>> {
+        let tasks: Vec<_> = urls
+            .iter()
+            .map(|url| {
+                let fetcher = self.clone();
+                let url = url.clone();
+                tokio::spawn(async move { fetcher.fetch(&url).await })
+            })
+            .collect();
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            results.push(task.await.ok().flatten());
+        }
+        results
+    }
+
+    async fn lookup(&self, url: &Url) -> Option<LinkMetadata> {
+        let oembed = match oembed_endpoint(url, &self.config) {
+            Some(endpoint) => self.oembed(endpoint, url).await,
+            None => None,
+        };
+        // oEmbed가 제목과 채널을 모두 주면 페이지는 받지 않습니다.
+        let complete = oembed
+            .as_ref()
+            .is_some_and(|o| o.title.is_some() && o.author_name.is_some());
+        let page = if complete {
+            None
+        } else {
+            self.open_graph(url).await
+        };
+
+        let title = oembed
+            .as_ref()
+            .and_then(|o| o.title.clone())
+            .or_else(|| page.as_ref().map(|p| p.title.clone()))
+            .filter(|title| !title.trim().is_empty())?;
+        Some(LinkMetadata {
+            title,
+            author: oembed
+                .and_then(|o| o.author_name)
+                .or_else(|| page.as_ref().and_then(|p| p.author.clone())),
+            duration_secs: page.and_then(|p| p.duration_secs),
+        })
+    }
+
+    async fn oembed(&self, endpoint: &str, url: &Url) -> Option<OEmbed> {
+        let response = self
+            .client
+            .get(endpoint)
+            .query(&[("url", url.as_str()), ("format", "json")])
+            .send()
+            .await
+            .ok()?;
+        if response.status() != StatusCode::OK {
+            debug!("oEmbed 응답 오류 ({}): {}", url, response.status());
+            return None;
+        }
+        let body = read_capped(response, self.config.max_body_bytes).await?;
+        serde_json::from_slice(&body).ok()
+    }
+
+    async fn open_graph(&self, url: &Url) -> Option<LinkMetadata> {
+        let mut url = url.clone();
+        for _ in 0..=MAX_REDIRECTS {
+            ensure_public_target(&url, self.config.block_private).ok()?;
+            let response = self.client.get(url.clone()).send().await.ok()?;
+            if let Some(next) = redirect_target(&url, &response) {
+                url = next;
+                continue;
+            }
+            if response.status() != StatusCode::OK || !is_html(&response) {
+                return None;
+            }
+            let body = read_capped(response, self.config.max_body_bytes).await?;
+            return parse_open_graph(&String::from_utf8_lossy(&body));
+        }
+        None
+    }
+
+    fn cached(&self, url: &str) -> Option<Option<LinkMetadata>> {
+        let cache = self.cache.lock().ok()?;
+        let (at, metadata) = cache.get(url)?;
+        (at.elapsed() < self.ttl(metadata)).then(|| metadata.clone())
+    }
+
+    fn store(&self, url: &str, metadata: Option<LinkMetadata>) {
+        let Ok(mut cache) = self.cache.lock() else {
+            return;
+        };
+        if cache.len() >= CACHE_CAPACITY {
+            cache.retain(|_, (at, metadata)| at.elapsed() < self.ttl(metadata));
+            if cache.len() >= CACHE_CAPACITY {
+                cache.clear();
+            }
+        }
+        cache.insert(url.to_string(), (Instant::now(), metadata));
+    }
+
+    fn ttl(&self, metadata: &Option<LinkMetadata>) -> Duration {
+        if metadata.is_some() {
+            self.config.cache_ttl
+        } else {
+            self.config.failure_ttl
+        }
+    }
+}
+
+fn oembed_endpoint<'a>(url: &Url, config: &'a MetadataConfig) -> Option<&'a str> {
+    static YOUTUBE: Lazy<Vec<String>> =
+        Lazy::new(|| vec!["youtube.com".to_string(), "youtu.be".to_string()]);
+    static SPOTIFY: Lazy<Vec<String>> = Lazy::new(|| vec!["open.spotify.com".to_string()]);
+    if host_matches(url, &YOUTUBE) {
+        Some(&config.youtube_oembed_url)
+    } else if host_matches(url, &SPOTIFY) {
+        Some(&config.spotify_oembed_url)
+    } else {
+        None
+    }
+}
+
+fn is_html(response: &reqwest::Response) -> bool {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(|value| value.contains("html"))
+}
+
+async fn read_capped(mut response: reqwest::Response, max_bytes: usize) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    while body.len() < max_bytes {
+        match response.chunk().await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+            Ok(None) => break,
+            Err(_) => return None,
+        }
+    }
+    body.truncate(max_bytes);
+    Some(body)
+}
+
+/// `<meta property|name|itemprop=… content=…>`와 `<title>`에서 정보를 읽습니다.
+fn parse_open_graph(html: &str) -> Option<LinkMetadata> {
+    static META: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<meta\s[^>]*>").unwrap());
+    static ATTR: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"(?i)([a-z][a-z:-]*)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap());
+    static TITLE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+
+    let mut tags: HashMap<String, String> = HashMap::new();
+    for tag in META.find_iter(html) {
+        let mut key = None;
+        let mut content = None;
+        for attr in ATTR.captures_iter(tag.as_str()) {
+            let value = attr
+                .get(2)
+                .or_else(|| attr.get(3))
+                .map_or("", |m| m.as_str());
+            match attr[1].to_lowercase().as_str() {
+                "property" | "name" | "itemprop" => key = Some(value.to_lowercase()),
+                "content" => content = Some(decode_entities(value)),
+                _ => {}
+            }
+        }
+        if let (Some(key), Some(content)) = (key, content) {
+            tags.entry(key).or_insert(content);
+        }
+    }
+
+    let get = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| tags.get(*key))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let title = get(&["og:title", "twitter:title"]).or_else(|| {
+        TITLE
+            .captures(html)
+            .map(|cap| decode_entities(cap[1].trim()))
+            .filter(|title| !title.is_empty())
+    })?;
+    let duration_secs = get(&["music:duration", "video:duration", "og:video:duration"])
+        .and_then(|raw| raw.parse::<u64>().ok())
+        .or_else(|| get(&["duration"]).and_then(|raw| parse_iso_duration(&raw)));
+    Some(LinkMetadata {
+        title,
+        author: get(&[
+            "music:musician_description",
+            "og:video:author",
+            "author",
+            "og:site_name",
+        ]),
+        duration_secs,
+    })
+}
+
+fn decode_entities(raw: &str) -> String {
+    raw.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// `PT1H2M3S` 같은 ISO 8601 기간.
+fn parse_iso_duration(raw: &str) -> Option<u64> {
+    static ISO: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^PT(?:(\d+)H)?(?:(\d+)M)?(?:(\d+)S)?$").unwrap());
+    let cap = ISO.captures(raw.trim())?;
+    let part = |index: usize| {
+        cap.get(index)
+            .map_or(Some(0), |m| m.as_str().parse::<u64>().ok())
+    };
+    Some(part(1)? * 3600 + part(2)? * 60 + part(3)?)
+}
+
+fn format_duration(secs: u64) -> String {
+    let (h, m, s) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Routes, mock_server};
+
+    fn fetcher(base: &str) -> MetadataFetcher {
+        MetadataFetcher::new(MetadataConfig {
+            youtube_oembed_url: format!("{}/oembed", base),
+            spotify_oembed_url: format!("{}/spotify-oembed", base),
+            block_private: false,
+            ..MetadataConfig::default()
+        })
+    }
+
+    fn server(routes: &[(&str, &str)]) -> (String, Routes) {
+        let routes: Routes = Arc::new(Mutex::new(
+            routes
+                .iter()
+                .map(|(path, body)| (path.to_string(), body.as_bytes().to_vec()))
+                .collect(),
+        ));
+        (mock_server(routes.clone()), routes)
+    }
+
+    #[test]
+    fn test_parse_open_graph_reads_title_author_and_duration() {
+        let html = r#"<html><head><title>fallback</title>
+            <meta property="og:title" content="Song &amp; Dance">
+            <meta name="music:musician_description" content="Artist">
+            <meta name="music:duration" content="215">
+            </head></html>"#;
+        let metadata = parse_open_graph(html).unwrap();
+        assert_eq!(metadata.summary(), "Song & Dance — Artist (3:35)");
+
+        let html = r#"<title>Only title</title><meta itemprop="duration" content="PT1H2M3S">"#;
+        let metadata = parse_open_graph(html).unwrap();
+        assert_eq!(metadata.summary(), "Only title (1:02:03)");
+        assert!(parse_open_graph("<html></html>").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fetches_oembed_for_youtube_and_caches() {
+        let (base, routes) =
+            server(&[("/oembed", r#"{"title": "Video", "author_name": "Channel"}"#)]);
+        let fetcher = fetcher(&base);
+        let url = "https://www.youtube.com/watch?v=abc";
+        let metadata = fetcher.fetch(url).await.unwrap();
+        assert_eq!(metadata.title, "Video");
+        assert_eq!(metadata.author.as_deref(), Some("Channel"));
+
+        routes.lock().unwrap().clear();
+        assert_eq!(fetcher.fetch(url).await, Some(metadata));
+    }
+
+    #[tokio::test]
+    async fn test_fetches_open_graph_for_other_pages() {
+        let (base, _) = server(&[(
+            "/page",
+            r#"<meta property="og:title" content="Page"><meta property="og:site_name" content="Site">"#,
+        )]);
+        let fetcher = fetcher(&base);
+        let metadata = fetcher
+            .fetch_all(&[format!("{}/page", base), format!("{}/missing", base)])
+            .await;
+        assert_eq!(metadata[0].as_ref().unwrap().summary(), "Page — Site");
+        assert!(metadata[1].is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_cached_only_for_failure_ttl() {
+        let (base, routes) = server(&[]);
+        let url = format!("{}/page", base);
+
+        let fetcher = fetcher(&base);
+        assert!(fetcher.fetch(&url).await.is_none());
+        routes.lock().unwrap().insert(
+            "/page".to_string(),
+            br#"<meta property="og:title" content="Page">"#.to_vec(),
+        );
+        assert!(fetcher.fetch(&url).await.is_none());
+
+        let fetcher = MetadataFetcher::new(MetadataConfig {
+            failure_ttl: Duration::ZERO,
+            ..fetcher.config.clone()
+        });
+        routes.lock().unwrap().remove("/page");
+        assert!(fetcher.fetch(&url).await.is_none());
+        routes.lock().unwrap().insert(
+            "/page".to_string(),
+            br#"<meta property="og:title" content="Page">"#.to_vec(),
+        );
+        assert_eq!(fetcher.fetch(&url).await.unwrap().title, "Page");
+    }
+
+    #[tokio::test]
+    async fn test_private_pages_are_not_fetched_by_default() {
+        let (base, _) = server(&[("/page", r#"<meta property="og:title" content="Page">"#)]);
+        let fetcher = MetadataFetcher::new(MetadataConfig::default());
+        assert!(fetcher.fetch(&format!("{}/page", base)).await.is_none());
+    }
+}
@@ -1,4 +1,13 @@
 mod handlers;
 mod link_utils;
+mod metadata;
+mod repost;
+mod resolver;
+mod syntax;
+mod tracking;
+mod undo;
 
-pub use handlers::url_handlers;
+pub use handlers::{handle_undo_callback, is_undo_callback, url_handlers};
+pub use link_utils::{LinkRewriter, apply_conversions, message_preview};
+pub use metadata::{MetadataConfig, MetadataFetcher};
+pub use undo::UndoStore;
@@ -0,0 +1,184 @@
+//! 관리자 권한으로 원본을 지우고 다시 올릴 메시지를 만듭니다.
+//! 서식(엔티티)의 위치는 Telegram 규칙대로 UTF-16 단위로 계산합니다.
+
+use teloxide::types::{Message, MessageEntity, MessageEntityKind, MessageId};
+
+use super::link_utils::{LinkConversion, apply_conversions};
+
+/// 다시 올릴 본문과 서식.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repost {
+    pub text: String,
+    pub entities: Vec<MessageEntity>,
+}
+
+/// 본문 또는 캡션. 링크 변환과 다시 올리기는 모두 이 문자열을 기준으로 합니다.
+pub fn message_text(msg: &Message) -> Option<&str> {
+    msg.text().or_else(|| msg.caption())
+}
+
+fn message_entities(msg: &Message) -> &[MessageEntity] {
+    msg.entities()
+        .or_else(|| msg.caption_entities())
+        .unwrap_or_default()
+}
+
+/// 원본이 답장이었다면 그 대상. 포럼 토픽의 첫 메시지는 답장으로 치지 않습니다.
+pub fn reply_target(msg: &Message) -> Option<MessageId> {
+    let target = msg.reply_to_message()?;
+    let topic_root =
+        msg.is_topic_message && msg.thread_id.is_some_and(|thread| thread.0 == target.id);
+    (!topic_root).then_some(target.id)
+}
+
+/// `"{작성자}: {변환된 본문}"`을 만들고, 작성자는 멘션으로, 원래 서식은 바뀐 위치에 맞춰 옮깁니다.
+pub fn build_repost(msg: &Message, links: &[LinkConversion]) -> Repost {
+    let text = message_text(msg).unwrap_or_default();
+    let (name, mention) = author(msg);
+
+    let prefix = format!("{}: ", name);
+    let shift = utf16_len(&prefix);
+    let mut entities = Vec::new();
+    if let Some(kind) = mention {
+        entities.push(MessageEntity::new(kind, 0, utf16_len(&name)));
+    }
+    entities.extend(
+        shift_entities(text, message_entities(msg), links)
+            .into_iter()
+            .map(|mut entity| {
+                entity.offset += shift;
+                entity
+            }),
+    );
+
+    Repost {
+        text: format!("{}{}", prefix, apply_conversions(text, links)),
+        entities,
+    }
+}
+
+fn author(msg: &Message) -> (String, Option<MessageEntityKind>) {
+    if let Some(user) = msg
+        .from
+        .as_ref()
+        .filter(|user| !user.is_anonymous() && !user.is_channel())
+    {
+        let name = user.username.clone().unwrap_or_else(|| user.full_name());
+        return (
+            name,
+            Some(MessageEntityKind::TextMention { user: user.clone() }),
+        );
+    }
+    let name = msg
+        .sender_chat
+        .as_ref()
+        .and_then(|chat| chat.title().map(str::to_string))
+        .unwrap_or_else(|| "Unknown".to_string());
+    (name, None)
+}
+
+/// 링크가 바뀐 만큼 엔티티 위치를 옮깁니다. 바뀐 링크 안에서 시작하거나 끝나는
+/// 엔티티는 바뀐 링크 전체로 늘이거나 줄이고, 길이가 0이 되면 버립니다.
+fn shift_entities(
+    text: &str,
+    entities: &[MessageEntity],
+    links: &[LinkConversion],
+) -> Vec<MessageEntity> {
+    // (원래 시작, 원래 끝, 새 시작, 새 끝) — UTF-16 단위
+    let mut spans = Vec::with_capacity(links.len());
+    let mut delta: isize = 0;
+    for link in links {
+        let old_start = utf16_len(&text[..link.span.start]);
+        let old_end = old_start + utf16_len(&text[link.span.clone()]);
+        let new_start = (old_start as isize + delta) as usize;
+        let new_end = new_start + utf16_len(&link.converted);
+        delta += new_end as isize - new_start as isize - (old_end - old_start) as isize;
+        spans.push((old_start, old_end, new_start, new_end));
+    }
+
+    let map = |pos: usize, is_end: bool| -> usize {
+        let mut delta: isize = 0;
+        for &(old_start, old_end, new_start, new_end) in &spans {
+            if pos <= old_start {
+                break;
+            }
+            if pos < old_end {
+                return if is_end { new_end } else { new_start };
+            }
+            delta = new_end as isize - old_end as isize;
+        }
+        (pos as isize + delta) as usize
+    };
+
+    entities
+        .iter()
+        .filter_map(|entity| {
+            let start = map(entity.offset, false);
+            let end = map(entity.offset + entity.length, true);
+            (end > start).then(|| MessageEntity::new(entity.kind.clone(), start, end - start))
+        })
+        .collect()
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::urlchanger::LinkRewriter;
+
+    fn shifted(text: &str, entities: &[MessageEntity]) -> (String, Vec<MessageEntity>) {
+        let links = LinkRewriter::default().rewrite(text);
+        (
+            apply_conversions(text, &links),
+            shift_entities(text, entities, &links),
+        )
+    }
+
+    fn slice(text: &str, entity: &MessageEntity) -> String {
+        let units: Vec<u16> = text.encode_utf16().collect();
+        String::from_utf16(&units[entity.offset..entity.offset + entity.length]).unwrap()
+    }
+
+    #[test]
+    fn test_entities_follow_changed_links() {
+        // "굵게"와 😀는 UTF-16 길이가 바이트 길이와 다릅니다.
+        let text = "😀 굵게 https://x.com/a/status/1?s=20 스포일러";
+        let units = |s: &str| utf16_len(s);
+        let bold = MessageEntity::bold(units("😀 "), units("굵게"));
+        let url = MessageEntity::new(
+            MessageEntityKind::Url,
+            units("😀 굵게 "),
+            units("https://x.com/a/status/1?s=20"),
+        );
+        let spoiler = MessageEntity::spoiler(
+            units("😀 굵게 https://x.com/a/status/1?s=20 "),
+            units("스포일러"),
+        );
+
+        let (new_text, entities) = shifted(text, &[bold, url, spoiler]);
+        assert_eq!(
+            new_text,
+            "😀 굵게 https://fxtwitter.com/a/status/1 스포일러"
+        );
+        let texts: Vec<String> = entities
+            .iter()
+            .map(|entity| slice(&new_text, entity))
+            .collect();
+        assert_eq!(
+            texts,
+            ["굵게", "https://fxtwitter.com/a/status/1", "스포일러"]
+        );
+    }
+
+    #[test]
+    fn test_entities_partly_inside_a_link_cover_the_new_link() {
+        let text = "보세요 https://youtu.be/a?si=b 끝";
+        // 링크 중간에서 끝나는 굵게 표시
+        let bold = MessageEntity::bold(0, utf16_len("보세요 https://you"));
+        let (new_text, entities) = shifted(text, &[bold]);
+        assert_eq!(slice(&new_text, &entities[0]), "보세요 https://youtu.be/a");
+    }
+}
@@ -0,0 +1,509 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{Client, Method, StatusCode, redirect};
+use url::Url;
+
+use crate::util::{USER_AGENT, env_number};
+
+use super::link_utils::host_matches;
+
+const DEFAULT_HOSTS: &[&str] = &[
+    "t.co",
+    "bit.ly",
+    "youtu.be",
+    "vm.tiktok.com",
+    "vt.tiktok.com",
+    "naver.me",
+    "buff.ly",
+    "tinyurl.com",
+    "goo.gl",
+    "han.gl",
+];
+const CACHE_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct ResolverConfig {
+    /// 이 호스트(하위 도메인 포함)의 링크만 따라갑니다. 다른 호스트에 도착하면 멈춥니다.
+    pub hosts: Vec<String>,
+    pub max_hops: usize,
+    /// 링크 하나를 끝까지 따라가는 데 쓸 수 있는 시간.
+    pub timeout: Duration,
+    /// `<meta refresh>`를 찾으려고 읽을 최대 본문 크기.
+    pub max_body_bytes: usize,
+    pub cache_ttl: Duration,
+    /// 사설·루프백 주소로 가는 요청을 막습니다. 테스트에서만 끕니다.
+    pub block_private: bool,
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self {
+            hosts: DEFAULT_HOSTS.iter().map(|host| host.to_string()).collect(),
+            max_hops: 5,
+            timeout: Duration::from_secs(5),
+            max_body_bytes: 64 * 1024,
+            cache_ttl: Duration::from_secs(24 * 60 * 60),
+            block_private: true,
+        }
+    }
+}
+
+impl ResolverConfig {
+    /// `PLANABOT_SHORTLINK_RESOLVE`가 켜져 있을 때만 설정을 돌려줍니다.
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("PLANABOT_SHORTLINK_RESOLVE")
+            .map(|raw| raw == "1" || raw.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+
+        let mut config = Self::default();
+        if let Ok(raw) = std::env::var("PLANABOT_SHORTLINK_HOSTS") {
+            let hosts: Vec<String> = raw
+                .split(|ch: char| ch == ',' || ch == ';' || ch.is_whitespace())
+                .map(|host| host.trim().to_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect();
+            if !hosts.is_empty() {
+                config.hosts = hosts;
+            }
+        }
+        if let Some(hops) = env_number("PLANABOT_SHORTLINK_MAX_HOPS") {
+            config.max_hops = hops.max(1);
+        }
+        if let Some(secs) = env_number("PLANABOT_SHORTLINK_TIMEOUT_SECS") {
+            config.timeout = Duration::from_secs(secs.max(1) as u64);
+        }
+        Some(config)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ResolveError {
+    /// 허용되지 않는 스킴이거나 사설 주소로 가는 링크입니다.
+    Blocked(String),
+    TooManyHops,
+    Timeout,
+    Request(String),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Blocked(url) => write!(f, "허용되지 않는 주소: {}", url),
+            ResolveError::TooManyHops => write!(f, "리다이렉트가 너무 많습니다"),
+            ResolveError::Timeout => write!(f, "요청 시간 초과"),
+            ResolveError::Request(err) => write!(f, "요청 실패: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// 단축 링크를 따라가 실제 주소를 알아냅니다.
+#[derive(Debug, Clone)]
+pub struct ShortLinkResolver {
+    client: Client,
+    config: ResolverConfig,
+    cache: Arc<Mutex<HashMap<String, (Instant, Url)>>>,
+}
+
+impl ShortLinkResolver {
+    pub fn new(config: ResolverConfig) -> Self {
+        let client = http_client(config.timeout, config.block_private);
+        Self {
+            client,
+            config,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn is_short_link(&self, url: &Url) -> bool {
+        host_matches(url, &self.config.hosts)
+    }
+
+    /// 단축 링크면 최종 주소를, 아니거나 따라가지 못하면 `None`을 돌려줍니다.
+    pub async fn resolve(&self, url: &Url) -> Option<Url> {
+        if !self.is_short_link(url) {
+            return None;
+        }
+        if let Some(cached) = self.cached(url) {
+            return Some(cached);
+        }
+
+        let result = tokio::time::timeout(self.config.timeout, self.follow(url.clone()))
+            .await
+            .unwrap_or(Err(ResolveError::Timeout));
+        match result {
+            Ok(target) if target != *url => {
+                self.store(url, &target);
+                Some(target)
+            }
+            Ok(_) => None,
+            Err(err) => {
+                warn!("단축 링크 확인 실패 ({}): {}", url, err);
+                None
+            }
+        }
+    }
+
+    async fn follow(&self, mut url: Url) -> Result<Url, ResolveError> {
+        for _ in 0..self.config.max_hops {
+            if !self.is_short_link(&url) {
+                return Ok(url);
+            }
+            self.check_target(&url)?;
+            match self.next_hop(&url).await? {
+                Some(next) => {
+                    debug!("단축 링크 이동: {} → {}", url, next);
+                    url = next;
+                }
+                None => return Ok(url),
+            }
+        }
+        if self.is_short_link(&url) {
+            Err(ResolveError::TooManyHops)
+        } else {
+            Ok(url)
+        }
+    }
+
+    /// HEAD로 리다이렉트를 확인하고, 안 되면 GET으로 본문 앞부분의 `<meta refresh>`까지 봅니다.
+    async fn next_hop(&self, url: &Url) -> Result<Option<Url>, ResolveError> {
+        let head = self
+            .client
+            .request(Method::HEAD, url.clone())
+            .send()
+            .await
+            .map_err(|err| ResolveError::Request(err.to_string()))?;
+        if let Some(next) = redirect_target(url, &head) {
+            return Ok(Some(next));
+        }
+
+        let mut response = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|err| ResolveError::Request(err.to_string()))?;
+        if let Some(next) = redirect_target(url, &response) {
+            return Ok(Some(next));
+        }
+        if response.status() != StatusCode::OK {
+            return Ok(None);
+        }
+
+        let mut body = Vec::new();
+        while body.len() < self.config.max_body_bytes {
+            match response.chunk().await {
+                Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+                Ok(None) => break,
+                Err(err) => return Err(ResolveError::Request(err.to_string())),
+            }
+        }
+        body.truncate(self.config.max_body_bytes);
+        Ok(meta_refresh_target(url, &String::from_utf8_lossy(&body)))
+    }
+
+    fn check_target(&self, url: &Url) -> Result<(), ResolveError> {
+        ensure_public_target(url, self.config.block_private)
+    }
+
+    fn cached(&self, url: &Url) -> Option<Url> {
+        let cache = self.cache.lock().ok()?;
+        let (at, target) = cache.get(url.as_str())?;
+        (at.elapsed() < self.config.cache_ttl).then(|| target.clone())
+    }
+
+    fn store(&self, url: &Url, target: &Url) {
+        let Ok(mut cache) = self.cache.lock() else {
+            return;
+        };
+        if cache.len() >= CACHE_CAPACITY {
+            let ttl = self.config.cache_ttl;
+            cache.retain(|_, (at, _)| at.elapsed() < ttl);
+            if cache.len() >= CACHE_CAPACITY {
+                cache.clear();
+            }
+        }
+        cache.insert(url.to_string(), (Instant::now(), target.clone()));
+    }
+}
+
+/// 리다이렉트를 따라가지 않는 요청용 클라이언트. `block_private`이면 [`PublicDns`]로 이름을 풉니다.
+pub(super) fn http_client(timeout: Duration, block_private: bool) -> Client {
+    let mut builder = Client::builder()
+        .redirect(redirect::Policy::none())
+        .timeout(timeout)
+        .user_agent(USER_AGENT);
+    if block_private {
+        builder = builder.dns_resolver(Arc::new(PublicDns));
+    }
+    builder.build().unwrap_or_else(|_| Client::new())
+}
+
+/// 사설·루프백 주소를 걸러 내는 DNS 조회.
+///
+/// 미리 확인한 뒤 요청할 때 다시 조회하면 그 사이에 주소가 바뀔 수 있으므로(DNS 리바인딩),
+/// 실제로 연결할 주소를 고르는 이 단계에서 막습니다.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PublicDns;
+
+impl Resolve for PublicDns {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let lookup = host.clone();
+            let addrs = tokio::task::spawn_blocking(move || {
+                (lookup.as_str(), 0)
+                    .to_socket_addrs()
+                    .map(|addrs| addrs.collect::<Vec<_>>())
+            })
+            .await??;
+            let public: Vec<_> = addrs
+                .into_iter()
+                .filter(|addr| !is_blocked_ip(addr.ip()))
+                .collect();
+            if public.is_empty() {
+                return Err(ResolveError::Blocked(host).into());
+            }
+            Ok(Box::new(public.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// `file://` 같은 스킴과, `block_private`이면 사설·루프백 IP를 직접 적은 주소를 막습니다.
+/// 호스트 이름은 연결할 때 [`PublicDns`]가 확인합니다.
+pub(super) fn ensure_public_target(url: &Url, block_private: bool) -> Result<(), ResolveError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(ResolveError::Blocked(url.to_string()));
+    }
+    if !block_private {
+        return Ok(());
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| ResolveError::Blocked(url.to_string()))?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    match host.parse::<IpAddr>() {
+        Ok(ip) if is_blocked_ip(ip) => Err(ResolveError::Blocked(url.to_string())),
+        _ => Ok(()),
+    }
+}
+
+pub(super) fn redirect_target(base: &Url, response: &reqwest::Response) -> Option<Url> {
+    if !response.status().is_redirection() {
+        return None;
+    }
+    let location = response
+        .headers()
+        .get(reqwest::header::LOCATION)?
+        .to_str()
+        .ok()?;
+    base.join(location).ok()
+}
+
+fn meta_refresh_target(base: &Url, body: &str) -> Option<Url> {
+    static META_REFRESH: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r#"(?i)<meta[^>]+http-equiv=["']?refresh["']?[^>]*content=["']?\s*\d+\s*;\s*url=([^"'>\s]+)"#)
+            .unwrap()
+    });
+    let target = META_REFRESH.captures(body)?.get(1)?.as_str();
+    base.join(&target.replace("&amp;", "&")).ok()
+}
+
+fn is_blocked_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                // 100.64.0.0/10 (CGNAT)
+                || (a == 100 && (64..128).contains(&b))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_blocked_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // fc00::/7 (고유 로컬), fe80::/10 (링크 로컬)
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Redirects, Routes, mock_server_with_redirects};
+
+    fn local_resolver(base: &str) -> ShortLinkResolver {
+        let host = Url::parse(base).unwrap().host_str().unwrap().to_string();
+        ShortLinkResolver::new(ResolverConfig {
+            hosts: vec![host],
+            block_private: false,
+            ..ResolverConfig::default()
+        })
+    }
+
+    fn server(redirects: &[(&str, &str)], routes: &[(&str, &str)]) -> (String, Redirects) {
+        let redirects: Redirects = Arc::new(Mutex::new(
+            redirects
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+        ));
+        let routes: Routes = Arc::new(Mutex::new(
+            routes
+                .iter()
+                .map(|(path, body)| (path.to_string(), body.as_bytes().to_vec()))
+                .collect(),
+        ));
+        (
+            mock_server_with_redirects(routes, redirects.clone()),
+            redirects,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_follows_redirect_chain_and_caches() {
+        let (base, redirects) = server(
+            &[
+                ("/a", "/b"),
+                ("/b", "https://www.youtube.com/watch?v=abc&si=tracking"),
+            ],
+            &[],
+        );
+        let resolver = local_resolver(&base);
+        let short = Url::parse(&format!("{}/a", base)).unwrap();
+
+        let target = resolver.resolve(&short).await.unwrap();
+        assert_eq!(
+            target.as_str(),
+            "https://www.youtube.com/watch?v=abc&si=tracking"
+        );
+
+        // 서버가 바뀌어도 캐시된 결과를 씁니다.
+        redirects.lock().unwrap().clear();
+        assert_eq!(resolver.resolve(&short).await.unwrap(), target);
+    }
+
+    #[tokio::test]
+    async fn test_follows_meta_refresh() {
+        let (base, _) = server(
+            &[],
+            &[(
+                "/m",
+                r#"<html><meta http-equiv="refresh" content="0;URL=https://example.com/x?a=1&amp;b=2"></html>"#,
+            )],
+        );
+        let resolver = local_resolver(&base);
+        let target = resolver
+            .resolve(&Url::parse(&format!("{}/m", base)).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(target.as_str(), "https://example.com/x?a=1&b=2");
+    }
+
+    #[tokio::test]
+    async fn test_stops_at_hop_limit() {
+        let (base, _) = server(&[("/loop", "/loop")], &[]);
+        let resolver = local_resolver(&base);
+        let url = Url::parse(&format!("{}/loop", base)).unwrap();
+        assert_eq!(
+            resolver.follow(url.clone()).await,
+            Err(ResolveError::TooManyHops)
+        );
+        assert!(resolver.resolve(&url).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_blocks_private_targets_and_other_schemes() {
+        let (base, _) = server(&[("/a", "https://example.com/")], &[]);
+        let host = Url::parse(&base).unwrap().host_str().unwrap().to_string();
+        let resolver = ShortLinkResolver::new(ResolverConfig {
+            hosts: vec![host, "localhost".to_string()],
+            ..ResolverConfig::default()
+        });
+        let url = Url::parse(&format!("{}/a", base)).unwrap();
+        assert!(matches!(
+            resolver.follow(url).await,
+            Err(ResolveError::Blocked(_))
+        ));
+        assert!(matches!(
+            resolver.check_target(&Url::parse("file:///etc/passwd").unwrap()),
+            Err(ResolveError::Blocked(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_public_dns_refuses_names_that_resolve_to_private_addresses() {
+        use std::str::FromStr;
+
+        // 미리 확인하는 단계를 건너뛰어도 연결할 때 막힙니다.
+        assert!(
+            PublicDns
+                .resolve(Name::from_str("localhost").unwrap())
+                .await
+                .is_err()
+        );
+
+        let (base, _) = server(&[], &[("/secret", "internal")]);
+        let port = Url::parse(&base).unwrap().port().unwrap();
+        let client = http_client(Duration::from_secs(5), true);
+        assert!(
+            client
+                .get(format!("http://localhost:{}/secret", port))
+                .send()
+                .await
+                .is_err()
+        );
+        let open = http_client(Duration::from_secs(5), false);
+        assert!(
+            open.get(format!("http://localhost:{}/secret", port))
+                .send()
+                .await
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_blocked_ip_ranges() {
+        for ip in [
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.0.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(is_blocked_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in ["8.8.8.8", "104.244.42.1", "2606:4700::1111"] {
+            assert!(!is_blocked_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! 메시지 안의 링크 앞에 붙이는 표시.
+//!
+//! - `!https://...` : 이 링크는 변환하지 않습니다.
+//! - `.https://...` : 변환하되 미리보기를 끕니다.
+//! - `^https://...` : 변환하고 이 링크의 미리보기를 본문 위에 크게 보여줍니다.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMarker {
+    Plain,
+    Keep,
+    NoPreview,
+    PreviewAbove,
+}
+
+impl LinkMarker {
+    fn from_prefix(prefix: &str) -> Self {
+        match prefix {
+            "!" => Self::Keep,
+            "." => Self::NoPreview,
+            "^" => Self::PreviewAbove,
+            _ => Self::Plain,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarkedLink<'a> {
+    pub marker: LinkMarker,
+    /// 표시까지 포함한 본문 속 문자열. 변환할 때 이 부분을 통째로 바꿉니다.
+    pub raw: &'a str,
+    /// 본문에서 `raw`가 시작하는 바이트 위치.
+    pub start: usize,
+    pub url: &'a str,
+}
+
+/// 메시지의 링크와 그 앞의 표시를 본문 순서대로 찾습니다.
+pub fn parse_links(text: &str) -> Vec<MarkedLink<'_>> {
+    static LINK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"([.!^]?)(https?://\S+)").unwrap());
+    LINK_RE
+        .captures_iter(text)
+        .filter_map(|cap| {
+            let whole = cap.get(0)?;
+            let raw = whole.as_str();
+            let prefix = cap.get(1).map_or("", |m| m.as_str());
+            let url = cap.get(2)?.as_str();
+            Some(MarkedLink {
+                marker: LinkMarker::from_prefix(prefix),
+                raw,
+                start: whole.start(),
+                url,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_links_reads_markers() {
+        let links =
+            parse_links("a https://a.com !https://b.com .https://c.com ^https://d.com/x?y=1");
+        let markers: Vec<_> = links.iter().map(|link| link.marker).collect();
+        assert_eq!(
+            markers,
+            [
+                LinkMarker::Plain,
+                LinkMarker::Keep,
+                LinkMarker::NoPreview,
+                LinkMarker::PreviewAbove
+            ]
+        );
+        assert_eq!(links[3].raw, "^https://d.com/x?y=1");
+        assert_eq!(links[3].url, "https://d.com/x?y=1");
+        assert_eq!(links[1].start, 16);
+    }
+}
@@ -0,0 +1,217 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use url::Url;
+
+use super::link_utils::{host_matches, retain_query};
+
+/// 기본 추적 파라미터 목록. `PLANABOT_TRACKING_RULES_PATH`로 최신 목록 파일을 지정할 수 있습니다.
+const DEFAULT_TRACKING_RULES: &str = include_str!("tracking_rules.json");
+
+#[derive(Debug, Deserialize)]
+struct TrackingConfig {
+    #[serde(default)]
+    global: Vec<String>,
+    #[serde(default)]
+    domains: Vec<DomainConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DomainConfig {
+    hosts: Vec<String>,
+    #[serde(default)]
+    strip: Vec<String>,
+    /// 꼭 필요한 파라미터. 다른 규칙에 걸려도 지우지 않습니다.
+    #[serde(default)]
+    allow: Vec<String>,
+}
+
+/// 파라미터 이름 패턴. 끝의 `*`는 접두사 일치입니다. (`utm_*`)
+#[derive(Debug, Clone)]
+enum ParamPattern {
+    Exact(String),
+    Prefix(String),
+}
+
+impl ParamPattern {
+    fn parse(raw: &str) -> Result<Self> {
+        let raw = raw.trim().to_lowercase();
+        match raw.strip_suffix('*') {
+            Some("") => Err(anyhow!("파라미터 패턴 `*`만으로는 쓸 수 없습니다")),
+            Some(prefix) => Ok(Self::Prefix(prefix.to_string())),
+            None if raw.is_empty() => Err(anyhow!("빈 파라미터 패턴이 있습니다")),
+            None => Ok(Self::Exact(raw)),
+        }
+    }
+
+    fn matches(&self, key: &str) -> bool {
+        match self {
+            Self::Exact(name) => key == name,
+            Self::Prefix(prefix) => key.starts_with(prefix.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DomainRule {
+    hosts: Vec<String>,
+    strip: Vec<ParamPattern>,
+    allow: Vec<ParamPattern>,
+}
+
+/// ClearURLs처럼 모든 링크에서 추적용 쿼리 파라미터를 지우는 규칙.
+#[derive(Debug, Clone)]
+pub struct TrackingRules {
+    global: Vec<ParamPattern>,
+    domains: Vec<DomainRule>,
+}
+
+impl Default for TrackingRules {
+    fn default() -> Self {
+        Self::from_json(DEFAULT_TRACKING_RULES).expect("bundled tracking rules should be valid")
+    }
+}
+
+impl TrackingRules {
+    pub fn from_json(raw: &str) -> Result<Self> {
+        let config: TrackingConfig =
+            serde_json::from_str(raw).context("추적 파라미터 규칙 형식이 올바르지 않습니다")?;
+        let parse_all = |patterns: &[String]| -> Result<Vec<ParamPattern>> {
+            patterns
+                .iter()
+                .map(|raw| ParamPattern::parse(raw))
+                .collect()
+        };
+        let domains = config
+            .domains
+            .iter()
+            .map(|domain| {
+                if domain.hosts.is_empty() {
+                    return Err(anyhow!("hosts가 없는 도메인 규칙이 있습니다"));
+                }
+                Ok(DomainRule {
+                    hosts: domain
+                        .hosts
+                        .iter()
+                        .map(|host| host.to_lowercase())
+                        .collect(),
+                    strip: parse_all(&domain.strip)?,
+                    allow: parse_all(&domain.allow)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            global: parse_all(&config.global)?,
+            domains,
+        })
+    }
+
+    /// `PLANABOT_TRACKING_RULES_PATH`가 있으면 그 파일을, 없으면 기본 목록을 씁니다.
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("PLANABOT_TRACKING_RULES_PATH") {
+            Ok(path) if !path.trim().is_empty() => Self::from_file(Path::new(path.trim())),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    fn from_file(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path).with_context(|| {
+            format!(
+                "추적 파라미터 규칙 파일을 읽지 못했습니다: {}",
+                path.display()
+            )
+        })?;
+        Self::from_json(&raw)
+            .with_context(|| format!("추적 파라미터 규칙 파일 오류: {}", path.display()))
+    }
+
+    /// 추적 파라미터를 지웁니다. 바뀐 것이 있으면 `true`.
+    pub fn clean(&self, url: &mut Url) -> bool {
+        let domains: Vec<&DomainRule> = self
+            .domains
+            .iter()
+            .filter(|domain| host_matches(url, &domain.hosts))
+            .collect();
+        retain_query(url, |key| {
+            let key = key.to_lowercase();
+            if domains
+                .iter()
+                .any(|d| d.allow.iter().any(|p| p.matches(&key)))
+            {
+                return true;
+            }
+            let tracked = self.global.iter().any(|p| p.matches(&key))
+                || domains
+                    .iter()
+                    .any(|d| d.strip.iter().any(|p| p.matches(&key)));
+            !tracked
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(raw: &str) -> String {
+        let mut url = Url::parse(raw).unwrap();
+        TrackingRules::default().clean(&mut url);
+        url.to_string()
+    }
+
+    #[test]
+    fn test_strips_global_tracking_params_on_any_site() {
+        assert_eq!(
+            clean("https://news.example.com/a?id=3&utm_source=tw&utm_medium=x&fbclid=abc&gclid=1"),
+            "https://news.example.com/a?id=3"
+        );
+        assert_eq!(
+            clean("https://blog.example/?mc_eid=1&ref_src=twsrc"),
+            "https://blog.example/"
+        );
+    }
+
+    #[test]
+    fn test_domain_rules_strip_and_allow() {
+        assert_eq!(
+            clean("https://www.youtube.com/watch?v=abc&t=42&list=PL1&si=x&feature=share&pp=yg"),
+            "https://www.youtube.com/watch?v=abc&t=42&list=PL1"
+        );
+        // `t`는 X에서는 추적용이지만 다른 사이트에서는 지우지 않습니다.
+        assert_eq!(
+            clean("https://x.com/a/status/1?s=20&t=abc"),
+            "https://x.com/a/status/1"
+        );
+        assert_eq!(
+            clean("https://example.com/v?t=10"),
+            "https://example.com/v?t=10"
+        );
+    }
+
+    #[test]
+    fn test_untouched_query_keeps_original_encoding() {
+        let mut url = Url::parse("https://example.com/search?q=a%20b&lang=ko").unwrap();
+        assert!(!TrackingRules::default().clean(&mut url));
+        assert_eq!(url.as_str(), "https://example.com/search?q=a%20b&lang=ko");
+    }
+
+    #[test]
+    fn test_empty_query_is_not_reported_as_cleaned() {
+        let mut url = Url::parse("https://example.com/page?").unwrap();
+        assert!(!TrackingRules::default().clean(&mut url));
+        assert_eq!(url.as_str(), "https://example.com/page?");
+    }
+
+    #[test]
+    fn test_rejects_invalid_rules() {
+        assert!(TrackingRules::from_json(r#"{"global": ["*"]}"#).is_err());
+        assert!(
+            TrackingRules::from_json(r#"{"domains": [{"hosts": [], "strip": ["a"]}]}"#).is_err()
+        );
+        let rules = TrackingRules::from_json(r#"{"global": ["track_*"]}"#).unwrap();
+        let mut url = Url::parse("https://a.com/?track_id=1&utm_source=x").unwrap();
+        assert!(rules.clean(&mut url));
+        assert_eq!(url.as_str(), "https://a.com/?utm_source=x");
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use teloxide::types::{
+    ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, MessageId, ThreadId, UserId,
+};
+
+use super::repost::{Repost, build_repost, reply_target};
+
+pub const UNDO_CALLBACK: &str = "link_undo";
+const DEFAULT_WINDOW_SECS: u64 = 600;
+
+/// 다시 올리기 전의 원본. 복원할 때 이대로 다시 보냅니다.
+#[derive(Debug, Clone)]
+pub struct UndoEntry {
+    pub author: Option<UserId>,
+    pub original: Repost,
+    pub reply_to: Option<MessageId>,
+    pub thread_id: Option<ThreadId>,
+    /// 캡션이 있는 미디어였으면 `true`. 다시 올린 메시지를 복사해 캡션만 되돌립니다.
+    pub media: bool,
+    expires_at: Instant,
+}
+
+/// 관리자 권한으로 다시 올린 메시지의 원본을 잠시 기억합니다. (메모리에만 보관)
+#[derive(Clone)]
+pub struct UndoStore {
+    window: Option<Duration>,
+    entries: Arc<Mutex<HashMap<(ChatId, MessageId), UndoEntry>>>,
+}
+
+impl UndoStore {
+    pub fn new(window: Option<Duration>) -> Self {
+        Self {
+            window,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// `PLANABOT_LINK_UNDO_SECS` (기본 600초, 0이면 복원 버튼을 달지 않음)
+    pub fn from_env() -> Self {
+        let secs = std::env::var("PLANABOT_LINK_UNDO_SECS")
+            .ok()
+            .and_then(|raw| raw.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_WINDOW_SECS);
+        Self::new((secs > 0).then(|| Duration::from_secs(secs)))
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.window.is_some()
+    }
+
+    pub fn keyboard() -> InlineKeyboardMarkup {
+        InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+            "↩ 원본 복원",
+            UNDO_CALLBACK,
+        )]])
+    }
+
+    /// `repost_id`로 다시 올린 `original` 메시지를 기억합니다.
+    pub fn remember(&self, chat_id: ChatId, repost_id: MessageId, original: &Message) {
+        let Some(window) = self.window else {
+            return;
+        };
+        let entry = UndoEntry {
+            author: original.from.as_ref().map(|user| user.id),
+            original: build_repost(original, &[]),
+            reply_to: reply_target(original),
+            thread_id: original.thread_id,
+            media: original.text().is_none(),
+            expires_at: Instant::now() + window,
+        };
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        entries.retain(|_, entry| entry.expires_at > now);
+        entries.insert((chat_id, repost_id), entry);
+    }
+
+    pub fn get(&self, chat_id: ChatId, repost_id: MessageId) -> Option<UndoEntry> {
+        let entries = self.entries.lock().ok()?;
+        entries
+            .get(&(chat_id, repost_id))
+            .filter(|entry| entry.expires_at > Instant::now())
+            .cloned()
+    }
+
+    /// 복원을 한 번만 하도록 꺼내면서 지웁니다.
+    pub fn take(&self, chat_id: ChatId, repost_id: MessageId) -> Option<UndoEntry> {
+        let mut entries = self.entries.lock().ok()?;
+        entries
+            .remove(&(chat_id, repost_id))
+            .filter(|entry| entry.expires_at > Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(text: &str) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "message_id": 10,
+            "date": 0,
+            "chat": {"id": -100, "type": "supergroup", "title": "t"},
+            "from": {"id": 7, "is_bot": false, "first_name": "A", "username": "alice"},
+            "text": text,
+            "entities": [{"type": "bold", "offset": 0, "length": 2}]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_remembers_original_with_author_and_entities() {
+        let store = UndoStore::new(Some(Duration::from_secs(60)));
+        let chat = ChatId(-100);
+        store.remember(
+            chat,
+            MessageId(11),
+            &message("굵게 https://youtu.be/a?si=b"),
+        );
+
+        let entry = store.get(chat, MessageId(11)).unwrap();
+        assert_eq!(entry.author, Some(UserId(7)));
+        assert_eq!(entry.original.text, "alice: 굵게 https://youtu.be/a?si=b");
+        assert_eq!(entry.original.entities.len(), 2);
+        assert_eq!(entry.original.entities[1].offset, "alice: ".len());
+        assert!(!entry.media);
+
+        assert!(store.take(chat, MessageId(11)).is_some());
+        assert!(store.take(chat, MessageId(11)).is_none());
+    }
+
+    #[test]
+    fn test_expired_or_disabled_entries_are_not_returned() {
+        let store = UndoStore::new(Some(Duration::ZERO));
+        store.remember(ChatId(1), MessageId(2), &message("x"));
+        assert!(store.get(ChatId(1), MessageId(2)).is_none());
+
+        let disabled = UndoStore::new(None);
+        assert!(!disabled.enabled());
+        disabled.remember(ChatId(1), MessageId(2), &message("x"));
+        assert!(disabled.get(ChatId(1), MessageId(2)).is_none());
+    }
+}
@@ -0,0 +1,24 @@
+//! 여러 모듈이 함께 쓰는 작은 도구.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 트레이트 객체로 쓰는 트레이트의 비동기 메서드가 돌려주는 future.
+pub(crate) type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// 외부 서버에 보내는 모든 HTTP 요청의 User-Agent.
+pub(crate) const USER_AGENT: &str = concat!("planabot/", env!("CARGO_PKG_VERSION"));
+
+/// 환경 변수 `key`를 숫자로 읽습니다. 없거나 숫자가 아니면 `None`입니다.
+pub(crate) fn env_number(key: &str) -> Option<usize> {
+    std::env::var(key).ok()?.trim().parse().ok()
+}
+
+/// 현재 Unix 시각(초).
+pub(crate) fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
@@ -3,19 +3,29 @@ import { AIMessage, HumanMessage, SystemMessage } from "@langchain/core/messages
 import type { Settings } from "../config/settings.js";
 import { createChatModel } from "../integrations/gemini/chat.js";
 import { createGoogleSearchTool } from "../integrations/googleSearch/retrievalTool.js";
-import { appendUserMemory, loadUserMemory } from "../memory/userMemoryStore.js";
+import {
+  appendUserMemory,
+  loadUserMemory,
+  type StoredChatMessage
+} from "../memory/userMemoryStore.js";
 
 export async function answerWithWebSearch(params: {
   question: string;
   settings: Settings;
   userId?: string;
+  /** 브리지(planabot)가 넘긴 대화 기록. 있으면 로컬 메모리 파일을 읽거나 쓰지 않습니다. */
+  history?: StoredChatMessage[] | null;
 }): Promise<string> {
   const tool = createGoogleSearchTool();
   const llm = createChatModel(params.settings).bindTools([tool]);
 
   const userId = params.userId ?? "default";
-  const history =
-    params.settings.memoryEnabled && params.settings.memoryMaxMessages > 0
+  const bridged = params.history != null;
+  const useLocalMemory =
+    !bridged && params.settings.memoryEnabled && params.settings.memoryMaxMessages > 0;
+  const history = bridged
+    ? (params.history ?? [])
+    : useLocalMemory
       ? await loadUserMemory({
           memoryDir: params.settings.memoryDir,
           userId,
@@ -33,7 +43,7 @@ export async function answerWithWebSearch(params: {
 
   const answer = String(result.content);
 
-  if (params.settings.memoryEnabled && params.settings.memoryMaxMessages > 0) {
+  if (useLocalMemory) {
     await appendUserMemory({
       memoryDir: params.settings.memoryDir,
       userId,
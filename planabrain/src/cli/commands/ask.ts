@@ -1,5 +1,6 @@
 import type { Settings } from "../../config/settings.js";
 import { answerWithWebSearch } from "../../chat/webSearchAnswer.js";
+import { readBridgedHistory } from "../../memory/bridgeHistory.js";
 
 export async function runAskCommand(args: string[], settings: Settings): Promise<void> {
   const question = args.join(" ").trim();
@@ -8,6 +9,7 @@ export async function runAskCommand(args: string[], settings: Settings): Promise
   }
 
   const userId = process.env.PLANABRAIN_USER_ID ?? "cli";
-  const answer = await answerWithWebSearch({ question, settings, userId });
+  const history = await readBridgedHistory();
+  const answer = await answerWithWebSearch({ question, settings, userId, history });
   process.stdout.write(`${answer}\n`);
 }
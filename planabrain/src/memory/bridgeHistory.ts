@@ -0,0 +1,32 @@
+import type { StoredChatMessage } from "./userMemoryStore.js";
+
+/**
+ * planabot(Rust)이 대화 기록을 관리할 때는 `PLANABRAIN_HISTORY_SOURCE=stdin`으로 실행하고
+ * `{ "messages": [...] }` JSON을 표준 입력으로 넘깁니다.
+ * 브리지가 없으면 null을 돌려주고, 호출부는 로컬 메모리 파일을 사용합니다.
+ */
+export async function readBridgedHistory(): Promise<StoredChatMessage[] | null> {
+  if (process.env.PLANABRAIN_HISTORY_SOURCE !== "stdin") return null;
+
+  const chunks: Buffer[] = [];
+  for await (const chunk of process.stdin) {
+    chunks.push(typeof chunk === "string" ? Buffer.from(chunk) : chunk);
+  }
+  const raw = Buffer.concat(chunks).toString("utf-8").trim();
+  if (!raw) return [];
+
+  const parsed = JSON.parse(raw) as { messages?: unknown };
+  const messages = Array.isArray(parsed.messages) ? parsed.messages : [];
+
+  return messages
+    .filter((m) => Boolean(m) && typeof (m as { content?: unknown }).content === "string")
+    .map((m) => {
+      const roleRaw = (m as { role?: unknown }).role;
+      const role: StoredChatMessage["role"] = roleRaw === "ai" ? "ai" : "human";
+      const content = String((m as { content?: unknown }).content ?? "");
+      const atRaw = (m as { at?: unknown }).at;
+      const at = typeof atRaw === "number" ? atRaw : Date.now();
+      return { role, content, at };
+    })
+    .filter((m) => m.content.trim().length > 0);
+}